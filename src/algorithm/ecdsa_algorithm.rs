@@ -0,0 +1,41 @@
+use failure::Error;
+
+use super::Algorithm;
+
+/// Hash algorithms used to compute ECDSA signatures
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[allow(non_camel_case_types)]
+#[repr(u8)]
+pub enum ECDSAAlgorithm {
+    /// ecdsa-sha256
+    EC_ECDSA_SHA256 = Algorithm::EC_ECDSA_SHA256 as u8,
+
+    /// ecdsa-sha384
+    EC_ECDSA_SHA384 = Algorithm::EC_ECDSA_SHA384 as u8,
+
+    /// ecdsa-sha512
+    EC_ECDSA_SHA512 = Algorithm::EC_ECDSA_SHA512 as u8,
+}
+
+impl ECDSAAlgorithm {
+    /// Convert from an `Algorithm` into an `ECDSAAlgorithm`
+    pub fn from_algorithm(algorithm: Algorithm) -> Result<Self, Error> {
+        Ok(match algorithm {
+            Algorithm::EC_ECDSA_SHA256 => ECDSAAlgorithm::EC_ECDSA_SHA256,
+            Algorithm::EC_ECDSA_SHA384 => ECDSAAlgorithm::EC_ECDSA_SHA384,
+            Algorithm::EC_ECDSA_SHA512 => ECDSAAlgorithm::EC_ECDSA_SHA512,
+            _ => bail!("unsupported/bad ECDSA algorithm: {:?}", algorithm),
+        })
+    }
+
+    /// Size of the digests produced by this algorithm's hash function
+    pub fn digest_len(self) -> usize {
+        match self {
+            ECDSAAlgorithm::EC_ECDSA_SHA256 => 32,
+            ECDSAAlgorithm::EC_ECDSA_SHA384 => 48,
+            ECDSAAlgorithm::EC_ECDSA_SHA512 => 64,
+        }
+    }
+}
+
+impl_algorithm!(ECDSAAlgorithm);
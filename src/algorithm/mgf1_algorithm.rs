@@ -0,0 +1,36 @@
+use failure::Error;
+
+use super::Algorithm;
+
+/// Hash algorithms used by the MGF1 mask generation function (e.g. for RSASSA-PSS)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[allow(non_camel_case_types)]
+#[repr(u8)]
+pub enum MGF1Algorithm {
+    /// mgf1-sha1
+    MGF1_SHA1 = Algorithm::MGF1_SHA1 as u8,
+
+    /// mgf1-sha256
+    MGF1_SHA256 = Algorithm::MGF1_SHA256 as u8,
+
+    /// mgf1-sha384
+    MGF1_SHA384 = Algorithm::MGF1_SHA384 as u8,
+
+    /// mgf1-sha512
+    MGF1_SHA512 = Algorithm::MGF1_SHA512 as u8,
+}
+
+impl MGF1Algorithm {
+    /// Convert from an `Algorithm` into an `MGF1Algorithm`
+    pub fn from_algorithm(algorithm: Algorithm) -> Result<Self, Error> {
+        Ok(match algorithm {
+            Algorithm::MGF1_SHA1 => MGF1Algorithm::MGF1_SHA1,
+            Algorithm::MGF1_SHA256 => MGF1Algorithm::MGF1_SHA256,
+            Algorithm::MGF1_SHA384 => MGF1Algorithm::MGF1_SHA384,
+            Algorithm::MGF1_SHA512 => MGF1Algorithm::MGF1_SHA512,
+            _ => bail!("unsupported/bad MGF1 algorithm: {:?}", algorithm),
+        })
+    }
+}
+
+impl_algorithm!(MGF1Algorithm);
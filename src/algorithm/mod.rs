@@ -32,16 +32,24 @@ macro_rules! impl_algorithm {
 
 mod asymmetric_algorithm;
 mod auth_algorithm;
+mod ecdsa_algorithm;
 mod hmac_algorithm;
+mod mgf1_algorithm;
 mod opaque_algorithm;
 mod otp_algorithm;
+mod rsa_pkcs1_algorithm;
+mod rsa_pss_algorithm;
 mod wrap_algorithm;
 
 pub use self::asymmetric_algorithm::*;
 pub use self::auth_algorithm::*;
+pub use self::ecdsa_algorithm::*;
 pub use self::hmac_algorithm::*;
+pub use self::mgf1_algorithm::*;
 pub use self::opaque_algorithm::*;
 pub use self::otp_algorithm::*;
+pub use self::rsa_pkcs1_algorithm::*;
+pub use self::rsa_pss_algorithm::*;
 pub use self::wrap_algorithm::*;
 
 /// Cryptographic algorithm types supported by the `YubiHSM2`
@@ -271,6 +279,14 @@ impl<'de> Deserialize<'de> for Algorithm {
             fn visit_u8<E: de::Error>(self, value: u8) -> Result<Algorithm, E> {
                 Algorithm::from_u8(value).or_else(|e| Err(E::custom(format!("{}", e))))
             }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Algorithm, E> {
+                if value > 0xff {
+                    return Err(E::custom(format!("invalid algorithm: {}", value)));
+                }
+
+                self.visit_u8(value as u8)
+            }
         }
 
         deserializer.deserialize_u8(AlgorithmVisitor)
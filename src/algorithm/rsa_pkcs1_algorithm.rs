@@ -0,0 +1,46 @@
+use failure::Error;
+
+use super::Algorithm;
+
+/// Hash algorithms used to compute RSASSA-PKCS#1v1.5 signatures
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[allow(non_camel_case_types)]
+#[repr(u8)]
+pub enum RSAPKCS1Algorithm {
+    /// rsa-pkcs1-sha1
+    RSA_PKCS1_SHA1 = Algorithm::RSA_PKCS1_SHA1 as u8,
+
+    /// rsa-pkcs1-sha256
+    RSA_PKCS1_SHA256 = Algorithm::RSA_PKCS1_SHA256 as u8,
+
+    /// rsa-pkcs1-sha384
+    RSA_PKCS1_SHA384 = Algorithm::RSA_PKCS1_SHA384 as u8,
+
+    /// rsa-pkcs1-sha512
+    RSA_PKCS1_SHA512 = Algorithm::RSA_PKCS1_SHA512 as u8,
+}
+
+impl RSAPKCS1Algorithm {
+    /// Convert from an `Algorithm` into an `RSAPKCS1Algorithm`
+    pub fn from_algorithm(algorithm: Algorithm) -> Result<Self, Error> {
+        Ok(match algorithm {
+            Algorithm::RSA_PKCS1_SHA1 => RSAPKCS1Algorithm::RSA_PKCS1_SHA1,
+            Algorithm::RSA_PKCS1_SHA256 => RSAPKCS1Algorithm::RSA_PKCS1_SHA256,
+            Algorithm::RSA_PKCS1_SHA384 => RSAPKCS1Algorithm::RSA_PKCS1_SHA384,
+            Algorithm::RSA_PKCS1_SHA512 => RSAPKCS1Algorithm::RSA_PKCS1_SHA512,
+            _ => bail!("unsupported/bad RSA PKCS#1 algorithm: {:?}", algorithm),
+        })
+    }
+
+    /// Size of the digests produced by this algorithm's hash function
+    pub fn digest_len(self) -> usize {
+        match self {
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA1 => 20,
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA256 => 32,
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA384 => 48,
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA512 => 64,
+        }
+    }
+}
+
+impl_algorithm!(RSAPKCS1Algorithm);
@@ -0,0 +1,56 @@
+use failure::Error;
+
+use super::{Algorithm, MGF1Algorithm};
+
+/// Hash algorithms used to compute RSASSA-PSS signatures
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[allow(non_camel_case_types)]
+#[repr(u8)]
+pub enum RSAPSSAlgorithm {
+    /// rsa-pss-sha1
+    RSA_PSS_SHA1 = Algorithm::RSA_PSS_SHA1 as u8,
+
+    /// rsa-pss-sha256
+    RSA_PSS_SHA256 = Algorithm::RSA_PSS_SHA256 as u8,
+
+    /// rsa-pss-sha384
+    RSA_PSS_SHA384 = Algorithm::RSA_PSS_SHA384 as u8,
+
+    /// rsa-pss-sha512
+    RSA_PSS_SHA512 = Algorithm::RSA_PSS_SHA512 as u8,
+}
+
+impl RSAPSSAlgorithm {
+    /// Convert from an `Algorithm` into an `RSAPSSAlgorithm`
+    pub fn from_algorithm(algorithm: Algorithm) -> Result<Self, Error> {
+        Ok(match algorithm {
+            Algorithm::RSA_PSS_SHA1 => RSAPSSAlgorithm::RSA_PSS_SHA1,
+            Algorithm::RSA_PSS_SHA256 => RSAPSSAlgorithm::RSA_PSS_SHA256,
+            Algorithm::RSA_PSS_SHA384 => RSAPSSAlgorithm::RSA_PSS_SHA384,
+            Algorithm::RSA_PSS_SHA512 => RSAPSSAlgorithm::RSA_PSS_SHA512,
+            _ => bail!("unsupported/bad RSA PSS algorithm: {:?}", algorithm),
+        })
+    }
+
+    /// Size of the digests produced by this algorithm's hash function
+    pub fn digest_len(self) -> usize {
+        match self {
+            RSAPSSAlgorithm::RSA_PSS_SHA1 => 20,
+            RSAPSSAlgorithm::RSA_PSS_SHA256 => 32,
+            RSAPSSAlgorithm::RSA_PSS_SHA384 => 48,
+            RSAPSSAlgorithm::RSA_PSS_SHA512 => 64,
+        }
+    }
+
+    /// MGF1 algorithm using the same hash function as this algorithm
+    pub fn mgf1(self) -> MGF1Algorithm {
+        match self {
+            RSAPSSAlgorithm::RSA_PSS_SHA1 => MGF1Algorithm::MGF1_SHA1,
+            RSAPSSAlgorithm::RSA_PSS_SHA256 => MGF1Algorithm::MGF1_SHA256,
+            RSAPSSAlgorithm::RSA_PSS_SHA384 => MGF1Algorithm::MGF1_SHA384,
+            RSAPSSAlgorithm::RSA_PSS_SHA512 => MGF1Algorithm::MGF1_SHA512,
+        }
+    }
+}
+
+impl_algorithm!(RSAPSSAlgorithm);
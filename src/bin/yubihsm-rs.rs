@@ -0,0 +1,114 @@
+//! Command-line utilities for debugging `YubiHSM2` traffic
+//!
+//! ```text
+//! yubihsm-rs decode command [HEX]
+//! yubihsm-rs decode response [HEX]
+//! yubihsm-rs decode recording PATH [KEY_LOG]
+//! ```
+//!
+//! Hex dumps may contain whitespace and colons, and are read from stdin if
+//! they aren't given as arguments.
+
+extern crate yubihsm;
+
+use std::{
+    env,
+    io::{self, Read},
+    process, str,
+};
+#[cfg(feature = "keylog")]
+use yubihsm::keylog::KeyLogDecoder;
+use yubihsm::wire;
+
+/// Usage information
+const USAGE: &str = "usage:
+    yubihsm-rs decode command [HEX]            decode a command message
+    yubihsm-rs decode response [HEX]           decode a response message
+    yubihsm-rs decode recording PATH [KEY_LOG] decode a RecordingConnector recording,
+                                               decrypting sessions in KEY_LOG";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match (
+        args.get(0).map(String::as_str),
+        args.get(1).map(String::as_str),
+    ) {
+        (Some("decode"), Some("command")) => read_hex(&args[2..])
+            .and_then(|bytes| wire::decode_command(&bytes).map_err(|e| e.to_string()))
+            .map(|command| println!("{}", command)),
+        (Some("decode"), Some("response")) => read_hex(&args[2..])
+            .and_then(|bytes| wire::decode_response(&bytes).map_err(|e| e.to_string()))
+            .map(|response| println!("{}", response)),
+        (Some("decode"), Some("recording")) if args.len() == 3 || args.len() == 4 => {
+            decode_recording(&args[2], args.get(3))
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Decode hex given as arguments, or read from stdin if there are none
+fn read_hex(args: &[String]) -> Result<Vec<u8>, String> {
+    let mut hex = args.concat();
+
+    if hex.is_empty() {
+        io::stdin()
+            .read_to_string(&mut hex)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let digits: Vec<u8> = hex
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':')
+        .collect();
+
+    if digits.len() % 2 != 0 {
+        return Err("odd number of hex digits".to_owned());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("invalid hex: {}", String::from_utf8_lossy(pair)))
+        })
+        .collect()
+}
+
+/// Decode (and decrypt, given a key log) every exchange in a recording
+#[cfg(feature = "keylog")]
+fn decode_recording(path: &str, key_log_path: Option<&String>) -> Result<(), String> {
+    let mut decoder = match key_log_path {
+        Some(key_log_path) => KeyLogDecoder::load(key_log_path).map_err(|e| e.to_string())?,
+        None => KeyLogDecoder::new(vec![]),
+    };
+
+    let exchanges = decoder
+        .decode_recording(path)
+        .map_err(|e| e.to_string())?;
+
+    for (i, exchange) in exchanges.iter().enumerate() {
+        match *exchange {
+            Ok(ref exchange) => println!("#{}: {}", i + 1, exchange),
+            Err(ref e) => println!("#{}: couldn't decode: {}", i + 1, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Decoding recordings requires the `keylog` feature
+#[cfg(not(feature = "keylog"))]
+fn decode_recording(_path: &str, _key_log_path: Option<&String>) -> Result<(), String> {
+    Err("decoding recordings requires building with the `keylog` feature".to_owned())
+}
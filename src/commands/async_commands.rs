@@ -0,0 +1,638 @@
+//! Asynchronous versions of the commands supported by the `YubiHSM2`
+//!
+//! Each function takes ownership of an `AsyncSession` and returns a future
+//! which resolves to the session along with the command's result, or along
+//! with the error if the command failed.
+
+// Failed commands return the session along with the error, so it can be reused
+#![allow(unknown_lints, result_large_err)]
+
+use futures::future::{self, Future};
+
+use super::attest_asymmetric::{AttestAsymmetricCommand, AttestationCertificate};
+use super::blink::BlinkCommand;
+use super::delete_object::DeleteObjectCommand;
+use super::device_info::{DeviceInfoCommand, DeviceInfoResponse};
+use super::echo::EchoCommand;
+use super::export_wrapped::ExportWrappedCommand;
+use super::generate_asymmetric_key::GenAsymmetricKeyCommand;
+use super::generate_hmac_key::GenHMACKeyCommand;
+use super::generate_wrap_key::GenWrapKeyCommand;
+use super::get_logs::{GetLogsCommand, GetLogsResponse};
+use super::get_object_info::GetObjectInfoCommand;
+use super::get_opaque::GetOpaqueCommand;
+use super::get_pseudo_random::GetPseudoRandomCommand;
+use super::get_pubkey::{GetPubKeyCommand, PublicKey};
+use super::hmac::{HMACDataCommand, HMACTag};
+use super::import_wrapped::{ImportWrappedCommand, ImportWrappedResponse};
+use super::list_objects::{ListObjectsCommand, ListObjectsEntry};
+use super::put_asymmetric_key::PutAsymmetricKeyCommand;
+use super::put_auth_key::PutAuthKeyCommand;
+use super::put_hmac_key::PutHMACKeyCommand;
+use super::put_object::PutObjectParams;
+use super::put_opaque::PutOpaqueCommand;
+use super::put_otp_aead_key::PutOTPAEADKeyCommand;
+use super::put_wrap_key::PutWrapKeyCommand;
+use super::reset::{reset_result, ResetCommand};
+use super::set_log_index::SetLogIndexCommand;
+#[cfg(
+    all(
+        feature = "sha2",
+        any(feature = "doc", not(feature = "mockhsm"))
+    )
+)]
+use super::sign_ecdsa;
+#[cfg(feature = "sha2")]
+use super::sign_ecdsa::{ECDSASignature, SignDataECDSACommand};
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use super::sign_ecdsa::mockhsm_digest;
+use super::sign_eddsa::{Ed25519Signature, SignDataEdDSACommand};
+#[cfg(feature = "rsa")]
+use super::sign_rsa_pkcs1v15::{RSAPKCS1Signature, SignDataPKCS1Command};
+#[cfg(feature = "rsa")]
+use super::sign_rsa_pss::{RSAPSSOptions, RSAPSSSignature, SignDataPSSCommand};
+use super::storage_status::{StorageStatusCommand, StorageStatusResponse};
+use super::unwrap_data::UnwrapDataCommand;
+use super::verify_hmac::{VerifyHMACCommand, VerifyHMACResponse};
+use super::wrap_data::WrapDataCommand;
+use super::Command;
+use connector::AsyncConnector;
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use mockhsm::MockConnector;
+use session::{AsyncSession, SessionError, SessionFuture};
+#[cfg(feature = "sha2")]
+use ECDSAAlgorithm;
+#[cfg(feature = "rsa")]
+use {RSAPKCS1Algorithm, RSAPSSAlgorithm};
+use {
+    AsymmetricAlgorithm, AuthAlgorithm, AuthKey, Capability, Domain, HMACAlgorithm, OTPAlgorithm,
+    ObjectId, ObjectInfo, ObjectLabel, ObjectType, OpaqueAlgorithm, WrapAlgorithm, WrapMessage,
+};
+
+/// Attest an asymmetric key stored in the `YubiHSM2`.
+/// See `commands::attest_asymmetric` for more information.
+pub fn attest_asymmetric<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    attestation_key_id: Option<ObjectId>,
+) -> SessionFuture<C, AttestationCertificate> {
+    let command = AttestAsymmetricCommand::new(key_id, attestation_key_id);
+    send(session, Ok(command))
+}
+
+/// Blink the YubiHSM2's LEDs (to identify it) for the given number of seconds
+pub fn blink<C: AsyncConnector>(session: AsyncSession<C>, num_seconds: u8) -> SessionFuture<C, ()> {
+    send(session, Ok(BlinkCommand { num_seconds }))
+}
+
+/// Delete an object of the given ID and type
+pub fn delete_object<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    object_id: ObjectId,
+    object_type: ObjectType,
+) -> SessionFuture<C, ()> {
+    let command = DeleteObjectCommand::new(object_id, object_type);
+    send(session, Ok(command))
+}
+
+/// Get information about the YubiHSM2 device
+pub fn device_info<C: AsyncConnector>(
+    session: AsyncSession<C>,
+) -> SessionFuture<C, DeviceInfoResponse> {
+    send(session, Ok(DeviceInfoCommand {}))
+}
+
+/// Have the card echo an input message
+pub fn echo<C, T>(session: AsyncSession<C>, message: T) -> SessionFuture<C, Vec<u8>>
+where
+    C: AsyncConnector,
+    T: Into<Vec<u8>>,
+{
+    let command = EchoCommand {
+        message: message.into(),
+    };
+
+    send(session, Ok(command))
+}
+
+/// Export an encrypted object from the `YubiHSM2` using the given key-wrapping key
+pub fn export_wrapped<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    wrap_key_id: ObjectId,
+    object_type: ObjectType,
+    object_id: ObjectId,
+) -> SessionFuture<C, WrapMessage> {
+    let command = ExportWrappedCommand::new(wrap_key_id, object_type, object_id);
+    send(session, Ok(command))
+}
+
+/// Generate a new asymmetric key within the `YubiHSM2`
+pub fn generate_asymmetric_key<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    algorithm: AsymmetricAlgorithm,
+) -> SessionFuture<C, ObjectId> {
+    let command = GenAsymmetricKeyCommand::new(key_id, label, domains, capabilities, algorithm);
+    send(session, Ok(command))
+}
+
+/// Generate a new HMAC key within the `YubiHSM2`
+pub fn generate_hmac_key<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    algorithm: HMACAlgorithm,
+) -> SessionFuture<C, ObjectId> {
+    let command = GenHMACKeyCommand::new(key_id, label, domains, capabilities, algorithm);
+    send(session, Ok(command))
+}
+
+/// Generate a new wrap key within the `YubiHSM2`.
+/// See `commands::generate_wrap_key` for more information.
+pub fn generate_wrap_key<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    delegated_capabilities: Capability,
+    algorithm: WrapAlgorithm,
+) -> SessionFuture<C, ObjectId> {
+    let command = GenWrapKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        delegated_capabilities,
+        algorithm,
+    );
+
+    send(session, Ok(command))
+}
+
+/// Get audit logs from the YubiHSM2 device
+pub fn get_logs<C: AsyncConnector>(session: AsyncSession<C>) -> SessionFuture<C, GetLogsResponse> {
+    send(session, Ok(GetLogsCommand {}))
+}
+
+/// Get information about an object
+pub fn get_object_info<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    object_id: ObjectId,
+    object_type: ObjectType,
+) -> SessionFuture<C, ObjectInfo> {
+    let command = GetObjectInfoCommand::new(object_id, object_type);
+    send(session, Ok(command))
+}
+
+/// Get an opaque object stored in the `YubiHSM2`
+pub fn get_opaque<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    object_id: ObjectId,
+) -> SessionFuture<C, Vec<u8>> {
+    send(session, Ok(GetOpaqueCommand { object_id }))
+}
+
+/// Get some number of bytes of pseudo random data generated on the device
+pub fn get_pseudo_random<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    bytes: u16,
+) -> SessionFuture<C, Vec<u8>> {
+    send(session, GetPseudoRandomCommand::new(bytes))
+}
+
+/// Get the public key for an asymmetric key stored on the device
+pub fn get_pubkey<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+) -> SessionFuture<C, PublicKey> {
+    send(session, Ok(GetPubKeyCommand { key_id }))
+}
+
+/// Compute an HMAC tag of the given data with the given key ID
+pub fn hmac<C, D>(session: AsyncSession<C>, key_id: ObjectId, data: D) -> SessionFuture<C, HMACTag>
+where
+    C: AsyncConnector,
+    D: Into<Vec<u8>>,
+{
+    let command = HMACDataCommand {
+        key_id,
+        data: data.into(),
+    };
+
+    send(session, Ok(command))
+}
+
+/// Import an encrypted object from the `YubiHSM2` using the given key-wrapping key
+pub fn import_wrapped<C, M>(
+    session: AsyncSession<C>,
+    wrap_key_id: ObjectId,
+    wrap_message: M,
+) -> SessionFuture<C, ImportWrappedResponse>
+where
+    C: AsyncConnector,
+    M: Into<WrapMessage>,
+{
+    let command = ImportWrappedCommand::new(wrap_key_id, wrap_message.into());
+    send(session, Ok(command))
+}
+
+/// List objects visible from the current session
+pub fn list_objects<C: AsyncConnector>(
+    session: AsyncSession<C>,
+) -> SessionFuture<C, Vec<ListObjectsEntry>> {
+    send(session, Ok(ListObjectsCommand {}))
+}
+
+/// Put an existing asymmetric key into the `YubiHSM2`
+pub fn put_asymmetric_key<C: AsyncConnector, T: Into<Vec<u8>>>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    algorithm: AsymmetricAlgorithm,
+    key_bytes: T,
+) -> SessionFuture<C, ObjectId> {
+    let command = PutAsymmetricKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        algorithm,
+        key_bytes.into(),
+    );
+
+    send(session, command)
+}
+
+/// Put an existing auth key into the `YubiHSM2`
+#[allow(unknown_lints, too_many_arguments)]
+pub fn put_auth_key<C: AsyncConnector, K: Into<AuthKey>>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    delegated_capabilities: Capability,
+    algorithm: AuthAlgorithm,
+    auth_key: K,
+) -> SessionFuture<C, ObjectId> {
+    let command = PutAuthKeyCommand {
+        params: PutObjectParams::new(key_id, label, domains, capabilities, algorithm),
+        delegated_capabilities,
+        auth_key: auth_key.into(),
+    };
+
+    send(session, Ok(command))
+}
+
+/// Put an existing HMAC key into the `YubiHSM2`
+pub fn put_hmac_key<C: AsyncConnector, T: Into<Vec<u8>>>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    algorithm: HMACAlgorithm,
+    key_bytes: T,
+) -> SessionFuture<C, ObjectId> {
+    let command = PutHMACKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        algorithm,
+        key_bytes.into(),
+    );
+
+    send(session, command)
+}
+
+/// Put an opaque object (X.509 certificate or other bytestring) into the `YubiHSM2`
+pub fn put_opaque<C: AsyncConnector, T: Into<Vec<u8>>>(
+    session: AsyncSession<C>,
+    object_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    algorithm: OpaqueAlgorithm,
+    bytes: T,
+) -> SessionFuture<C, ObjectId> {
+    let command = PutOpaqueCommand {
+        params: PutObjectParams::new(object_id, label, domains, capabilities, algorithm),
+        data: bytes.into(),
+    };
+
+    send(session, Ok(command))
+}
+
+/// Put an existing OTP AEAD key into the `YubiHSM2`
+pub fn put_otp_aead_key<C: AsyncConnector, T: Into<Vec<u8>>>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    algorithm: OTPAlgorithm,
+    key_bytes: T,
+) -> SessionFuture<C, ObjectId> {
+    let command = PutOTPAEADKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        algorithm,
+        key_bytes.into(),
+    );
+
+    send(session, command)
+}
+
+/// Put an existing wrap key into the `YubiHSM2`
+#[allow(unknown_lints, too_many_arguments)]
+pub fn put_wrap_key<C: AsyncConnector, T: Into<Vec<u8>>>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    delegated_capabilities: Capability,
+    algorithm: WrapAlgorithm,
+    key_bytes: T,
+) -> SessionFuture<C, ObjectId> {
+    let command = PutWrapKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        delegated_capabilities,
+        algorithm,
+        key_bytes.into(),
+    );
+
+    send(session, command)
+}
+
+/// Reset the `YubiHSM2` to a factory default state and reboot
+pub fn reset<C: AsyncConnector>(
+    session: AsyncSession<C>,
+) -> Box<Future<Item = (), Error = SessionError> + Send> {
+    Box::new(
+        session
+            .send_command(ResetCommand {})
+            .then(|result| {
+                reset_result(result.map(|(_, response)| response).map_err(|(_, e)| e))
+            }),
+    )
+}
+
+/// Set the index of the last consumed index of the `YubiHSM2` audit log
+pub fn set_log_index<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    log_index: u16,
+) -> SessionFuture<C, ()> {
+    send(session, Ok(SetLogIndexCommand { log_index }))
+}
+
+/// Compute an ECDSA signature of the hash of the given data with the given key ID, using the
+/// given hash algorithm (e.g. SHA-384 for P-384 keys)
+#[cfg(
+    all(
+        feature = "sha2",
+        any(feature = "doc", not(feature = "mockhsm"))
+    )
+)]
+pub fn sign_ecdsa<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+) -> SessionFuture<C, ECDSASignature> {
+    let command = SignDataECDSACommand {
+        key_id,
+        digest: sign_ecdsa::digest(algorithm, data),
+    };
+
+    send(session, Ok(command))
+}
+
+/// Compute an ECDSA signature of the hash of the given data with the given key ID, using the
+/// given hash algorithm (e.g. SHA-384 for P-384 keys)
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa(
+    session: AsyncSession<MockConnector>,
+    key_id: ObjectId,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+) -> SessionFuture<MockConnector, ECDSASignature> {
+    // See `commands::sign_ecdsa` for what the MockHSM is passed
+    let data = data.to_vec();
+
+    Box::new(
+        get_pubkey(session, key_id).and_then(move |(session, public_key)| {
+            let command = mockhsm_digest(public_key.algorithm, algorithm, &data)
+                .map(|digest| SignDataECDSACommand { key_id, digest });
+
+            send(session, command)
+        }),
+    )
+}
+
+/// Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID
+#[cfg(
+    all(
+        feature = "sha2",
+        any(feature = "doc", not(feature = "mockhsm"))
+    )
+)]
+pub fn sign_ecdsa_sha256<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    data: &[u8],
+) -> SessionFuture<C, ECDSASignature> {
+    sign_ecdsa(session, key_id, ECDSAAlgorithm::EC_ECDSA_SHA256, data)
+}
+
+/// Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa_sha256(
+    session: AsyncSession<MockConnector>,
+    key_id: ObjectId,
+    data: &[u8],
+) -> SessionFuture<MockConnector, ECDSASignature> {
+    sign_ecdsa(session, key_id, ECDSAAlgorithm::EC_ECDSA_SHA256, data)
+}
+
+/// Compute an Ed25519 signature with the given key ID
+pub fn sign_ed25519<C, T>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    data: T,
+) -> SessionFuture<C, Ed25519Signature>
+where
+    C: AsyncConnector,
+    T: Into<Vec<u8>>,
+{
+    let command = SignDataEdDSACommand {
+        key_id,
+        data: data.into(),
+    };
+
+    send(session, Ok(command))
+}
+
+/// Compute an RSASSA-PKCS#1v1.5 signature of the hash of the given data with the given key ID,
+/// using the given hash algorithm
+///
+/// WARNING: This method has not been tested and is not confirmed to actually work! Use at your
+/// own risk!
+#[cfg(feature = "rsa")]
+pub fn sign_rsa_pkcs1v15<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    algorithm: RSAPKCS1Algorithm,
+    data: &[u8],
+) -> SessionFuture<C, RSAPKCS1Signature> {
+    let command = SignDataPKCS1Command::new(key_id, algorithm, data);
+    send(session, Ok(command))
+}
+
+/// Compute an RSASSA-PKCS#1v1.5 signature of the SHA-256 hash of the given data.
+///
+/// WARNING: This method has not been tested and is not confirmed to actually work! Use at your
+/// own risk!
+#[cfg(feature = "rsa")]
+pub fn sign_rsa_pkcs1v15_sha256<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    data: &[u8],
+) -> SessionFuture<C, RSAPKCS1Signature> {
+    sign_rsa_pkcs1v15(session, key_id, RSAPKCS1Algorithm::RSA_PKCS1_SHA256, data)
+}
+
+/// Compute an RSASSA-PSS signature of the hash of the given data with the given key ID, using
+/// the given hash algorithm
+#[cfg(feature = "rsa")]
+pub fn sign_rsa_pss<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    algorithm: RSAPSSAlgorithm,
+    data: &[u8],
+) -> SessionFuture<C, RSAPSSSignature> {
+    sign_rsa_pss_with_options(session, key_id, algorithm, data, RSAPSSOptions::default())
+}
+
+/// Compute an RSASSA-PSS signature of the hash of the given data with the given key ID, using
+/// the given hash algorithm and options
+#[cfg(feature = "rsa")]
+pub fn sign_rsa_pss_with_options<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    algorithm: RSAPSSAlgorithm,
+    data: &[u8],
+    options: RSAPSSOptions,
+) -> SessionFuture<C, RSAPSSSignature> {
+    let command = SignDataPSSCommand::new(key_id, algorithm, data, options);
+    send(session, Ok(command))
+}
+
+/// Compute an RSASSA-PSS signature of the SHA-256 hash of the given data with the given key ID.
+#[cfg(feature = "rsa")]
+pub fn sign_rsa_pss_sha256<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    data: &[u8],
+) -> SessionFuture<C, RSAPSSSignature> {
+    sign_rsa_pss(session, key_id, RSAPSSAlgorithm::RSA_PSS_SHA256, data)
+}
+
+/// Get storage status (i.e. currently free storage) from the `YubiHSM2` device
+pub fn storage_status<C: AsyncConnector>(
+    session: AsyncSession<C>,
+) -> SessionFuture<C, StorageStatusResponse> {
+    send(session, Ok(StorageStatusCommand {}))
+}
+
+/// Decrypt data which was encrypted (using AES-CCM) under a wrap key
+pub fn unwrap_data<C, M>(
+    session: AsyncSession<C>,
+    wrap_key_id: ObjectId,
+    wrap_message: M,
+) -> SessionFuture<C, Vec<u8>>
+where
+    C: AsyncConnector,
+    M: Into<WrapMessage>,
+{
+    let command = UnwrapDataCommand::new(wrap_key_id, wrap_message.into());
+    send(session, Ok(command))
+}
+
+/// Verify an HMAC tag of the given data with the given key ID
+pub fn verify_hmac<C, D, T>(
+    session: AsyncSession<C>,
+    key_id: ObjectId,
+    data: D,
+    tag: T,
+) -> SessionFuture<C, ()>
+where
+    C: AsyncConnector,
+    D: Into<Vec<u8>>,
+    T: Into<HMACTag>,
+{
+    let command = VerifyHMACCommand {
+        key_id,
+        tag: tag.into(),
+        data: data.into(),
+    };
+
+    Box::new(
+        send(session, Ok(command)).and_then(|(session, response): (_, VerifyHMACResponse)| {
+            match response.into_result() {
+                Ok(()) => Ok((session, ())),
+                Err(e) => Err((session, e)),
+            }
+        }),
+    )
+}
+
+/// Encrypt data (using AES-CCM) under the given wrap key
+pub fn wrap_data<C: AsyncConnector>(
+    session: AsyncSession<C>,
+    wrap_key_id: ObjectId,
+    plaintext: Vec<u8>,
+) -> SessionFuture<C, WrapMessage> {
+    let command = WrapDataCommand {
+        wrap_key_id,
+        plaintext,
+    };
+
+    send(session, Ok(command))
+}
+
+/// Send a command (if it was constructed successfully), converting the
+/// response into the given result type the same way the synchronous
+/// version of the command does
+fn send<C, T, R>(session: AsyncSession<C>, command: Result<T, SessionError>) -> SessionFuture<C, R>
+where
+    C: AsyncConnector,
+    T: Command + Send + 'static,
+    T::ResponseType: Into<R> + Send + 'static,
+    R: Send + 'static,
+{
+    match command {
+        Ok(command) => Box::new(
+            session
+                .send_command(command)
+                .map(|(session, response)| (session, response.into())),
+        ),
+        Err(e) => Box::new(future::err((session, e))),
+    }
+}
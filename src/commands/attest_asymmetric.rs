@@ -20,10 +20,7 @@ pub fn attest_asymmetric<C: Connector>(
     key_id: ObjectId,
     attestation_key_id: Option<ObjectId>,
 ) -> Result<AttestationCertificate, SessionError> {
-    session.send_encrypted_command(AttestAsymmetricCommand {
-        key_id,
-        attestation_key_id: attestation_key_id.unwrap_or(0),
-    })
+    session.send_encrypted_command(AttestAsymmetricCommand::new(key_id, attestation_key_id))
 }
 
 /// Request parameters for `commands::attest_asymmetric`
@@ -36,6 +33,17 @@ pub(crate) struct AttestAsymmetricCommand {
     pub attestation_key_id: ObjectId,
 }
 
+impl AttestAsymmetricCommand {
+    /// Create a command to attest the given key, using the device's default
+    /// attestation key if none is given
+    pub(crate) fn new(key_id: ObjectId, attestation_key_id: Option<ObjectId>) -> Self {
+        Self {
+            key_id,
+            attestation_key_id: attestation_key_id.unwrap_or(0),
+        }
+    }
+}
+
 impl Command for AttestAsymmetricCommand {
     type ResponseType = AttestationCertificate;
 }
@@ -30,3 +30,7 @@ pub(crate) struct BlinkResponse {}
 impl Response for BlinkResponse {
     const COMMAND_TYPE: CommandType = CommandType::Blink;
 }
+
+impl From<BlinkResponse> for () {
+    fn from(_response: BlinkResponse) {}
+}
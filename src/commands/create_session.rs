@@ -21,10 +21,17 @@ pub(crate) fn create_session<C: Connector>(
 
     let uuid = command_message.uuid;
     let response_body = connector.send_command(uuid, command_message.into())?;
+    parse_response(response_body)
+}
+
+/// Parse the HSM's response to a `CreateSession` command
+pub(crate) fn parse_response(
+    response_body: Vec<u8>,
+) -> Result<(SessionId, CreateSessionResponse), SessionError> {
     let response_message = ResponseMessage::parse(response_body)?;
 
     if response_message.is_err() {
-        command_fail!(ResponseError, "HSM error: {:?}", response_message.code);
+        return Err(response_message.error_code().into());
     }
 
     if response_message.command().unwrap() != CommandType::CreateSession {
@@ -47,7 +54,7 @@ pub(crate) fn create_session<C: Connector>(
 
 /// Request parameters for `commands::create_session`
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct CreateSessionCommand {
+pub struct CreateSessionCommand {
     /// Authentication key ID to use
     pub auth_key_id: ObjectId,
 
@@ -61,7 +68,7 @@ impl Command for CreateSessionCommand {
 
 /// Response from `commands::create_session`
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct CreateSessionResponse {
+pub struct CreateSessionResponse {
     /// Randomly generated challenge from the card
     pub card_challenge: Challenge,
 
@@ -11,11 +11,9 @@ pub fn delete_object<C: Connector>(
     object_id: ObjectId,
     object_type: ObjectType,
 ) -> Result<(), SessionError> {
-    session.send_encrypted_command(DeleteObjectCommand {
-        object_id,
-        object_type,
-    })?;
-    Ok(())
+    session
+        .send_encrypted_command(DeleteObjectCommand::new(object_id, object_type))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::delete_object`
@@ -28,6 +26,16 @@ pub(crate) struct DeleteObjectCommand {
     pub object_type: ObjectType,
 }
 
+impl DeleteObjectCommand {
+    /// Create a command to delete the given object
+    pub(crate) fn new(object_id: ObjectId, object_type: ObjectType) -> Self {
+        Self {
+            object_id,
+            object_type,
+        }
+    }
+}
+
 impl Command for DeleteObjectCommand {
     type ResponseType = DeleteObjectResponse;
 }
@@ -39,3 +47,7 @@ pub(crate) struct DeleteObjectResponse {}
 impl Response for DeleteObjectResponse {
     const COMMAND_TYPE: CommandType = CommandType::DeleteObject;
 }
+
+impl From<DeleteObjectResponse> for () {
+    fn from(_response: DeleteObjectResponse) {}
+}
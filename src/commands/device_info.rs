@@ -14,7 +14,7 @@ pub fn device_info<C: Connector>(
 
 /// Request parameters for `commands::device_info`
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct DeviceInfoCommand {}
+pub struct DeviceInfoCommand {}
 
 impl Command for DeviceInfoCommand {
     type ResponseType = DeviceInfoResponse;
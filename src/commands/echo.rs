@@ -15,12 +15,12 @@ where
         .send_encrypted_command(EchoCommand {
             message: message.into(),
         })
-        .map(|response| response.0)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::echo`
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct EchoCommand {
+pub struct EchoCommand {
     /// Message to echo
     pub message: Vec<u8>,
 }
@@ -31,8 +31,14 @@ impl Command for EchoCommand {
 
 /// Response from `commands::ccho`
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct EchoResponse(pub(crate) Vec<u8>);
+pub struct EchoResponse(pub Vec<u8>);
 
 impl Response for EchoResponse {
     const COMMAND_TYPE: CommandType = CommandType::Echo;
 }
+
+impl From<EchoResponse> for Vec<u8> {
+    fn from(response: EchoResponse) -> Vec<u8> {
+        response.0
+    }
+}
@@ -13,12 +13,8 @@ pub fn export_wrapped<C: Connector>(
     object_id: ObjectId,
 ) -> Result<WrapMessage, SessionError> {
     session
-        .send_encrypted_command(ExportWrappedCommand {
-            wrap_key_id,
-            object_type,
-            object_id,
-        })
-        .map(|response| response.0)
+        .send_encrypted_command(ExportWrappedCommand::new(wrap_key_id, object_type, object_id))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::export_wrapped`
@@ -34,6 +30,17 @@ pub(crate) struct ExportWrappedCommand {
     pub object_id: ObjectId,
 }
 
+impl ExportWrappedCommand {
+    /// Create a command to export the given object under the given wrap key
+    pub(crate) fn new(wrap_key_id: ObjectId, object_type: ObjectType, object_id: ObjectId) -> Self {
+        Self {
+            wrap_key_id,
+            object_type,
+            object_id,
+        }
+    }
+}
+
 impl Command for ExportWrappedCommand {
     type ResponseType = ExportWrappedResponse;
 }
@@ -45,3 +52,9 @@ pub(crate) struct ExportWrappedResponse(pub(crate) WrapMessage);
 impl Response for ExportWrappedResponse {
     const COMMAND_TYPE: CommandType = CommandType::ExportWrapped;
 }
+
+impl From<ExportWrappedResponse> for WrapMessage {
+    fn from(response: ExportWrappedResponse) -> WrapMessage {
+        response.0
+    }
+}
@@ -19,20 +19,39 @@ pub fn generate_asymmetric_key<C: Connector>(
     algorithm: AsymmetricAlgorithm,
 ) -> Result<ObjectId, SessionError> {
     session
-        .send_encrypted_command(GenAsymmetricKeyCommand(GenerateKeyParams {
+        .send_encrypted_command(GenAsymmetricKeyCommand::new(
             key_id,
             label,
             domains,
             capabilities,
-            algorithm: algorithm.into(),
-        }))
-        .map(|response| response.key_id)
+            algorithm,
+        ))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::generate_asymmetric_key`
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct GenAsymmetricKeyCommand(pub(crate) GenerateKeyParams);
 
+impl GenAsymmetricKeyCommand {
+    /// Create a command to generate a key with the given parameters
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: AsymmetricAlgorithm,
+    ) -> Self {
+        GenAsymmetricKeyCommand(GenerateKeyParams::new(
+            key_id,
+            label,
+            domains,
+            capabilities,
+            algorithm,
+        ))
+    }
+}
+
 impl Command for GenAsymmetricKeyCommand {
     type ResponseType = GenAsymmetricKeyResponse;
 }
@@ -47,3 +66,9 @@ pub(crate) struct GenAsymmetricKeyResponse {
 impl Response for GenAsymmetricKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::GenerateAsymmetricKey;
 }
+
+impl From<GenAsymmetricKeyResponse> for ObjectId {
+    fn from(response: GenAsymmetricKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
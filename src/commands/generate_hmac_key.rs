@@ -19,20 +19,39 @@ pub fn generate_hmac_key<C: Connector>(
     algorithm: HMACAlgorithm,
 ) -> Result<ObjectId, SessionError> {
     session
-        .send_encrypted_command(GenHMACKeyCommand(GenerateKeyParams {
+        .send_encrypted_command(GenHMACKeyCommand::new(
             key_id,
             label,
             domains,
             capabilities,
-            algorithm: algorithm.into(),
-        }))
-        .map(|response| response.key_id)
+            algorithm,
+        ))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::generate_hmac_key`
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct GenHMACKeyCommand(pub(crate) GenerateKeyParams);
 
+impl GenHMACKeyCommand {
+    /// Create a command to generate a key with the given parameters
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: HMACAlgorithm,
+    ) -> Self {
+        GenHMACKeyCommand(GenerateKeyParams::new(
+            key_id,
+            label,
+            domains,
+            capabilities,
+            algorithm,
+        ))
+    }
+}
+
 impl Command for GenHMACKeyCommand {
     type ResponseType = GenHMACKeyResponse;
 }
@@ -47,3 +66,9 @@ pub(crate) struct GenHMACKeyResponse {
 impl Response for GenHMACKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::GenerateHMACKey;
 }
+
+impl From<GenHMACKeyResponse> for ObjectId {
+    fn from(response: GenHMACKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
@@ -20,3 +20,22 @@ pub(crate) struct GenerateKeyParams {
     /// Key algorithm
     pub algorithm: Algorithm,
 }
+
+impl GenerateKeyParams {
+    /// Create the parameters for generating a key with the given algorithm
+    pub(crate) fn new<A: Into<Algorithm>>(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: A,
+    ) -> Self {
+        Self {
+            key_id,
+            label,
+            domains,
+            capabilities,
+            algorithm: algorithm.into(),
+        }
+    }
+}
@@ -23,17 +23,15 @@ pub fn generate_wrap_key<C: Connector>(
     algorithm: WrapAlgorithm,
 ) -> Result<ObjectId, SessionError> {
     session
-        .send_encrypted_command(GenWrapKeyCommand {
-            params: GenerateKeyParams {
-                key_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
+        .send_encrypted_command(GenWrapKeyCommand::new(
+            key_id,
+            label,
+            domains,
+            capabilities,
             delegated_capabilities,
-        })
-        .map(|response| response.key_id)
+            algorithm,
+        ))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::generate_wrap_key`
@@ -46,6 +44,23 @@ pub(crate) struct GenWrapKeyCommand {
     pub delegated_capabilities: Capability,
 }
 
+impl GenWrapKeyCommand {
+    /// Create a command to generate a wrap key with the given parameters
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        delegated_capabilities: Capability,
+        algorithm: WrapAlgorithm,
+    ) -> Self {
+        Self {
+            params: GenerateKeyParams::new(key_id, label, domains, capabilities, algorithm),
+            delegated_capabilities,
+        }
+    }
+}
+
 impl Command for GenWrapKeyCommand {
     type ResponseType = GenWrapKeyResponse;
 }
@@ -60,3 +75,9 @@ pub(crate) struct GenWrapKeyResponse {
 impl Response for GenWrapKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::GenerateWrapKey;
 }
+
+impl From<GenWrapKeyResponse> for ObjectId {
+    fn from(response: GenWrapKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
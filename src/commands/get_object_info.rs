@@ -14,17 +14,21 @@ pub fn get_object_info<C: Connector>(
     object_type: ObjectType,
 ) -> Result<ObjectInfo, SessionError> {
     session
-        .send_encrypted_command(GetObjectInfoCommand(ObjectHandle::new(
-            object_id,
-            object_type,
-        )))
-        .map(|response| response.0)
+        .send_encrypted_command(GetObjectInfoCommand::new(object_id, object_type))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::get_object_info`
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct GetObjectInfoCommand(pub(crate) ObjectHandle);
 
+impl GetObjectInfoCommand {
+    /// Create a command to get information about the given object
+    pub(crate) fn new(object_id: ObjectId, object_type: ObjectType) -> Self {
+        GetObjectInfoCommand(ObjectHandle::new(object_id, object_type))
+    }
+}
+
 impl Command for GetObjectInfoCommand {
     type ResponseType = GetObjectInfoResponse;
 }
@@ -36,3 +40,9 @@ pub(crate) struct GetObjectInfoResponse(pub(crate) ObjectInfo);
 impl Response for GetObjectInfoResponse {
     const COMMAND_TYPE: CommandType = CommandType::GetObjectInfo;
 }
+
+impl From<GetObjectInfoResponse> for ObjectInfo {
+    fn from(response: GetObjectInfoResponse) -> ObjectInfo {
+        response.0
+    }
+}
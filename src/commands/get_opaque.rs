@@ -12,7 +12,7 @@ pub fn get_opaque<C: Connector>(
 ) -> Result<Vec<u8>, SessionError> {
     session
         .send_encrypted_command(GetOpaqueCommand { object_id })
-        .map(|response| response.0)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::get_opaque`
@@ -33,3 +33,9 @@ pub(crate) struct GetOpaqueResponse(pub(crate) Vec<u8>);
 impl Response for GetOpaqueResponse {
     const COMMAND_TYPE: CommandType = CommandType::GetOpaqueObject;
 }
+
+impl From<GetOpaqueResponse> for Vec<u8> {
+    fn from(response: GetOpaqueResponse) -> Vec<u8> {
+        response.0
+    }
+}
@@ -14,15 +14,9 @@ pub fn get_pseudo_random<C: Connector>(
     session: &mut Session<C>,
     bytes: u16,
 ) -> Result<Vec<u8>, SessionError> {
-    if bytes >= MAX_RAND_BYTES {
-        command_fail!(
-            ProtocolError,
-            "Requested too many random bytes (>= 2045) to fit in response packet"
-        );
-    }
     session
-        .send_encrypted_command(GetPseudoRandomCommand { bytes })
-        .map(|response| response.bytes)
+        .send_encrypted_command(GetPseudoRandomCommand::new(bytes)?)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::get_pseudo_random`
@@ -32,6 +26,20 @@ pub(crate) struct GetPseudoRandomCommand {
     pub bytes: u16,
 }
 
+impl GetPseudoRandomCommand {
+    /// Create a new command, ensuring the response will fit in a packet
+    pub(crate) fn new(bytes: u16) -> Result<Self, SessionError> {
+        if bytes >= MAX_RAND_BYTES {
+            command_fail!(
+                ProtocolError,
+                "Requested too many random bytes (>= 2045) to fit in response packet"
+            );
+        }
+
+        Ok(Self { bytes })
+    }
+}
+
 impl Command for GetPseudoRandomCommand {
     type ResponseType = GetPseudoRandomResponse;
 }
@@ -46,3 +54,9 @@ pub(crate) struct GetPseudoRandomResponse {
 impl Response for GetPseudoRandomResponse {
     const COMMAND_TYPE: CommandType = CommandType::GetPseudoRandom;
 }
+
+impl From<GetPseudoRandomResponse> for Vec<u8> {
+    fn from(response: GetPseudoRandomResponse) -> Vec<u8> {
+        response.bytes
+    }
+}
@@ -25,7 +25,7 @@ impl Command for GetPubKeyCommand {
 }
 
 /// Response from `commands::get_pubkey`
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PublicKey {
     /// Algorithm of the public key
     pub algorithm: AsymmetricAlgorithm,
@@ -39,9 +39,10 @@ pub struct PublicKey {
     /// - Ed25519: Public point A, compressed (0x20 bytes)
     ///
     /// In particular note that in the case of e.g. ECDSA public keys, many
-    /// libraries will expect a 0x04 (DER OCTET STRING) tag byte at the
-    /// beginning of the key. The YubiHSM does not return this, so you may
-    /// need to add it depending on your particular application.
+    /// libraries will expect a 0x04 (SEC1 uncompressed point) tag byte at
+    /// the beginning of the key. The YubiHSM does not return this: use e.g.
+    /// `to_sec1_uncompressed` or `to_spki_der` to encode the key in a
+    /// standard format.
     pub bytes: Vec<u8>,
 }
 
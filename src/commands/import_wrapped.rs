@@ -15,13 +15,7 @@ where
     C: Connector,
     M: Into<WrapMessage>,
 {
-    let WrapMessage { nonce, ciphertext } = wrap_message.into();
-
-    session.send_encrypted_command(ImportWrappedCommand {
-        wrap_key_id,
-        nonce,
-        ciphertext,
-    })
+    session.send_encrypted_command(ImportWrappedCommand::new(wrap_key_id, wrap_message.into()))
 }
 
 /// Request parameters for `commands::import_wrapped`
@@ -37,6 +31,19 @@ pub(crate) struct ImportWrappedCommand {
     pub ciphertext: Vec<u8>,
 }
 
+impl ImportWrappedCommand {
+    /// Create a command to import the given wrapped object
+    pub(crate) fn new(wrap_key_id: ObjectId, wrap_message: WrapMessage) -> Self {
+        let WrapMessage { nonce, ciphertext } = wrap_message;
+
+        Self {
+            wrap_key_id,
+            nonce,
+            ciphertext,
+        }
+    }
+}
+
 impl Command for ImportWrappedCommand {
     type ResponseType = ImportWrappedResponse;
 }
@@ -12,7 +12,7 @@ pub fn list_objects<C: Connector>(
     // TODO: support for filtering objects
     session
         .send_encrypted_command(ListObjectsCommand {})
-        .map(|response| response.0)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::list_objects`
@@ -44,3 +44,9 @@ pub struct ListObjectsEntry {
     /// previously existed
     pub sequence: SequenceId,
 }
+
+impl From<ListObjectsResponse> for Vec<ListObjectsEntry> {
+    fn from(response: ListObjectsResponse) -> Vec<ListObjectsEntry> {
+        response.0
+    }
+}
@@ -34,10 +34,12 @@ macro_rules! command_fail {
     };
 }
 
+#[cfg(feature = "async")]
+pub mod async_commands;
 pub mod attest_asymmetric;
 pub mod blink;
 pub(crate) mod close_session;
-pub(crate) mod create_session;
+pub mod create_session;
 pub mod delete_object;
 pub mod device_info;
 pub mod echo;
@@ -77,8 +79,12 @@ pub mod wrap_data;
 /// Structured commands (i.e. requests) which are encrypted and then sent to
 /// the HSM. Every command has a corresponding `ResponseType`.
 ///
+/// Commands this crate doesn't support yet can be defined by implementing
+/// this trait (and `Response`) for types which serialize to the command's
+/// wire format, then sent with `Session::send_encrypted_command`.
+///
 /// See <https://developers.yubico.com/YubiHSM2/Commands>
-pub(crate) trait Command: Serialize + DeserializeOwned + Sized {
+pub trait Command: Serialize + DeserializeOwned + Sized {
     /// Response type for this command
     type ResponseType: Response;
 
@@ -93,17 +99,23 @@ impl<C: Command> From<C> for CommandMessage {
 }
 
 /// Structured responses to `Command` messages sent from the HSM
-pub(crate) trait Response: Serialize + DeserializeOwned + Sized {
+pub trait Response: Serialize + DeserializeOwned + Sized {
     /// Command ID this response is for
     const COMMAND_TYPE: CommandType;
+}
 
+/// Serialize responses into messages sent back from the `MockHSM`
+#[cfg(feature = "mockhsm")]
+pub(crate) trait SerializeResponse: Response {
     /// Serialize a response type into a ResponseMessage
-    #[cfg(feature = "mockhsm")]
     fn serialize(&self) -> ResponseMessage {
         ResponseMessage::success(Self::COMMAND_TYPE, serialize(self).unwrap())
     }
 }
 
+#[cfg(feature = "mockhsm")]
+impl<R: Response> SerializeResponse for R {}
+
 /// Command IDs for `YubiHSM2` operations
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[allow(missing_docs)]
@@ -228,6 +240,45 @@ impl CommandType {
     pub fn to_u8(self) -> u8 {
         self as u8
     }
+
+    /// Can this command safely be sent again if we don't know whether the
+    /// `YubiHSM2` received it? i.e. it doesn't modify the HSM's state, aside
+    /// from e.g. audit log entries.
+    pub fn is_idempotent(self) -> bool {
+        match self {
+            CommandType::Echo
+            | CommandType::DeviceInfo
+            | CommandType::StorageStatus
+            | CommandType::GetOpaqueObject
+            | CommandType::SignDataPKCS1
+            | CommandType::ListObjects
+            | CommandType::DecryptPKCS1
+            | CommandType::ExportWrapped
+            | CommandType::GetLogs
+            | CommandType::GetObjectInfo
+            | CommandType::GetOption
+            | CommandType::GetPseudoRandom
+            | CommandType::HMACData
+            | CommandType::GetPubKey
+            | CommandType::SignDataPSS
+            | CommandType::SignDataECDSA
+            | CommandType::DecryptECDH
+            | CommandType::DecryptOAEP
+            | CommandType::VerifyHMAC
+            | CommandType::SSHCertify
+            | CommandType::GetTemplate
+            | CommandType::DecryptOTP
+            | CommandType::CreateOTPAEAD
+            | CommandType::RandomOTPAEAD
+            | CommandType::RewrapOTPAEAD
+            | CommandType::AttestAsymmetric
+            | CommandType::WrapData
+            | CommandType::UnwrapData
+            | CommandType::SignDataEdDSA
+            | CommandType::Blink => true,
+            _ => false,
+        }
+    }
 }
 
 impl Serialize for CommandType {
@@ -2,11 +2,15 @@
 //!
 //! <https://developers.yubico.com/YubiHSM2/Commands/Put_Asymmetric.html>
 
+use clear_on_drop::clear::Clear;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Debug};
+
 use super::put_object::PutObjectParams;
 use super::{Command, Response};
 use {
     AsymmetricAlgorithm, Capability, CommandType, Connector, Domain, ObjectId, ObjectLabel,
-    Session, SessionError,
+    PrivateKey, Session, SessionError,
 };
 
 /// Put an existing asymmetric key into the `YubiHSM2`
@@ -19,30 +23,42 @@ pub fn put_asymmetric_key<C: Connector, T: Into<Vec<u8>>>(
     algorithm: AsymmetricAlgorithm,
     key_bytes: T,
 ) -> Result<ObjectId, SessionError> {
-    let data = key_bytes.into();
-
-    if data.len() != algorithm.key_len() {
-        command_fail!(
-            ProtocolError,
-            "invalid key length for {:?}: {} (expected {})",
-            algorithm,
-            data.len(),
-            algorithm.key_len()
-        );
-    }
+    let command = PutAsymmetricKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        algorithm,
+        key_bytes.into(),
+    )?;
 
     session
-        .send_encrypted_command(PutAsymmetricKeyCommand {
-            params: PutObjectParams {
-                id: key_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
-            data,
-        })
-        .map(|response| response.key_id)
+        .send_encrypted_command(command)
+        .map(Into::into)
+}
+
+/// Put an existing private key, parsed from a standard encoding (e.g. with
+/// `PrivateKey::from_pem`), into the `YubiHSM2`
+pub fn put_private_key<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    label: ObjectLabel,
+    domains: Domain,
+    capabilities: Capability,
+    private_key: &PrivateKey,
+) -> Result<ObjectId, SessionError> {
+    let command = PutAsymmetricKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        private_key.algorithm(),
+        private_key.as_bytes().to_vec(),
+    )?;
+
+    session
+        .send_encrypted_command(command)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::put_asymmetric_key`
@@ -52,13 +68,85 @@ pub(crate) struct PutAsymmetricKeyCommand {
     pub params: PutObjectParams,
 
     /// Serialized object
-    pub data: Vec<u8>,
+    pub data: KeyBytes,
+}
+
+impl PutAsymmetricKeyCommand {
+    /// Create a new command, ensuring the key is the correct length
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: AsymmetricAlgorithm,
+        data: Vec<u8>,
+    ) -> Result<Self, SessionError> {
+        let data = KeyBytes(data);
+
+        if data.len() != algorithm.key_len() {
+            command_fail!(
+                ProtocolError,
+                "invalid key length for {:?}: {} (expected {})",
+                algorithm,
+                data.len(),
+                algorithm.key_len()
+            );
+        }
+
+        Ok(Self {
+            params: PutObjectParams::new(key_id, label, domains, capabilities, algorithm),
+            data,
+        })
+    }
 }
 
 impl Command for PutAsymmetricKeyCommand {
     type ResponseType = PutAsymmetricKeyResponse;
 }
 
+/// Private key bytes, which are cleared from memory when dropped
+pub(crate) struct KeyBytes(Vec<u8>);
+
+impl KeyBytes {
+    /// Get the length of the key
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl AsRef<[u8]> for KeyBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl Debug for KeyBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Avoid leaking secrets in debug messages
+        write!(f, "KeyBytes(...)")
+    }
+}
+
+impl Drop for KeyBytes {
+    fn drop(&mut self) {
+        self.0.as_mut_slice().clear();
+    }
+}
+
+impl Serialize for KeyBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Written in a single call, so the key isn't partially copied into
+        // buffers which are reallocated (and freed without being cleared)
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(KeyBytes)
+    }
+}
+
 /// Response from `commands::put_asymmetric_key`
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct PutAsymmetricKeyResponse {
@@ -69,3 +157,9 @@ pub(crate) struct PutAsymmetricKeyResponse {
 impl Response for PutAsymmetricKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::PutAsymmetricKey;
 }
+
+impl From<PutAsymmetricKeyResponse> for ObjectId {
+    fn from(response: PutAsymmetricKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
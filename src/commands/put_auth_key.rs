@@ -23,17 +23,11 @@ pub fn put_auth_key<C: Connector, K: Into<AuthKey>>(
 ) -> Result<ObjectId, SessionError> {
     session
         .send_encrypted_command(PutAuthKeyCommand {
-            params: PutObjectParams {
-                id: key_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
+            params: PutObjectParams::new(key_id, label, domains, capabilities, algorithm),
             delegated_capabilities,
             auth_key: auth_key.into(),
         })
-        .map(|response| response.key_id)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::put_auth_key`
@@ -63,3 +57,9 @@ pub(crate) struct PutAuthKeyResponse {
 impl Response for PutAuthKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::PutAuthKey;
 }
+
+impl From<PutAuthKeyResponse> for ObjectId {
+    fn from(response: PutAuthKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
@@ -22,31 +22,18 @@ pub fn put_hmac_key<C: Connector, T: Into<Vec<u8>>>(
     algorithm: HMACAlgorithm,
     key_bytes: T,
 ) -> Result<ObjectId, SessionError> {
-    let hmac_key = key_bytes.into();
-
-    if hmac_key.len() < HMAC_MIN_KEY_SIZE || hmac_key.len() > algorithm.max_key_len() {
-        command_fail!(
-            ProtocolError,
-            "invalid key length for {:?}: {} (min {}, max {})",
-            algorithm,
-            hmac_key.len(),
-            HMAC_MIN_KEY_SIZE,
-            algorithm.max_key_len()
-        );
-    }
+    let command = PutHMACKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        algorithm,
+        key_bytes.into(),
+    )?;
 
     session
-        .send_encrypted_command(PutHMACKeyCommand {
-            params: PutObjectParams {
-                id: key_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
-            hmac_key,
-        })
-        .map(|response| response.key_id)
+        .send_encrypted_command(command)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::put_hmac_key`
@@ -59,6 +46,34 @@ pub(crate) struct PutHMACKeyCommand {
     pub hmac_key: Vec<u8>,
 }
 
+impl PutHMACKeyCommand {
+    /// Create a new command, ensuring the key is an acceptable length
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: HMACAlgorithm,
+        hmac_key: Vec<u8>,
+    ) -> Result<Self, SessionError> {
+        if hmac_key.len() < HMAC_MIN_KEY_SIZE || hmac_key.len() > algorithm.max_key_len() {
+            command_fail!(
+                ProtocolError,
+                "invalid key length for {:?}: {} (min {}, max {})",
+                algorithm,
+                hmac_key.len(),
+                HMAC_MIN_KEY_SIZE,
+                algorithm.max_key_len()
+            );
+        }
+
+        Ok(Self {
+            params: PutObjectParams::new(key_id, label, domains, capabilities, algorithm),
+            hmac_key,
+        })
+    }
+}
+
 impl Command for PutHMACKeyCommand {
     type ResponseType = PutHMACKeyResponse;
 }
@@ -73,3 +88,9 @@ pub(crate) struct PutHMACKeyResponse {
 impl Response for PutHMACKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::PutHMACKey;
 }
+
+impl From<PutHMACKeyResponse> for ObjectId {
+    fn from(response: PutHMACKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
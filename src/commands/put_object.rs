@@ -21,3 +21,22 @@ pub(crate) struct PutObjectParams {
     /// Object algorithm
     pub algorithm: Algorithm,
 }
+
+impl PutObjectParams {
+    /// Create the parameters for putting an object with the given algorithm
+    pub(crate) fn new<A: Into<Algorithm>>(
+        id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: A,
+    ) -> Self {
+        Self {
+            id,
+            label,
+            domains,
+            capabilities,
+            algorithm: algorithm.into(),
+        }
+    }
+}
@@ -21,16 +21,10 @@ pub fn put_opaque<C: Connector, T: Into<Vec<u8>>>(
 ) -> Result<ObjectId, SessionError> {
     session
         .send_encrypted_command(PutOpaqueCommand {
-            params: PutObjectParams {
-                id: object_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
+            params: PutObjectParams::new(object_id, label, domains, capabilities, algorithm),
             data: bytes.into(),
         })
-        .map(|response| response.object_id)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::put_opaque`
@@ -57,3 +51,9 @@ pub(crate) struct PutOpaqueResponse {
 impl Response for PutOpaqueResponse {
     const COMMAND_TYPE: CommandType = CommandType::PutOpaqueObject;
 }
+
+impl From<PutOpaqueResponse> for ObjectId {
+    fn from(response: PutOpaqueResponse) -> ObjectId {
+        response.object_id
+    }
+}
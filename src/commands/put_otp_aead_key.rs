@@ -21,30 +21,18 @@ pub fn put_otp_aead_key<C: Connector, T: Into<Vec<u8>>>(
     algorithm: OTPAlgorithm,
     key_bytes: T,
 ) -> Result<ObjectId, SessionError> {
-    let data = key_bytes.into();
-
-    if data.len() != algorithm.key_len() {
-        command_fail!(
-            ProtocolError,
-            "invalid key length for {:?}: {} (expected {})",
-            algorithm,
-            data.len(),
-            algorithm.key_len()
-        );
-    }
+    let command = PutOTPAEADKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        algorithm,
+        key_bytes.into(),
+    )?;
 
     session
-        .send_encrypted_command(PutOTPAEADKeyCommand {
-            params: PutObjectParams {
-                id: key_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
-            data,
-        })
-        .map(|response| response.key_id)
+        .send_encrypted_command(command)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::put_otp_aead_key`
@@ -57,6 +45,33 @@ pub(crate) struct PutOTPAEADKeyCommand {
     pub data: Vec<u8>,
 }
 
+impl PutOTPAEADKeyCommand {
+    /// Create a new command, ensuring the key is the correct length
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: OTPAlgorithm,
+        data: Vec<u8>,
+    ) -> Result<Self, SessionError> {
+        if data.len() != algorithm.key_len() {
+            command_fail!(
+                ProtocolError,
+                "invalid key length for {:?}: {} (expected {})",
+                algorithm,
+                data.len(),
+                algorithm.key_len()
+            );
+        }
+
+        Ok(Self {
+            params: PutObjectParams::new(key_id, label, domains, capabilities, algorithm),
+            data,
+        })
+    }
+}
+
 impl Command for PutOTPAEADKeyCommand {
     type ResponseType = PutOTPAEADKeyResponse;
 }
@@ -71,3 +86,9 @@ pub(crate) struct PutOTPAEADKeyResponse {
 impl Response for PutOTPAEADKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::PutOTPAEAD;
 }
+
+impl From<PutOTPAEADKeyResponse> for ObjectId {
+    fn from(response: PutOTPAEADKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
@@ -21,31 +21,19 @@ pub fn put_wrap_key<C: Connector, T: Into<Vec<u8>>>(
     algorithm: WrapAlgorithm,
     key_bytes: T,
 ) -> Result<ObjectId, SessionError> {
-    let data = key_bytes.into();
-
-    if data.len() != algorithm.key_len() {
-        command_fail!(
-            ProtocolError,
-            "invalid key length for {:?}: {} (expected {})",
-            algorithm,
-            data.len(),
-            algorithm.key_len()
-        );
-    }
+    let command = PutWrapKeyCommand::new(
+        key_id,
+        label,
+        domains,
+        capabilities,
+        delegated_capabilities,
+        algorithm,
+        key_bytes.into(),
+    )?;
 
     session
-        .send_encrypted_command(PutWrapKeyCommand {
-            params: PutObjectParams {
-                id: key_id,
-                label,
-                domains,
-                capabilities,
-                algorithm: algorithm.into(),
-            },
-            delegated_capabilities,
-            data,
-        })
-        .map(|response| response.key_id)
+        .send_encrypted_command(command)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::put_wrap_key`
@@ -61,6 +49,35 @@ pub(crate) struct PutWrapKeyCommand {
     pub data: Vec<u8>,
 }
 
+impl PutWrapKeyCommand {
+    /// Create a new command, ensuring the key is the correct length
+    pub(crate) fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        delegated_capabilities: Capability,
+        algorithm: WrapAlgorithm,
+        data: Vec<u8>,
+    ) -> Result<Self, SessionError> {
+        if data.len() != algorithm.key_len() {
+            command_fail!(
+                ProtocolError,
+                "invalid key length for {:?}: {} (expected {})",
+                algorithm,
+                data.len(),
+                algorithm.key_len()
+            );
+        }
+
+        Ok(Self {
+            params: PutObjectParams::new(key_id, label, domains, capabilities, algorithm),
+            delegated_capabilities,
+            data,
+        })
+    }
+}
+
 impl Command for PutWrapKeyCommand {
     type ResponseType = PutWrapKeyResponse;
 }
@@ -76,4 +93,10 @@ impl Response for PutWrapKeyResponse {
     const COMMAND_TYPE: CommandType = CommandType::PutWrapKey;
 }
 
-// DC not added
\ No newline at end of file
+// DC not added
+
+impl From<PutWrapKeyResponse> for ObjectId {
+    fn from(response: PutWrapKeyResponse) -> ObjectId {
+        response.key_id
+    }
+}
@@ -9,11 +9,25 @@ use session::{Session, SessionError, SessionErrorKind};
 
 /// Reset the `YubiHSM2` to a factory default state and reboot
 pub fn reset<C: Connector>(mut session: Session<C>) -> Result<(), SessionError> {
+    let result = reset_result(session.send_encrypted_command(ResetCommand {}));
+
+    // The session no longer exists once the YubiHSM2 has been reset
+    session.abandon();
+    result
+}
+
+/// Interpret the result of sending a `ResetCommand`
+pub(crate) fn reset_result(
+    result: Result<ResetResponse, SessionError>,
+) -> Result<(), SessionError> {
     // Resetting the session does not send a valid response
-    if let Err(e) = session.send_encrypted_command(ResetCommand {}) {
+    if let Err(e) = result {
         match e.kind() {
             // TODO: we don't handle the yubihsm-connector response to reset correctly
-            SessionErrorKind::ProtocolError => Ok(()),
+            SessionErrorKind::ProtocolError
+            | SessionErrorKind::ConnectorError(_)
+            | SessionErrorKind::SecureChannelError(_)
+            | SessionErrorKind::SerializationError(_) => Ok(()),
             _ => Err(e),
         }
     } else {
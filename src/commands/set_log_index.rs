@@ -33,3 +33,7 @@ pub(crate) struct SetLogIndexResponse {}
 impl Response for SetLogIndexResponse {
     const COMMAND_TYPE: CommandType = CommandType::SetLogIndex;
 }
+
+impl From<SetLogIndexResponse> for () {
+    fn from(_response: SetLogIndexResponse) {}
+}
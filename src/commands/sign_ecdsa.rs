@@ -1,27 +1,123 @@
-//! Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID
+//! Compute an ECDSA signature of the hash of the given data with the given key ID
 //!
 //! <https://developers.yubico.com/YubiHSM2/Commands/Sign_Data_Ecdsa.html>
 
 use super::{Command, Response};
+#[cfg(feature = "sha2")]
+use commands::get_pubkey::get_pubkey;
 #[cfg(all(feature = "mockhsm", not(feature = "doc")))]
 use mockhsm::MockConnector;
 #[cfg(feature = "sha2")]
 use session::{Session, SessionError};
+#[cfg(feature = "sha2")]
+use sha2::{Digest, Sha256, Sha384, Sha512};
+#[cfg(feature = "sha2")]
+use Connector;
+#[cfg(feature = "sha2")]
+use {AsymmetricAlgorithm, ECDSAAlgorithm};
+use {CommandType, ObjectId};
+
+/// Options for computing ECDSA signatures
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ECDSAOptions {
+    /// Normalize the signature so `s` is at most half the curve's order
+    /// (as required by e.g. Bitcoin's consensus rules). Unless the key's curve
+    /// is already known, this looks it up with `get_pubkey`, at the cost of an
+    /// additional command.
+    pub low_s: bool,
+}
+
+/// Compute an ECDSA signature of the hash of the given data with the given key ID, using the
+/// given hash algorithm (e.g. SHA-384 for P-384 keys)
 #[cfg(
     all(
         feature = "sha2",
         any(feature = "doc", not(feature = "mockhsm"))
     )
 )]
-use sha2::{Digest, Sha256};
+pub fn sign_ecdsa<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_with_options(session, key_id, algorithm, data, ECDSAOptions::default())
+}
+
+/// Compute an ECDSA signature of the hash of the given data with the given key ID, using the
+/// given hash algorithm (e.g. SHA-384 for P-384 keys)
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa(
+    session: &mut Session<MockConnector>,
+    key_id: ObjectId,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_with_options(session, key_id, algorithm, data, ECDSAOptions::default())
+}
+
+/// Compute an ECDSA signature of the hash of the given data with the given key ID, using the
+/// given hash algorithm and options
 #[cfg(
     all(
         feature = "sha2",
         any(feature = "doc", not(feature = "mockhsm"))
     )
 )]
-use Connector;
-use {CommandType, ObjectId};
+pub fn sign_ecdsa_with_options<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_digest(session, key_id, &digest(algorithm, data), None, options)
+}
+
+/// Compute an ECDSA signature of the hash of the given data with the given key ID, using the
+/// given hash algorithm and options
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa_with_options(
+    session: &mut Session<MockConnector>,
+    key_id: ObjectId,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    let key_algorithm = get_pubkey(session, key_id)?.algorithm;
+    let digest = mockhsm_digest(key_algorithm, algorithm, data)?;
+    sign_ecdsa_digest(session, key_id, &digest, Some(key_algorithm), options)
+}
+
+/// Compute the "digest" the MockHSM signs for the given data with a key with the given
+/// algorithm (see `sign_ecdsa_with_options`)
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub(crate) fn mockhsm_digest(
+    key_algorithm: AsymmetricAlgorithm,
+    algorithm: ECDSAAlgorithm,
+    data: &[u8],
+) -> Result<Vec<u8>, SessionError> {
+    // The MockHSM signs digests with secp256k1 keys like the `YubiHSM2`, but *ring* does not
+    // (yet) provide an API for signing a raw digest, so P-256 and P-384 keys are passed the
+    // unhashed raw message. See: https://github.com/briansmith/ring/issues/253
+    //
+    // The MockHSM hashes these messages with SHA-256 for P-256 keys and SHA-384 for P-384 keys,
+    // so other combinations are rejected rather than signing a different digest than the one
+    // requested.
+    match (key_algorithm, algorithm) {
+        (AsymmetricAlgorithm::EC_K256, _) => Ok(digest(algorithm, data)),
+        (AsymmetricAlgorithm::EC_P256, ECDSAAlgorithm::EC_ECDSA_SHA256)
+        | (AsymmetricAlgorithm::EC_P384, ECDSAAlgorithm::EC_ECDSA_SHA384) => Ok(data.into()),
+        _ => command_fail!(
+            ProtocolError,
+            "MockHSM can't compute {:?} signatures with {:?} keys",
+            algorithm,
+            key_algorithm
+        ),
+    }
+}
 
 /// Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID
 #[cfg(
@@ -35,10 +131,7 @@ pub fn sign_ecdsa_sha256<C: Connector>(
     key_id: ObjectId,
     data: &[u8],
 ) -> Result<ECDSASignature, SessionError> {
-    session.send_encrypted_command(SignDataECDSACommand {
-        key_id,
-        digest: Sha256::digest(data).as_slice().into(),
-    })
+    sign_ecdsa(session, key_id, ECDSAAlgorithm::EC_ECDSA_SHA256, data)
 }
 
 /// Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID
@@ -49,11 +142,205 @@ pub fn sign_ecdsa_sha256(
     key_id: ObjectId,
     data: &[u8],
 ) -> Result<ECDSASignature, SessionError> {
-    // When using the MockHSM, pass the unhashed raw message. This is because *ring* does not (yet)
-    // provide an API for signing a raw digest. See: https://github.com/briansmith/ring/issues/253
-    session.send_encrypted_command(SignDataECDSACommand {
+    sign_ecdsa(session, key_id, ECDSAAlgorithm::EC_ECDSA_SHA256, data)
+}
+
+/// Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID,
+/// using the given options
+#[cfg(
+    all(
+        feature = "sha2",
+        any(feature = "doc", not(feature = "mockhsm"))
+    )
+)]
+pub fn sign_ecdsa_sha256_with_options<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    data: &[u8],
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_with_options(
+        session,
+        key_id,
+        ECDSAAlgorithm::EC_ECDSA_SHA256,
+        data,
+        options,
+    )
+}
+
+/// Compute an ECDSA signature of the SHA-256 hash of the given data with the given key ID,
+/// using the given options
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa_sha256_with_options(
+    session: &mut Session<MockConnector>,
+    key_id: ObjectId,
+    data: &[u8],
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_with_options(
+        session,
+        key_id,
+        ECDSAAlgorithm::EC_ECDSA_SHA256,
+        data,
+        options,
+    )
+}
+
+/// Compute an ECDSA signature of a digest the caller has already computed (e.g. a Keccak-256
+/// hash for Ethereum) with the given key ID.
+///
+/// The key's curve is looked up with `get_pubkey`, and the digest must be no longer than the
+/// curve's scalar size (i.e. it can't be truncated by the `YubiHSM2`).
+#[cfg(
+    all(
+        feature = "sha2",
+        any(feature = "doc", not(feature = "mockhsm"))
+    )
+)]
+pub fn sign_ecdsa_prehashed<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    digest: &[u8],
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_prehashed_with_options(session, key_id, digest, ECDSAOptions::default())
+}
+
+/// Compute an ECDSA signature of a digest the caller has already computed with the given
+/// key ID. The MockHSM can only sign digests with secp256k1 keys, so this returns an error
+/// for other keys after checking the key and digest.
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa_prehashed(
+    session: &mut Session<MockConnector>,
+    key_id: ObjectId,
+    digest: &[u8],
+) -> Result<ECDSASignature, SessionError> {
+    sign_ecdsa_prehashed_with_options(session, key_id, digest, ECDSAOptions::default())
+}
+
+/// Compute an ECDSA signature of a digest the caller has already computed with the given
+/// key ID, using the given options. See `sign_ecdsa_prehashed`.
+#[cfg(
+    all(
+        feature = "sha2",
+        any(feature = "doc", not(feature = "mockhsm"))
+    )
+)]
+pub fn sign_ecdsa_prehashed_with_options<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    digest: &[u8],
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    let algorithm = prehashed_key_algorithm(session, key_id, digest)?;
+    sign_ecdsa_digest(session, key_id, digest, Some(algorithm), options)
+}
+
+/// Compute an ECDSA signature of a digest the caller has already computed with the given
+/// key ID, using the given options. The MockHSM can only sign digests with secp256k1 keys,
+/// so this returns an error for other keys after checking the key and digest.
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub fn sign_ecdsa_prehashed_with_options(
+    session: &mut Session<MockConnector>,
+    key_id: ObjectId,
+    digest: &[u8],
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    let algorithm = prehashed_key_algorithm(session, key_id, digest)?;
+
+    // *ring* can't sign a raw digest (see `sign_ecdsa_with_options`), and hashing it again
+    // would produce a signature over a different digest than the one requested
+    if algorithm != AsymmetricAlgorithm::EC_K256 {
+        command_fail!(
+            ProtocolError,
+            "MockHSM can only sign prehashed digests with secp256k1 keys (key {} algorithm: {:?})",
+            key_id,
+            algorithm
+        );
+    }
+
+    sign_ecdsa_digest(session, key_id, digest, Some(algorithm), options)
+}
+
+/// Look up the algorithm of the given key, ensuring it's an ECDSA key which can sign the
+/// given digest
+#[cfg(feature = "sha2")]
+fn prehashed_key_algorithm<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    digest: &[u8],
+) -> Result<AsymmetricAlgorithm, SessionError> {
+    let algorithm = get_pubkey(session, key_id)?.algorithm;
+
+    match algorithm {
+        AsymmetricAlgorithm::RSA2048
+        | AsymmetricAlgorithm::RSA3072
+        | AsymmetricAlgorithm::RSA4096
+        | AsymmetricAlgorithm::EC_ED25519 => command_fail!(
+            ProtocolError,
+            "key {} is not an ECDSA key (algorithm: {:?})",
+            key_id,
+            algorithm
+        ),
+        _ => (),
+    }
+
+    if digest.is_empty() || digest.len() > algorithm.key_len() {
+        command_fail!(
+            ProtocolError,
+            "invalid {}-byte digest for {:?} key (expected 1-{} bytes)",
+            digest.len(),
+            algorithm,
+            algorithm.key_len()
+        );
+    }
+
+    Ok(algorithm)
+}
+
+/// Hash the given data with the hash function of the given algorithm
+#[cfg(feature = "sha2")]
+pub(crate) fn digest(algorithm: ECDSAAlgorithm, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        ECDSAAlgorithm::EC_ECDSA_SHA256 => Sha256::digest(data).as_slice().into(),
+        ECDSAAlgorithm::EC_ECDSA_SHA384 => Sha384::digest(data).as_slice().into(),
+        ECDSAAlgorithm::EC_ECDSA_SHA512 => Sha512::digest(data).as_slice().into(),
+    }
+}
+
+/// Sign the given digest, applying the given options to the resulting signature. The key's
+/// algorithm is looked up if it's needed and wasn't given.
+#[cfg(feature = "sha2")]
+fn sign_ecdsa_digest<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    digest: &[u8],
+    algorithm: Option<AsymmetricAlgorithm>,
+    options: ECDSAOptions,
+) -> Result<ECDSASignature, SessionError> {
+    let signature = session.send_encrypted_command(SignDataECDSACommand {
         key_id,
-        digest: data.into(),
+        digest: digest.into(),
+    })?;
+
+    if !options.low_s {
+        return Ok(signature);
+    }
+
+    let algorithm = match algorithm {
+        Some(algorithm) => algorithm,
+        None => get_pubkey(session, key_id)?.algorithm,
+    };
+
+    signature.to_low_s(algorithm).map_err(|e| {
+        command_err!(
+            ProtocolError,
+            "couldn't normalize {:?} signature: {}",
+            algorithm,
+            e
+        )
     })
 }
 
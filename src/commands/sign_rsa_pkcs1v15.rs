@@ -1,12 +1,28 @@
-//! Compute an RSASSA-PKCS#1v1.5 signature of the SHA-256 hash of the given data
+//! Compute an RSASSA-PKCS#1v1.5 signature of the hash of the given data
 //!
 //! <https://developers.yubico.com/YubiHSM2/Commands/Sign_Data_Pkcs1.html>
 
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
 use super::{Command, Response};
 use session::{Session, SessionError};
-use sha2::{Digest, Sha256};
 use Connector;
-use {CommandType, ObjectId};
+use {CommandType, ObjectId, RSAPKCS1Algorithm};
+
+/// Compute an RSASSA-PKCS#1v1.5 signature of the hash of the given data with the given key ID,
+/// using the given hash algorithm
+///
+/// WARNING: This method has not been tested and is not confirmed to actually work! Use at your
+/// own risk!
+pub fn sign_rsa_pkcs1v15<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    algorithm: RSAPKCS1Algorithm,
+    data: &[u8],
+) -> Result<RSAPKCS1Signature, SessionError> {
+    session.send_encrypted_command(SignDataPKCS1Command::new(key_id, algorithm, data))
+}
 
 /// Compute an RSASSA-PKCS#1v1.5 signature of the SHA-256 hash of the given data.
 ///
@@ -17,10 +33,7 @@ pub fn sign_rsa_pkcs1v15_sha256<C: Connector>(
     key_id: ObjectId,
     data: &[u8],
 ) -> Result<RSAPKCS1Signature, SessionError> {
-    session.send_encrypted_command(SignDataPKCS1Command {
-        key_id,
-        digest: Sha256::digest(data).as_slice().into(),
-    })
+    sign_rsa_pkcs1v15(session, key_id, RSAPKCS1Algorithm::RSA_PKCS1_SHA256, data)
 }
 
 /// Request parameters for `commands::sign_rsa_pkcs1v15*`
@@ -33,6 +46,21 @@ pub(crate) struct SignDataPKCS1Command {
     pub digest: Vec<u8>,
 }
 
+impl SignDataPKCS1Command {
+    /// Create a command to sign the hash of the given data. The `YubiHSM2`
+    /// encodes the digest as a `DigestInfo` itself, so only the raw digest is sent
+    pub(crate) fn new(key_id: ObjectId, algorithm: RSAPKCS1Algorithm, data: &[u8]) -> Self {
+        let digest = match algorithm {
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA1 => Sha1::digest(data).to_vec(),
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA256 => Sha256::digest(data).to_vec(),
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA384 => Sha384::digest(data).to_vec(),
+            RSAPKCS1Algorithm::RSA_PKCS1_SHA512 => Sha512::digest(data).to_vec(),
+        };
+
+        Self { key_id, digest }
+    }
+}
+
 impl Command for SignDataPKCS1Command {
     type ResponseType = RSAPKCS1Signature;
 }
@@ -76,3 +104,39 @@ impl Into<Vec<u8>> for RSAPKCS1Signature {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::hash::{hash, MessageDigest};
+
+    #[test]
+    fn digest_test() {
+        let command = SignDataPKCS1Command::new(1, RSAPKCS1Algorithm::RSA_PKCS1_SHA1, b"abc");
+
+        assert_eq!(
+            command.digest,
+            [
+                0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+                0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+            ].as_ref()
+        );
+    }
+
+    /// The command carries the raw digest of the message (without a
+    /// `DigestInfo` prefix) for every supported hash
+    #[test]
+    fn raw_digest_test() {
+        for &(algorithm, digest) in &[
+            (RSAPKCS1Algorithm::RSA_PKCS1_SHA1, MessageDigest::sha1()),
+            (RSAPKCS1Algorithm::RSA_PKCS1_SHA256, MessageDigest::sha256()),
+            (RSAPKCS1Algorithm::RSA_PKCS1_SHA384, MessageDigest::sha384()),
+            (RSAPKCS1Algorithm::RSA_PKCS1_SHA512, MessageDigest::sha512()),
+        ] {
+            let command = SignDataPKCS1Command::new(1, algorithm, b"abc");
+
+            assert_eq!(command.digest.len(), algorithm.digest_len());
+            assert_eq!(command.digest, hash(digest, b"abc").unwrap().to_vec());
+        }
+    }
+}
@@ -1,49 +1,59 @@
-//! Compute an RSASSA-PSS signature of the SHA-256 hash of the given data
+//! Compute an RSASSA-PSS signature of the hash of the given data
 //!
 //! <https://developers.yubico.com/YubiHSM2/Commands/Sign_Data_Pss.html>
 
-use byteorder::{BigEndian, ByteOrder};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
 
 use super::{Command, Response};
 use session::{Session, SessionError};
-use sha2::{Digest, Sha256};
 use Connector;
-use {Algorithm, CommandType, ObjectId};
+use {CommandType, MGF1Algorithm, ObjectId, RSAPSSAlgorithm};
 
 /// Maximum message size supported for RSASSA-PSS
+#[deprecated(note = "messages are now hashed before they're sent, so their size isn't limited")]
 pub const RSA_PSS_MAX_MESSAGE_SIZE: usize = 0xFFFF;
 
-/// Compute an RSASSA-PSS signature of the SHA-256 hash of the given data with the given key ID.
-///
-/// WARNING: This method has not been tested and is not confirmed to actually work! Use at your
-/// own risk!
-pub fn sign_rsa_pss_sha256<C: Connector>(
+/// Options for computing RSASSA-PSS signatures
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RSAPSSOptions {
+    /// Hash algorithm used by MGF1 (default: the signature's hash algorithm)
+    pub mgf1: Option<MGF1Algorithm>,
+
+    /// Salt length in bytes (default: the length of the signature's digest)
+    pub salt_len: Option<u16>,
+}
+
+/// Compute an RSASSA-PSS signature of the hash of the given data with the given key ID, using
+/// the given hash algorithm
+pub fn sign_rsa_pss<C: Connector>(
     session: &mut Session<C>,
     key_id: ObjectId,
+    algorithm: RSAPSSAlgorithm,
     data: &[u8],
 ) -> Result<RSAPSSSignature, SessionError> {
-    if data.len() > RSA_PSS_MAX_MESSAGE_SIZE {
-        command_fail!(
-            ProtocolError,
-            "message too large to be signed (max: {})",
-            RSA_PSS_MAX_MESSAGE_SIZE
-        );
-    }
-
-    let mut hasher = Sha256::default();
+    sign_rsa_pss_with_options(session, key_id, algorithm, data, RSAPSSOptions::default())
+}
 
-    let mut length = [0u8; 2];
-    BigEndian::write_u16(&mut length, data.len() as u16);
-    hasher.input(&length);
-    hasher.input(data);
-    let digest = hasher.result();
+/// Compute an RSASSA-PSS signature of the hash of the given data with the given key ID, using
+/// the given hash algorithm and options
+pub fn sign_rsa_pss_with_options<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    algorithm: RSAPSSAlgorithm,
+    data: &[u8],
+    options: RSAPSSOptions,
+) -> Result<RSAPSSSignature, SessionError> {
+    session.send_encrypted_command(SignDataPSSCommand::new(key_id, algorithm, data, options))
+}
 
-    session.send_encrypted_command(SignDataPSSCommand {
-        key_id,
-        mgf1_hash_alg: Algorithm::MGF1_SHA256,
-        salt_len: digest.as_slice().len() as u16,
-        digest: digest.as_slice().into(),
-    })
+/// Compute an RSASSA-PSS signature of the SHA-256 hash of the given data with the given key ID.
+pub fn sign_rsa_pss_sha256<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    data: &[u8],
+) -> Result<RSAPSSSignature, SessionError> {
+    sign_rsa_pss(session, key_id, RSAPSSAlgorithm::RSA_PSS_SHA256, data)
 }
 
 /// Request parameters for `commands::sign_rsa_pss*`
@@ -53,7 +63,7 @@ pub(crate) struct SignDataPSSCommand {
     pub key_id: ObjectId,
 
     /// Hash algorithm to use for MGF1
-    pub mgf1_hash_alg: Algorithm,
+    pub mgf1_hash_alg: MGF1Algorithm,
 
     /// Salt length
     pub salt_len: u16,
@@ -62,6 +72,32 @@ pub(crate) struct SignDataPSSCommand {
     pub digest: Vec<u8>,
 }
 
+impl SignDataPSSCommand {
+    /// Create a command to sign the hash of the given data
+    pub(crate) fn new(
+        key_id: ObjectId,
+        algorithm: RSAPSSAlgorithm,
+        data: &[u8],
+        options: RSAPSSOptions,
+    ) -> Self {
+        let digest = match algorithm {
+            RSAPSSAlgorithm::RSA_PSS_SHA1 => Sha1::digest(data).to_vec(),
+            RSAPSSAlgorithm::RSA_PSS_SHA256 => Sha256::digest(data).to_vec(),
+            RSAPSSAlgorithm::RSA_PSS_SHA384 => Sha384::digest(data).to_vec(),
+            RSAPSSAlgorithm::RSA_PSS_SHA512 => Sha512::digest(data).to_vec(),
+        };
+
+        Self {
+            key_id,
+            mgf1_hash_alg: options.mgf1.unwrap_or_else(|| algorithm.mgf1()),
+            salt_len: options
+                .salt_len
+                .unwrap_or(algorithm.digest_len() as u16),
+            digest,
+        }
+    }
+}
+
 impl Command for SignDataPSSCommand {
     type ResponseType = RSAPSSSignature;
 }
@@ -105,3 +141,116 @@ impl Into<Vec<u8>> for RSAPSSSignature {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::hash::MessageDigest;
+    use openssl::md::{Md, MdRef};
+    use openssl::pkey::PKey;
+    use openssl::pkey_ctx::PkeyCtx;
+    use openssl::rsa::{Padding, Rsa};
+    use openssl::sign::{RsaPssSaltlen, Verifier};
+
+    #[test]
+    fn pss_options_test() {
+        let command = SignDataPSSCommand::new(
+            1,
+            RSAPSSAlgorithm::RSA_PSS_SHA384,
+            b"abc",
+            RSAPSSOptions::default(),
+        );
+
+        assert_eq!(command.mgf1_hash_alg, MGF1Algorithm::MGF1_SHA384);
+        assert_eq!(command.salt_len, 48);
+        assert_eq!(command.digest, Sha384::digest(b"abc").as_slice());
+
+        let command = SignDataPSSCommand::new(
+            1,
+            RSAPSSAlgorithm::RSA_PSS_SHA1,
+            b"abc",
+            RSAPSSOptions {
+                mgf1: Some(MGF1Algorithm::MGF1_SHA256),
+                salt_len: Some(0),
+            },
+        );
+
+        assert_eq!(command.mgf1_hash_alg, MGF1Algorithm::MGF1_SHA256);
+        assert_eq!(command.salt_len, 0);
+        assert_eq!(command.digest.len(), 20);
+    }
+
+    /// Sign the command's digest the way the `YubiHSM2` does (i.e. with the
+    /// command's MGF1 hash and salt length), and verify the signature of the
+    /// original message with OpenSSL
+    #[test]
+    fn pss_signature_test() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+        let vectors = [
+            (
+                RSAPSSAlgorithm::RSA_PSS_SHA256,
+                RSAPSSOptions::default(),
+                MessageDigest::sha256(),
+                MessageDigest::sha256(),
+                32,
+            ),
+            (
+                RSAPSSAlgorithm::RSA_PSS_SHA384,
+                RSAPSSOptions {
+                    mgf1: Some(MGF1Algorithm::MGF1_SHA1),
+                    salt_len: Some(0),
+                },
+                MessageDigest::sha384(),
+                MessageDigest::sha1(),
+                0,
+            ),
+        ];
+
+        for &(algorithm, options, digest, mgf1, salt_len) in &vectors {
+            let command = SignDataPSSCommand::new(1, algorithm, b"abc", options);
+
+            let mut ctx = PkeyCtx::new(&key).unwrap();
+            ctx.sign_init().unwrap();
+            ctx.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+            ctx.set_signature_md(digest_md(command.digest.len())).unwrap();
+            ctx.set_rsa_mgf1_md(mgf1_md(command.mgf1_hash_alg)).unwrap();
+            ctx.set_rsa_pss_saltlen(RsaPssSaltlen::custom(i32::from(command.salt_len)))
+                .unwrap();
+
+            let mut signature = vec![];
+            ctx.sign_to_vec(&command.digest, &mut signature).unwrap();
+
+            let mut verifier = Verifier::new(digest, &key).unwrap();
+            verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+            verifier.set_rsa_mgf1_md(mgf1).unwrap();
+            verifier
+                .set_rsa_pss_saltlen(RsaPssSaltlen::custom(salt_len))
+                .unwrap();
+            verifier.update(b"abc").unwrap();
+
+            assert!(verifier.verify(&signature).unwrap());
+        }
+    }
+
+    /// Get the OpenSSL digest the `YubiHSM2` would use for a digest of the given length
+    fn digest_md(len: usize) -> &'static MdRef {
+        match len {
+            20 => Md::sha1(),
+            32 => Md::sha256(),
+            48 => Md::sha384(),
+            64 => Md::sha512(),
+            _ => panic!("unexpected digest length: {}", len),
+        }
+    }
+
+    /// Get the OpenSSL digest for the given MGF1 algorithm
+    fn mgf1_md(algorithm: MGF1Algorithm) -> &'static MdRef {
+        match algorithm {
+            MGF1Algorithm::MGF1_SHA1 => Md::sha1(),
+            MGF1Algorithm::MGF1_SHA256 => Md::sha256(),
+            MGF1Algorithm::MGF1_SHA384 => Md::sha384(),
+            MGF1Algorithm::MGF1_SHA512 => Md::sha512(),
+        }
+    }
+}
@@ -15,15 +15,9 @@ where
     C: Connector,
     M: Into<WrapMessage>,
 {
-    let WrapMessage { nonce, ciphertext } = wrap_message.into();
-
     session
-        .send_encrypted_command(UnwrapDataCommand {
-            wrap_key_id,
-            nonce,
-            ciphertext,
-        })
-        .map(|response| response.0)
+        .send_encrypted_command(UnwrapDataCommand::new(wrap_key_id, wrap_message.into()))
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::unwrap_data`
@@ -39,6 +33,19 @@ pub(crate) struct UnwrapDataCommand {
     pub ciphertext: Vec<u8>,
 }
 
+impl UnwrapDataCommand {
+    /// Create a command to decrypt the given message
+    pub(crate) fn new(wrap_key_id: ObjectId, wrap_message: WrapMessage) -> Self {
+        let WrapMessage { nonce, ciphertext } = wrap_message;
+
+        Self {
+            wrap_key_id,
+            nonce,
+            ciphertext,
+        }
+    }
+}
+
 impl Command for UnwrapDataCommand {
     type ResponseType = UnwrapDataResponse;
 }
@@ -50,3 +57,9 @@ pub(crate) struct UnwrapDataResponse(pub(crate) Vec<u8>);
 impl Response for UnwrapDataResponse {
     const COMMAND_TYPE: CommandType = CommandType::UnwrapData;
 }
+
+impl From<UnwrapDataResponse> for Vec<u8> {
+    fn from(response: UnwrapDataResponse) -> Vec<u8> {
+        response.0
+    }
+}
@@ -18,17 +18,13 @@ where
     D: Into<Vec<u8>>,
     T: Into<HMACTag>,
 {
-    let result = session.send_encrypted_command(VerifyHMACCommand {
-        key_id,
-        tag: tag.into(),
-        data: data.into(),
-    })?;
-
-    if result.0 == 1 {
-        Ok(())
-    } else {
-        Err(command_err!(ResponseError, "HMAC verification failure"))
-    }
+    session
+        .send_encrypted_command(VerifyHMACCommand {
+            key_id,
+            tag: tag.into(),
+            data: data.into(),
+        })?
+        .into_result()
 }
 
 /// Request parameters for `commands::hmac`
@@ -52,6 +48,17 @@ impl Command for VerifyHMACCommand {
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct VerifyHMACResponse(pub(crate) u8);
 
+impl VerifyHMACResponse {
+    /// Did the HMAC tag verify successfully?
+    pub(crate) fn into_result(self) -> Result<(), SessionError> {
+        if self.0 == 1 {
+            Ok(())
+        } else {
+            Err(command_err!(ResponseError, "HMAC verification failure"))
+        }
+    }
+}
+
 impl Response for VerifyHMACResponse {
     const COMMAND_TYPE: CommandType = CommandType::VerifyHMAC;
 }
@@ -16,7 +16,7 @@ pub fn wrap_data<C: Connector>(
             wrap_key_id,
             plaintext,
         })
-        .map(|response| response.0)
+        .map(Into::into)
 }
 
 /// Request parameters for `commands::wrap_data`
@@ -40,3 +40,9 @@ pub(crate) struct WrapDataResponse(pub(crate) WrapMessage);
 impl Response for WrapDataResponse {
     const COMMAND_TYPE: CommandType = CommandType::WrapData;
 }
+
+impl From<WrapDataResponse> for WrapMessage {
+    fn from(response: WrapDataResponse) -> WrapMessage {
+        response.0
+    }
+}
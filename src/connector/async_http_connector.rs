@@ -0,0 +1,207 @@
+//! Asynchronous version of the minimalist yubihsm-connector HTTP client,
+//! built on tokio.
+//!
+//! Requests are serialized and responses parsed using the same code as the
+//! synchronous `HttpConnector`. Sockets are opened lazily and returned to the
+//! connector after each successful request (unless the connector asked us to
+//! close the connection), so a request which fails (or times out)
+//! transparently causes a new connection to be opened for the next.
+
+use futures::future::{self, Loop};
+use futures::Future;
+use std::{
+    net::SocketAddr,
+    str,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{io, net::TcpStream, timer::Timeout};
+use uuid::Uuid;
+
+use super::http_connector::{get_request, post_request, resolve};
+use super::http_response::{HttpResponse, ResponseLimits, ResponseReader};
+use super::{AsyncConnector, ConnectorError, ConnectorFuture, HttpConfig, Status};
+
+/// Size of the buffer used for each individual read from the socket
+const READ_CHUNK_SIZE: usize = 1024;
+
+/// Asynchronous HTTP(-ish) connector which supports the minimal parts of the
+/// protocol required to communicate with the yubihsm-connector service.
+pub struct AsyncHttpConnector {
+    /// Host we're configured to connect to (i.e. the "Host" HTTP header)
+    host: String,
+
+    /// Resolved address of the connector
+    addr: SocketAddr,
+
+    /// Configured timeout as a rust duration
+    timeout: Duration,
+
+    /// Size limits for responses from the connector
+    limits: ResponseLimits,
+
+    /// Idle socket to the connector process (if we have one)
+    idle_socket: Arc<Mutex<Option<TcpStream>>>,
+}
+
+impl AsyncConnector for AsyncHttpConnector {
+    type Config = HttpConfig;
+
+    /// Open a connection to a yubihsm-connector
+    fn open(config: Self::Config) -> ConnectorFuture<Self> {
+        #[cfg(feature = "tls")]
+        {
+            if config.tls.is_some() {
+                return Box::new(future::err(connector_err!(
+                    ConfigError,
+                    "HTTPS is not yet supported by AsyncHttpConnector"
+                )));
+            }
+        }
+
+        let host = format!("{}:{}", config.addr, config.port);
+
+        // TODO: DNS resolution is presently blocking
+        let addr = match resolve(&host) {
+            Ok(addr) => addr,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        let connector = Self {
+            host,
+            addr,
+            timeout: Duration::from_millis(config.timeout_ms),
+            limits: ResponseLimits::from(&config),
+            idle_socket: Arc::new(Mutex::new(None)),
+        };
+
+        // Connect eagerly so connection failures are reported by `open`
+        let socket = connector.checkout_socket();
+        let timeout = connector.timeout;
+
+        with_timeout(
+            socket.map(move |socket| {
+                connector.checkin_socket(socket);
+                connector
+            }),
+            timeout,
+        )
+    }
+
+    /// GET /connector/status returning the result as connector::Status
+    fn status(&self) -> ConnectorFuture<Status> {
+        let request = match get_request(&self.host, "/connector/status") {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        Box::new(
+            self.request(request)
+                .and_then(|response| Status::parse(str::from_utf8(&response)?)),
+        )
+    }
+
+    /// POST /connector/api with a given command message
+    fn send_command(&self, uuid: Uuid, cmd: Vec<u8>) -> ConnectorFuture<Vec<u8>> {
+        match post_request(&self.host, "/connector/api", uuid, cmd) {
+            Ok(request) => self.request(request),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+}
+
+impl AsyncHttpConnector {
+    /// Send a serialized HTTP request to the connector, returning the body
+    /// of the response
+    fn request(&self, request: Vec<u8>) -> ConnectorFuture<Vec<u8>> {
+        let host = self.host.clone();
+        let limits = self.limits;
+        let idle_socket = Arc::clone(&self.idle_socket);
+        let request_start = Instant::now();
+
+        let response = self
+            .checkout_socket()
+            .and_then(|socket| io::write_all(socket, request).from_err())
+            .and_then(move |(socket, _)| read_response(socket, limits))
+            .map(move |(socket, response)| {
+                let elapsed_time = Instant::now().duration_since(request_start);
+
+                debug!(
+                    "yubihsm-connector: host={} async request t={}ms",
+                    host,
+                    elapsed_time.as_secs() * 1000 + u64::from(elapsed_time.subsec_millis())
+                );
+
+                if response.keep_alive {
+                    *idle_socket.lock().unwrap() = Some(socket);
+                }
+
+                response.body
+            });
+
+        with_timeout(response, self.timeout)
+    }
+
+    /// Take the idle socket to the connector, opening a new one if necessary
+    fn checkout_socket(&self) -> ConnectorFuture<TcpStream> {
+        if let Some(socket) = self.idle_socket.lock().unwrap().take() {
+            return Box::new(future::ok(socket));
+        }
+
+        debug!("yubihsm-connector: host={} connecting", self.host);
+        Box::new(TcpStream::connect(&self.addr).from_err())
+    }
+
+    /// Return a socket to the connector so it can be reused
+    fn checkin_socket(&self, socket: TcpStream) {
+        *self.idle_socket.lock().unwrap() = Some(socket);
+    }
+}
+
+/// Read an HTTP response from the given socket, returning the socket
+/// along with the response
+fn read_response(
+    socket: TcpStream,
+    limits: ResponseLimits,
+) -> ConnectorFuture<(TcpStream, HttpResponse)> {
+    Box::new(future::loop_fn(
+        (socket, ResponseReader::new(limits)),
+        |(socket, mut reader)| {
+            io::read(socket, vec![0u8; READ_CHUNK_SIZE])
+                .from_err()
+                .and_then(move |(socket, chunk, nbytes)| {
+                    if nbytes == 0 {
+                        return Ok(Loop::Break((socket, reader.finish()?)));
+                    }
+
+                    if reader.feed(&chunk[..nbytes])? {
+                        Ok(Loop::Break((socket, reader.into_response())))
+                    } else {
+                        Ok(Loop::Continue((socket, reader)))
+                    }
+                })
+        },
+    ))
+}
+
+/// Fail the given future with a `ConnectionFailed` error if it doesn't
+/// complete within the given timeout
+fn with_timeout<F, T>(future: F, timeout: Duration) -> ConnectorFuture<T>
+where
+    F: Future<Item = T, Error = ConnectorError> + Send + 'static,
+    T: Send + 'static,
+{
+    Box::new(Timeout::new(future, timeout).map_err(move |e| {
+        if e.is_elapsed() {
+            connector_err!(
+                ConnectionFailed,
+                "timed out after {}ms",
+                timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis())
+            )
+        } else if e.is_inner() {
+            e.into_inner().unwrap()
+        } else {
+            connector_err!(IoError, "timer error: {}", e)
+        }
+    }))
+}
@@ -16,6 +16,10 @@ pub enum ConnectorErrorKind {
     #[fail(display = "invalid URL")]
     InvalidURL,
 
+    /// Connector configuration (e.g. TLS certificates or keys) is not valid
+    #[fail(display = "invalid configuration")]
+    ConfigError,
+
     /// Connection to yubihsm-connector failed
     #[fail(display = "connection failed")]
     ConnectionFailed,
@@ -33,6 +37,21 @@ pub enum ConnectorErrorKind {
     ResponseError,
 }
 
+impl ConnectorErrorKind {
+    /// Could this error be fixed by reconnecting and trying again?
+    /// Errors resulting from invalid configuration or requests are fatal.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            ConnectorErrorKind::ConnectionFailed
+            | ConnectorErrorKind::IoError
+            | ConnectorErrorKind::ResponseError => true,
+            ConnectorErrorKind::InvalidURL
+            | ConnectorErrorKind::ConfigError
+            | ConnectorErrorKind::RequestError => false,
+        }
+    }
+}
+
 /// Create a new connector error with a formatted message
 macro_rules! connector_err {
     ($kind:ident, $msg:expr) => {
@@ -0,0 +1,371 @@
+//! Connector which fails over between several yubihsm-connectors, e.g.
+//! several connectors in front of `YubiHSM2`s with identical key material.
+//!
+//! Endpoints are health checked with `GET /connector/status` when they are
+//! selected. An endpoint which fails a health check or a request is marked
+//! down for a configurable interval, during which other endpoints are
+//! preferred. Requests are never retried on another endpoint: encrypted
+//! sessions are specific to a `YubiHSM2`, so a failed request marks the
+//! `Session` as unhealthy, and its reconnect logic then calls
+//! `FailoverConnector::reconnect` to pick a new endpoint and re-authenticate.
+//! Until then, requests fail without selecting another endpoint.
+//!
+//! Requests and health checks are performed without holding the connector's
+//! internal lock, so a slow endpoint doesn't block other threads.
+
+use rand::{self, Rng};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use super::http_connector::resolve_all;
+use super::{Connector, ConnectorError, HttpConfig, HttpConnector, Status};
+use securechannel::Challenge;
+
+/// Default amount of time an unhealthy endpoint is marked down for (30 seconds)
+pub const DEFAULT_DOWN_INTERVAL_MILLIS: u64 = 30_000;
+
+/// Status message returned from healthy connectors
+const CONNECTOR_STATUS_OK: &str = "OK";
+
+/// Function which opens a connection to a failover endpoint
+type ConnectorFactory<C> = Arc<Fn() -> Result<C, ConnectorError> + Send + Sync>;
+
+/// Order in which failover endpoints are selected
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum FailoverStrategy {
+    /// Always prefer the first healthy endpoint, in the order they were given
+    InOrder,
+
+    /// Start with a randomly chosen endpoint, and move to the next one each
+    /// time we reconnect. This spreads sessions across endpoints.
+    RoundRobin,
+}
+
+impl Default for FailoverStrategy {
+    fn default() -> Self {
+        FailoverStrategy::InOrder
+    }
+}
+
+/// Configuration options for `FailoverConnector`, generic over the
+/// configuration type of its endpoints
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FailoverConfig<T = HttpConfig> {
+    /// Endpoints to fail over between
+    pub endpoints: Vec<T>,
+
+    /// Order in which endpoints are selected
+    pub strategy: FailoverStrategy,
+
+    /// Amount of time an unhealthy endpoint is marked down for in milliseconds
+    pub down_interval_ms: u64,
+}
+
+impl<T: Default> Default for FailoverConfig<T> {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![T::default()],
+            strategy: FailoverStrategy::default(),
+            down_interval_ms: DEFAULT_DOWN_INTERVAL_MILLIS,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for FailoverConfig<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failover(")?;
+
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", endpoint)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Connector which fails over between several endpoints, generic over the
+/// endpoints' `Connector` type.
+pub struct FailoverConnector<C = HttpConnector>
+where
+    C: Connector,
+{
+    /// Order in which endpoints are selected
+    strategy: FailoverStrategy,
+
+    /// Amount of time an unhealthy endpoint is marked down for
+    down_interval: Duration,
+
+    /// Mutable state of the connector
+    state: Mutex<FailoverState<C>>,
+}
+
+/// Mutable state of a `FailoverConnector`
+struct FailoverState<C: Connector> {
+    /// Endpoints we can fail over between
+    endpoints: Vec<Endpoint<C>>,
+
+    /// Index of the endpoint requests are presently sent to
+    active: Option<usize>,
+
+    /// Index of the endpoint to try first when selecting a new one
+    next: usize,
+}
+
+/// An individual endpoint of a `FailoverConnector`
+struct Endpoint<C: Connector> {
+    /// Description of the endpoint for logging and errors
+    name: String,
+
+    /// Opens a connection to this endpoint
+    open_connector: ConnectorFactory<C>,
+
+    /// Connection to this endpoint, if it's presently open
+    connector: Option<Arc<C>>,
+
+    /// If this endpoint is marked down, the time when it can next be tried
+    down_until: Option<Instant>,
+}
+
+impl<C> Connector for FailoverConnector<C>
+where
+    C: Connector,
+    C::Config: Clone + Send + Sync + 'static,
+{
+    type Config = FailoverConfig<C::Config>;
+
+    /// Open a connection to the first healthy endpoint
+    fn open(config: Self::Config) -> Result<Self, ConnectorError> {
+        let mut connector = Self::new(
+            config.strategy,
+            Duration::from_millis(config.down_interval_ms),
+        );
+
+        for endpoint_config in config.endpoints {
+            connector.add_endpoint(&endpoint_config.to_string(), move || {
+                C::open(endpoint_config.clone())
+            });
+        }
+
+        connector.reconnect()?;
+        Ok(connector)
+    }
+
+    /// Select a healthy endpoint, failing over if the current one is down
+    fn reconnect(&self) -> Result<(), ConnectorError> {
+        self.select()
+    }
+
+    /// GET /connector/status from the active endpoint
+    fn status(&self) -> Result<Status, ConnectorError> {
+        self.with_active(|connector| connector.status())
+    }
+
+    /// POST /connector/api to the active endpoint
+    fn send_command(&self, uuid: Uuid, cmd: Vec<u8>) -> Result<Vec<u8>, ConnectorError> {
+        self.with_active(|connector| connector.send_command(uuid, cmd))
+    }
+
+    /// Host challenge pinned by the active endpoint (if any)
+    fn host_challenge(&self) -> Option<Challenge> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .active
+            .and_then(|i| state.endpoints[i].connector.as_ref())
+            .and_then(|connector| connector.host_challenge())
+    }
+}
+
+impl FailoverConnector<HttpConnector> {
+    /// Resolve the given yubihsm-connector's `addr`, adding each of the
+    /// addresses it resolves to as a separate endpoint (i.e. DNS round robin)
+    pub fn add_resolved_endpoints(&mut self, config: &HttpConfig) -> Result<(), ConnectorError> {
+        let name = config.to_string();
+
+        for addr in resolve_all(&format!("{}:{}", config.addr, config.port))? {
+            let config = config.clone();
+
+            self.add_endpoint(&format!("{} ({})", name, addr), move || {
+                HttpConnector::open_addr(config.clone(), Some(addr))
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: Connector> FailoverConnector<C> {
+    /// Create a new `FailoverConnector` with no endpoints. Endpoints must be
+    /// added with `add_endpoint`, and one of them selected with `reconnect`,
+    /// before it's used.
+    pub fn new(strategy: FailoverStrategy, down_interval: Duration) -> Self {
+        Self {
+            strategy,
+            down_interval,
+            state: Mutex::new(FailoverState {
+                endpoints: vec![],
+                active: None,
+                next: 0,
+            }),
+        }
+    }
+
+    /// Add an endpoint which is connected to using the given function
+    pub fn add_endpoint<F>(&mut self, name: &str, open_connector: F)
+    where
+        F: Fn() -> Result<C, ConnectorError> + Send + Sync + 'static,
+    {
+        let state = self.state.get_mut().unwrap();
+
+        state.endpoints.push(Endpoint {
+            name: name.to_owned(),
+            open_connector: Arc::new(open_connector),
+            connector: None,
+            down_until: None,
+        });
+
+        if self.strategy == FailoverStrategy::RoundRobin {
+            state.next = rand::thread_rng().gen_range(0, state.endpoints.len());
+        }
+    }
+
+    /// Name of the endpoint requests are presently sent to (if any)
+    pub fn active_endpoint(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.active.map(|i| state.endpoints[i].name.clone())
+    }
+
+    /// Select a healthy endpoint, opening a new connection to it.
+    ///
+    /// Endpoints which are marked down are only tried if every other
+    /// endpoint is unhealthy.
+    fn select(&self) -> Result<(), ConnectorError> {
+        let candidates = {
+            let mut state = self.state.lock().unwrap();
+            let num_endpoints = state.endpoints.len();
+
+            if num_endpoints == 0 {
+                connector_fail!(InvalidURL, "no failover endpoints configured");
+            }
+
+            if let Some(active) = state.active.take() {
+                state.endpoints[active].connector = None;
+            }
+
+            let start = match self.strategy {
+                FailoverStrategy::InOrder => 0,
+                FailoverStrategy::RoundRobin => state.next,
+            };
+
+            let now = Instant::now();
+            let (mut up, down): (Vec<usize>, Vec<usize>) = (0..num_endpoints)
+                .map(|i| (start + i) % num_endpoints)
+                .partition(|&i| state.endpoints[i].down_until.map_or(true, |t| t <= now));
+
+            up.extend(down);
+
+            up.into_iter()
+                .map(|i| {
+                    let endpoint = &state.endpoints[i];
+                    (i, endpoint.name.clone(), Arc::clone(&endpoint.open_connector))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut errors = vec![];
+
+        for (i, name, open_connector) in candidates {
+            match check_endpoint(&open_connector) {
+                Ok(connector) => {
+                    debug!("yubihsm-connector: failover to {}", name);
+
+                    let mut state = self.state.lock().unwrap();
+                    let num_endpoints = state.endpoints.len();
+                    state.endpoints[i].connector = Some(Arc::new(connector));
+                    state.endpoints[i].down_until = None;
+                    state.active = Some(i);
+                    state.next = (i + 1) % num_endpoints;
+                    return Ok(());
+                }
+                Err(e) => {
+                    debug!("yubihsm-connector: marking {} down: {}", name, e);
+                    errors.push(format!("{}: {}", name, e));
+                    self.state.lock().unwrap().endpoints[i].down_until =
+                        Some(Instant::now() + self.down_interval);
+                }
+            }
+        }
+
+        connector_fail!(
+            ConnectionFailed,
+            "no healthy connectors available ({})",
+            errors.join("; ")
+        )
+    }
+
+    /// Perform a request with the active endpoint, marking it down if the
+    /// request fails. A new endpoint is only selected by `reconnect`.
+    fn with_active<F, T>(&self, f: F) -> Result<T, ConnectorError>
+    where
+        F: FnOnce(&C) -> Result<T, ConnectorError>,
+    {
+        let (active, connector) = {
+            let state = self.state.lock().unwrap();
+
+            match state.active {
+                Some(i) => (i, Arc::clone(state.endpoints[i].connector.as_ref().unwrap())),
+                None => connector_fail!(
+                    ConnectionFailed,
+                    "no active failover endpoint (reconnect to select one)"
+                ),
+            }
+        };
+
+        let result = f(&connector);
+
+        if let Err(ref e) = result {
+            let mut state = self.state.lock().unwrap();
+
+            // Another thread may have reconnected while the request was in flight
+            let still_active = state.active == Some(active) && state.endpoints[active]
+                .connector
+                .as_ref()
+                .map_or(false, |c| Arc::ptr_eq(c, &connector));
+
+            if still_active {
+                debug!(
+                    "yubihsm-connector: marking {} down: {}",
+                    state.endpoints[active].name, e
+                );
+
+                let endpoint = &mut state.endpoints[active];
+                endpoint.connector = None;
+                endpoint.down_until = Some(Instant::now() + self.down_interval);
+                state.active = None;
+            }
+        }
+
+        result
+    }
+}
+
+/// Open a new connection to an endpoint and ensure it's healthy
+fn check_endpoint<C: Connector>(open_connector: &ConnectorFactory<C>) -> Result<C, ConnectorError> {
+    let connector = open_connector()?;
+    let status = connector.status()?;
+
+    if status.message != CONNECTOR_STATUS_OK {
+        connector_fail!(ConnectionFailed, "bad status response: {}", status.message);
+    }
+
+    Ok(connector)
+}
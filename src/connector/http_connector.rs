@@ -11,37 +11,28 @@
 
 #![allow(unknown_lints, write_with_newline)]
 
+#[cfg(feature = "tls")]
+use native_tls::TlsStream;
 use std::{
-    cell::RefCell,
     fmt::{self, Write as FmtWrite},
-    io::{Read, Write as IoWrite},
-    net::{TcpStream, ToSocketAddrs},
+    io::{self, Read, Write as IoWrite},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
     str,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use uuid::Uuid;
 
+use super::http_response::{
+    HttpResponse, ResponseLimits, ResponseReader, DEFAULT_MAX_BODY_SIZE, DEFAULT_MAX_HEADERS_SIZE,
+};
+#[cfg(feature = "tls")]
+use super::tls::{TlsConfig, TlsContext};
 use super::{Connector, ConnectorError, Status, USER_AGENT};
 
 /// Default timeouts for reading and writing (5 seconds)
 pub const DEFAULT_TIMEOUT_MILLIS: u64 = 5000;
 
-/// Maximum size of the HTTP response from the connector
-pub const MAX_RESPONSE_SIZE: usize = 4096;
-
-/// Delimiter string that separates HTTP headers from bodies
-const HEADER_DELIMITER: &[u8] = b"\r\n\r\n";
-
-/// HTTP response status indicating success
-const HTTP_SUCCESS_STATUS: &str = "HTTP/1.1 200 OK";
-
-/// The Content-Length Header
-const CONTENT_LENGTH_HEADER: &str = "Content-Length: ";
-
-/// The Transfer-Encoding Header
-const TRANSFER_ENCODING_HEADER: &str = "Transfer-Encoding: ";
-
 /// Write consistent `debug!(...) lines for connectors
 macro_rules! http_debug {
     ($connector:expr, $msg:expr) => {
@@ -54,6 +45,7 @@ macro_rules! http_debug {
 
 /// Configuration options for this connector
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct HttpConfig {
     /// Address of the connector (IP address or DNS name)
     pub addr: String,
@@ -63,6 +55,17 @@ pub struct HttpConfig {
 
     /// Timeout for connecting, reading, and writing in milliseconds
     pub timeout_ms: u64,
+
+    /// Maximum size of the status line and headers of HTTP responses
+    pub max_headers_size: usize,
+
+    /// Maximum size of the (decoded) body of HTTP responses
+    pub max_body_size: usize,
+
+    /// Connect to the connector using HTTPS with the given TLS options.
+    /// If `None`, plaintext HTTP is used.
+    #[cfg(feature = "tls")]
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for HttpConfig {
@@ -76,27 +79,79 @@ impl Default for HttpConfig {
 
             // 5 seconds
             timeout_ms: DEFAULT_TIMEOUT_MILLIS,
+
+            // 8 kB
+            max_headers_size: DEFAULT_MAX_HEADERS_SIZE,
+
+            // 64 kB
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+
+            // Plaintext HTTP
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 }
 
 impl fmt::Display for HttpConfig {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "http://{}:{}", self.addr, self.port)
+        write!(f, "{}://{}:{}", self.scheme(), self.addr, self.port)
+    }
+}
+
+impl HttpConfig {
+    /// URL scheme used to connect to the connector
+    #[cfg(feature = "tls")]
+    fn scheme(&self) -> &'static str {
+        if self.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    /// URL scheme used to connect to the connector
+    #[cfg(not(feature = "tls"))]
+    fn scheme(&self) -> &'static str {
+        "http"
+    }
+}
+
+impl<'a> From<&'a HttpConfig> for ResponseLimits {
+    fn from(config: &'a HttpConfig) -> ResponseLimits {
+        ResponseLimits {
+            max_headers_size: config.max_headers_size,
+            max_body_size: config.max_body_size,
+        }
     }
 }
 
 /// HTTP(-ish) connector which supports the minimal parts of the protocol
 /// required to communicate with the yubihsm-connector service.
+///
+/// If the `tls` cargo feature is enabled and `HttpConfig::tls` is set,
+/// requests are made over HTTPS.
 pub struct HttpConnector {
     /// Host we're configured to connect to (i.e. the "Host" HTTP header)
     host: String,
 
+    /// Address to connect to. If `None`, `host` is resolved each time we
+    /// connect and each of its addresses is tried in turn.
+    addr: Option<SocketAddr>,
+
     /// Configured timeout as a rust duration
     timeout: Duration,
 
-    /// Socket to the connector process
-    socket: Arc<Mutex<RefCell<TcpStream>>>,
+    /// Size limits for responses from the connector
+    limits: ResponseLimits,
+
+    /// TLS configuration, if we're connecting over HTTPS
+    #[cfg(feature = "tls")]
+    tls: Option<TlsContext>,
+
+    /// Socket to the connector process. `None` if the connector closed the
+    /// connection or a request failed, in which case we reconnect lazily.
+    socket: Arc<Mutex<Option<Stream>>>,
 }
 
 impl Connector for HttpConnector {
@@ -104,22 +159,14 @@ impl Connector for HttpConnector {
 
     /// Open a connection to a yubihsm-connector
     fn open(config: Self::Config) -> Result<Self, ConnectorError> {
-        let host = format!("{}:{}", config.addr, config.port);
-        let timeout = Duration::from_millis(config.timeout_ms);
-        let socket = connect(&host, timeout)?;
-
-        Ok(Self {
-            host,
-            timeout,
-            socket: Arc::new(Mutex::new(RefCell::new(socket))),
-        })
+        Self::open_addr(config, None)
     }
 
     /// Reconnect to yubihsm-connector, closing the existing connection
     fn reconnect(&self) -> Result<(), ConnectorError> {
-        let socket_cell = self.socket.lock().unwrap();
-        let new_socket = connect(&self.host, self.timeout)?;
-        socket_cell.replace(new_socket);
+        let mut socket = self.socket.lock().unwrap();
+        *socket = None;
+        *socket = Some(self.connect()?);
         Ok(())
     }
 
@@ -135,41 +182,110 @@ impl Connector for HttpConnector {
     }
 }
 
-/// Open a socket to yubihsm-connector
-fn connect(host: &str, timeout: Duration) -> Result<TcpStream, ConnectorError> {
-    // Resolve DNS, and for now pick the first available address
-    // TODO: round robin DNS support?
-    let socketaddr = &host.to_socket_addrs()?.next().ok_or_else(|| {
-        connector_err!(
+/// Resolve the address of a yubihsm-connector, picking the first address
+#[cfg(feature = "async")]
+pub(super) fn resolve(host: &str) -> Result<SocketAddr, ConnectorError> {
+    Ok(resolve_all(host)?[0])
+}
+
+/// Resolve all addresses of a yubihsm-connector
+pub(super) fn resolve_all(host: &str) -> Result<Vec<SocketAddr>, ConnectorError> {
+    let addrs: Vec<SocketAddr> = host.to_socket_addrs()?.collect();
+
+    if addrs.is_empty() {
+        connector_fail!(
             InvalidURL,
             "couldn't resolve DNS for {}",
             host.split(':').next().unwrap()
-        )
-    })?;
+        );
+    }
+
+    Ok(addrs)
+}
+
+/// Open a socket to yubihsm-connector. Unless a specific address is given,
+/// each of the host's addresses is tried in turn.
+fn connect(
+    host: &str,
+    addr: Option<SocketAddr>,
+    timeout: Duration,
+) -> Result<TcpStream, ConnectorError> {
+    let addrs = match addr {
+        Some(addr) => vec![addr],
+        None => resolve_all(host)?,
+    };
+
+    let mut last_error = None;
 
-    let socket = TcpStream::connect_timeout(socketaddr, timeout)?;
-    socket.set_read_timeout(Some(timeout))?;
-    socket.set_write_timeout(Some(timeout))?;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(socket) => {
+                socket.set_read_timeout(Some(timeout))?;
+                socket.set_write_timeout(Some(timeout))?;
+                return Ok(socket);
+            }
+            Err(e) => {
+                debug!(
+                    "yubihsm-connector: host={} error connecting to {}: {}",
+                    host, addr, e
+                );
+                last_error = Some(e);
+            }
+        }
+    }
 
-    Ok(socket)
+    Err(last_error.unwrap().into())
 }
 
 impl HttpConnector {
-    /// Make an HTTP GET request to the yubihsm-connector
-    fn get(&self, path: &str) -> Result<Vec<u8>, ConnectorError> {
-        let mut request = String::new();
+    /// Open a connection to a yubihsm-connector at a specific address (e.g.
+    /// one of several `config.addr` resolves to). The "Host" header and TLS
+    /// domain are still derived from `config`.
+    pub(super) fn open_addr(
+        config: HttpConfig,
+        addr: Option<SocketAddr>,
+    ) -> Result<Self, ConnectorError> {
+        let connector = Self {
+            host: format!("{}:{}", config.addr, config.port),
+            addr,
+            timeout: Duration::from_millis(config.timeout_ms),
+            limits: ResponseLimits::from(&config),
+            #[cfg(feature = "tls")]
+            tls: match config.tls {
+                Some(ref tls_config) => Some(TlsContext::new(tls_config, &config.addr)?),
+                None => None,
+            },
+            socket: Arc::new(Mutex::new(None)),
+        };
 
-        write!(request, "GET {} HTTP/1.1\r\n", path)?;
-        write!(request, "Host: {}\r\n", self.host)?;
-        write!(request, "User-Agent: {}\r\n", USER_AGENT)?;
-        write!(request, "Content-Length: 0\r\n\r\n")?;
+        connector.reconnect()?;
+        Ok(connector)
+    }
 
-        let socket = self.socket.lock().unwrap();
+    /// Open a socket to yubihsm-connector, performing a TLS handshake if
+    /// we're configured to use HTTPS
+    #[cfg(feature = "tls")]
+    fn connect(&self) -> Result<Stream, ConnectorError> {
+        let socket = connect(&self.host, self.addr, self.timeout)?;
 
-        let request_start = Instant::now();
-        socket.borrow_mut().write_all(request.as_bytes())?;
+        match self.tls {
+            Some(ref tls) => Ok(Stream::Tls(Box::new(tls.connect(socket)?))),
+            None => Ok(Stream::Tcp(socket)),
+        }
+    }
 
-        let response = ResponseReader::read(&mut socket.borrow_mut())?;
+    /// Open a socket to yubihsm-connector
+    #[cfg(not(feature = "tls"))]
+    fn connect(&self) -> Result<Stream, ConnectorError> {
+        Ok(Stream::Tcp(connect(&self.host, self.addr, self.timeout)?))
+    }
+
+    /// Make an HTTP GET request to the yubihsm-connector
+    fn get(&self, path: &str) -> Result<Vec<u8>, ConnectorError> {
+        let request = get_request(&self.host, path)?;
+
+        let request_start = Instant::now();
+        let response = self.request(&request)?;
         let elapsed_time = Instant::now().duration_since(request_start);
 
         http_debug!(
@@ -179,30 +295,15 @@ impl HttpConnector {
             elapsed_time.as_secs() * 1000 + u64::from(elapsed_time.subsec_millis())
         );
 
-        Ok(response.into())
+        Ok(response)
     }
 
     /// Make an HTTP POST request to the yubihsm-connector
-    fn post(&self, path: &str, uuid: Uuid, mut body: Vec<u8>) -> Result<Vec<u8>, ConnectorError> {
-        let mut headers = String::new();
-
-        write!(headers, "POST {} HTTP/1.1\r\n", path)?;
-        write!(headers, "Host: {}\r\n", self.host)?;
-        write!(headers, "User-Agent: {}\r\n", USER_AGENT)?;
-        write!(headers, "X-Request-ID: {}\r\n", uuid)?;
-        write!(headers, "Content-Length: {}\r\n\r\n", body.len())?;
-
-        // It's friendlier to Nagle's algorithm if we combine the request
-        // headers and body, especially if the request fits in a single packet
-        let mut request: Vec<u8> = headers.into();
-        request.append(&mut body);
-
-        let socket = self.socket.lock().unwrap();
+    fn post(&self, path: &str, uuid: Uuid, body: Vec<u8>) -> Result<Vec<u8>, ConnectorError> {
+        let request = post_request(&self.host, path, uuid, body)?;
 
         let request_start = Instant::now();
-        socket.borrow_mut().write_all(&request)?;
-
-        let response = ResponseReader::read(&mut socket.borrow_mut())?;
+        let response = self.request(&request)?;
         let elapsed_time = Instant::now().duration_since(request_start);
 
         http_debug!(
@@ -213,144 +314,119 @@ impl HttpConnector {
             elapsed_time.as_secs() * 1000 + u64::from(elapsed_time.subsec_millis())
         );
 
-        Ok(response.into())
-    }
-}
-
-/// Buffered reader for short (i.e. 8kB or less) HTTP responses
-struct ResponseReader {
-    /// Data buffer
-    buffer: [u8; MAX_RESPONSE_SIZE],
-
-    /// Position inside of the data buffer
-    pos: usize,
-
-    /// Position at which the body begins
-    body_offset: Option<usize>,
-
-    /// Length of the body (if we're received it)
-    content_length: usize,
-}
-
-impl ResponseReader {
-    /// Create a new response buffer
-    pub fn read(socket: &mut TcpStream) -> Result<Self, ConnectorError> {
-        let mut buffer = Self {
-            buffer: [0u8; MAX_RESPONSE_SIZE],
-            pos: 0,
-            body_offset: None,
-            content_length: 0,
-        };
-
-        buffer.read_headers(socket)?;
-        buffer.read_body(socket)?;
-
-        Ok(buffer)
+        Ok(response)
     }
 
-    /// Read some data into the internal buffer
-    fn fill_buffer(&mut self, socket: &mut TcpStream) -> Result<usize, ConnectorError> {
-        let nbytes = socket.read(&mut self.buffer[..])?;
-        self.pos += nbytes;
-        Ok(nbytes)
-    }
+    /// Send a serialized HTTP request, returning the body of the response.
+    ///
+    /// The connection is kept alive between requests unless the connector
+    /// asks us to close it or an error occurs, in which case a new connection
+    /// is opened for the next request.
+    fn request(&self, request: &[u8]) -> Result<Vec<u8>, ConnectorError> {
+        let mut socket = self.socket.lock().unwrap();
+
+        if socket.is_none() {
+            http_debug!(self, "reconnecting");
+            *socket = Some(self.connect()?);
+        }
 
-    /// Read the HTTP response headers
-    fn read_headers(&mut self, socket: &mut TcpStream) -> Result<(), ConnectorError> {
-        assert!(self.body_offset.is_none(), "already read headers!");
-
-        loop {
-            self.fill_buffer(socket)?;
-
-            // Scan the buffer for the header delimiter
-            // TODO: this is less efficient than it should be
-            let mut offset = 0;
-            while self.buffer[offset..].len() > HEADER_DELIMITER.len() {
-                if self.buffer[offset..].starts_with(HEADER_DELIMITER) {
-                    self.body_offset = Some(offset + HEADER_DELIMITER.len());
-                    break;
-                } else {
-                    offset += 1;
+        match send_request(socket.as_mut().unwrap(), request, self.limits) {
+            Ok(response) => {
+                if !response.keep_alive {
+                    http_debug!(self, "connection closed by connector");
+                    *socket = None;
                 }
-            }
 
-            if self.body_offset.is_some() {
-                break;
-            } else if self.pos + 1 >= MAX_RESPONSE_SIZE {
-                connector_fail!(
-                    ResponseError,
-                    "exceeded {}-byte response limit reading headers",
-                    MAX_RESPONSE_SIZE
-                );
+                Ok(response.body)
+            }
+            Err(e) => {
+                *socket = None;
+                Err(e)
             }
         }
-
-        self.parse_headers()
     }
+}
 
-    /// Parse the HTTP headers, extracting the Content-Length
-    fn parse_headers(&mut self) -> Result<(), ConnectorError> {
-        let body_offset = self.body_offset.unwrap();
-        let header_str = str::from_utf8(&self.buffer[..body_offset])?;
-
-        let mut header_iter = header_str.split("\r\n");
-
-        // Ensure we got a 200 OK status
-        match header_iter.next() {
-            Some(HTTP_SUCCESS_STATUS) => (),
-            Some(status) => connector_fail!(
-                ResponseError,
-                "unexpected HTTP response status: \"{}\"",
-                status
-            ),
-            None => connector_fail!(ResponseError, "HTTP response status line missing!"),
-        }
-
-        for header in header_iter {
-            if header.starts_with(CONTENT_LENGTH_HEADER) {
-                let content_length: usize = header[CONTENT_LENGTH_HEADER.len()..].parse()?;
+/// Connection to yubihsm-connector, which may be encrypted with TLS
+enum Stream {
+    /// Plaintext HTTP connection
+    Tcp(TcpStream),
 
-                if MAX_RESPONSE_SIZE - body_offset < content_length {
-                    connector_fail!(
-                        ResponseError,
-                        "response body length too large for buffer ({} bytes)",
-                        content_length
-                    );
-                }
+    /// HTTPS connection
+    #[cfg(feature = "tls")]
+    Tls(Box<TlsStream<TcpStream>>),
+}
 
-                self.content_length = content_length;
-            } else if header.starts_with(TRANSFER_ENCODING_HEADER) {
-                let transfer_encoding = &header[TRANSFER_ENCODING_HEADER.len()..];
-                connector_fail!(
-                    ResponseError,
-                    "connector sent unsupported transfer encoding: {}",
-                    transfer_encoding
-                );
-            }
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref mut socket) => socket.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.read(buf),
         }
-
-        Ok(())
     }
+}
 
-    /// Read the response body into the internal buffer
-    fn read_body(&mut self, socket: &mut TcpStream) -> Result<(), ConnectorError> {
-        let body_end =
-            self.content_length + self.body_offset.expect("not ready to read the body yet");
-
-        while self.pos < body_end {
-            self.fill_buffer(socket)?;
+impl IoWrite for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref mut socket) => socket.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.write(buf),
         }
+    }
 
-        Ok(())
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Stream::Tcp(ref mut socket) => socket.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.flush(),
+        }
     }
 }
 
-impl Into<Vec<u8>> for ResponseReader {
-    fn into(self) -> Vec<u8> {
-        let body_offset = self
-            .body_offset
-            .expect("we should've already read the body");
+/// Write a request to the given socket and read the response
+fn send_request(
+    socket: &mut Stream,
+    request: &[u8],
+    limits: ResponseLimits,
+) -> Result<HttpResponse, ConnectorError> {
+    socket.write_all(request)?;
+    socket.flush()?;
+    ResponseReader::new(limits).read(socket)
+}
 
-        Vec::from(&self.buffer[body_offset..self.pos])
-    }
+/// Serialize an HTTP GET request to the yubihsm-connector
+pub(super) fn get_request(host: &str, path: &str) -> Result<Vec<u8>, ConnectorError> {
+    let mut request = String::new();
+
+    write!(request, "GET {} HTTP/1.1\r\n", path)?;
+    write!(request, "Host: {}\r\n", host)?;
+    write!(request, "User-Agent: {}\r\n", USER_AGENT)?;
+    write!(request, "Content-Length: 0\r\n\r\n")?;
+
+    Ok(request.into())
+}
+
+/// Serialize an HTTP POST request to the yubihsm-connector
+pub(super) fn post_request(
+    host: &str,
+    path: &str,
+    uuid: Uuid,
+    mut body: Vec<u8>,
+) -> Result<Vec<u8>, ConnectorError> {
+    let mut headers = String::new();
+
+    write!(headers, "POST {} HTTP/1.1\r\n", path)?;
+    write!(headers, "Host: {}\r\n", host)?;
+    write!(headers, "User-Agent: {}\r\n", USER_AGENT)?;
+    write!(headers, "X-Request-ID: {}\r\n", uuid)?;
+    write!(headers, "Content-Length: {}\r\n\r\n", body.len())?;
+
+    // It's friendlier to Nagle's algorithm if we combine the request
+    // headers and body, especially if the request fits in a single packet
+    let mut request: Vec<u8> = headers.into();
+    request.append(&mut body);
+
+    Ok(request)
 }
@@ -0,0 +1,554 @@
+//! Incremental parser for HTTP responses from yubihsm-connector
+//!
+//! Supports the subset of HTTP/1.x needed to talk to the connector (or a
+//! proxy in front of it): `Content-Length` delimited, chunked, and
+//! read-until-close bodies, along with `Connection` keep-alive handling.
+
+use std::{io::Read, str};
+
+use super::ConnectorError;
+
+/// Default maximum size of the HTTP response headers (8 kB)
+pub const DEFAULT_MAX_HEADERS_SIZE: usize = 8192;
+
+/// Default maximum size of an HTTP response body (64 kB)
+pub const DEFAULT_MAX_BODY_SIZE: usize = 65_536;
+
+/// Maximum length of a chunk size line in a chunked response
+const MAX_CHUNK_LINE_SIZE: usize = 1024;
+
+/// Size of the buffer used for each individual read from the socket
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Delimiter string that separates HTTP headers from bodies
+const HEADER_DELIMITER: &[u8] = b"\r\n\r\n";
+
+/// Delimiter string that separates lines in HTTP messages
+const LINE_DELIMITER: &[u8] = b"\r\n";
+
+/// Limits on the size of HTTP responses
+#[derive(Copy, Clone, Debug)]
+pub(super) struct ResponseLimits {
+    /// Maximum size of the status line and headers
+    pub max_headers_size: usize,
+
+    /// Maximum size of the (decoded) body
+    pub max_body_size: usize,
+}
+
+/// A complete HTTP response
+#[derive(Debug)]
+pub(super) struct HttpResponse {
+    /// Body of the response
+    pub body: Vec<u8>,
+
+    /// Can the connection be reused for subsequent requests?
+    pub keep_alive: bool,
+}
+
+/// States of the response parser
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum State {
+    /// Reading the status line and headers
+    Headers,
+
+    /// Reading a body with a known number of bytes remaining
+    Body(usize),
+
+    /// Reading a body terminated by the connection closing
+    UntilClose,
+
+    /// Reading the size line of a chunk
+    ChunkSize,
+
+    /// Reading a chunk with a known number of bytes remaining
+    ChunkData(usize),
+
+    /// Reading the CRLF which follows a chunk
+    ChunkEnd,
+
+    /// Reading trailer headers after the last chunk
+    Trailers,
+
+    /// The response is complete
+    Done,
+}
+
+/// Incremental HTTP response reader: data can be fed in arbitrarily sized
+/// pieces as it's read from the socket
+pub(super) struct ResponseReader {
+    /// Size limits for the response
+    limits: ResponseLimits,
+
+    /// Data which has been received but not yet parsed
+    buffer: Vec<u8>,
+
+    /// Offset in the buffer where scanning for the header delimiter resumes
+    scan_offset: usize,
+
+    /// Current parser state
+    state: State,
+
+    /// Can the connection be reused once this response is complete?
+    keep_alive: bool,
+
+    /// Body of the response (decoded from chunked encoding if necessary)
+    body: Vec<u8>,
+}
+
+impl ResponseReader {
+    /// Create a new response reader
+    pub fn new(limits: ResponseLimits) -> Self {
+        Self {
+            limits,
+            buffer: vec![],
+            scan_offset: 0,
+            state: State::Headers,
+            keep_alive: false,
+            body: vec![],
+        }
+    }
+
+    /// Read a complete response from the given socket
+    pub fn read<R: Read>(mut self, socket: &mut R) -> Result<HttpResponse, ConnectorError> {
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+
+        loop {
+            let nbytes = socket.read(&mut chunk)?;
+
+            if nbytes == 0 {
+                return self.finish();
+            }
+
+            if self.feed(&chunk[..nbytes])? {
+                return Ok(self.into_response());
+            }
+        }
+    }
+
+    /// Process data received from the socket. Returns `true` if the response
+    /// is complete, in which case it can be obtained with `into_response()`
+    pub fn feed(&mut self, data: &[u8]) -> Result<bool, ConnectorError> {
+        if self.state == State::Done {
+            connector_fail!(ResponseError, "received data after the end of the response");
+        }
+
+        self.buffer.extend_from_slice(data);
+        while self.advance()? {}
+
+        if self.state != State::Done {
+            return Ok(false);
+        }
+
+        if !self.buffer.is_empty() {
+            connector_fail!(
+                ResponseError,
+                "{} unexpected bytes after the end of the response",
+                self.buffer.len()
+            );
+        }
+
+        Ok(true)
+    }
+
+    /// Handle the connection being closed by the remote peer
+    pub fn finish(mut self) -> Result<HttpResponse, ConnectorError> {
+        match self.state {
+            State::UntilClose | State::Done => {
+                self.state = State::Done;
+                Ok(self.into_response())
+            }
+            state => connector_fail!(
+                ResponseError,
+                "connection closed before response was complete (state: {:?})",
+                state
+            ),
+        }
+    }
+
+    /// Obtain the parsed response.
+    ///
+    /// Panics if the response is incomplete.
+    pub fn into_response(self) -> HttpResponse {
+        assert_eq!(self.state, State::Done, "response is incomplete");
+
+        HttpResponse {
+            body: self.body,
+            keep_alive: self.keep_alive,
+        }
+    }
+
+    /// Attempt to make progress parsing the buffered data, returning
+    /// whether or not any progress was made
+    fn advance(&mut self) -> Result<bool, ConnectorError> {
+        match self.state {
+            State::Headers => self.read_headers(),
+            State::Body(remaining) => {
+                let remaining = remaining - self.read_body(remaining)?;
+
+                self.state = if remaining == 0 {
+                    State::Done
+                } else {
+                    State::Body(remaining)
+                };
+
+                Ok(false)
+            }
+            State::UntilClose => {
+                let nbytes = self.buffer.len();
+                self.read_body(nbytes)?;
+                Ok(false)
+            }
+            State::ChunkSize => match self.read_line()? {
+                Some(line) => {
+                    let chunk_size = parse_chunk_size(&line)?;
+
+                    self.state = if chunk_size == 0 {
+                        State::Trailers
+                    } else {
+                        State::ChunkData(chunk_size)
+                    };
+
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            State::ChunkData(remaining) => {
+                let remaining = remaining - self.read_body(remaining)?;
+
+                if remaining == 0 {
+                    self.state = State::ChunkEnd;
+                    Ok(true)
+                } else {
+                    self.state = State::ChunkData(remaining);
+                    Ok(false)
+                }
+            }
+            State::ChunkEnd => {
+                if self.buffer.len() < LINE_DELIMITER.len() {
+                    return Ok(false);
+                }
+
+                if !self.buffer.starts_with(LINE_DELIMITER) {
+                    connector_fail!(ResponseError, "malformed chunked response body");
+                }
+
+                self.buffer.drain(..LINE_DELIMITER.len());
+                self.state = State::ChunkSize;
+                Ok(true)
+            }
+            State::Trailers => match self.read_line()? {
+                Some(ref line) if line.is_empty() => {
+                    self.state = State::Done;
+                    Ok(false)
+                }
+                // Trailers aren't used by the connector, so ignore them
+                Some(_) => Ok(true),
+                None => Ok(false),
+            },
+            State::Done => Ok(false),
+        }
+    }
+
+    /// Scan the buffer for the end of the headers, parsing them if found
+    fn read_headers(&mut self) -> Result<bool, ConnectorError> {
+        let header_end = match find(&self.buffer[self.scan_offset..], HEADER_DELIMITER) {
+            Some(pos) => self.scan_offset + pos + HEADER_DELIMITER.len(),
+            None => {
+                // The delimiter may be split across reads, so back up a bit
+                self.scan_offset = self
+                    .buffer
+                    .len()
+                    .saturating_sub(HEADER_DELIMITER.len() - 1);
+
+                if self.buffer.len() > self.limits.max_headers_size {
+                    connector_fail!(
+                        ResponseError,
+                        "exceeded {}-byte limit reading response headers",
+                        self.limits.max_headers_size
+                    );
+                }
+
+                return Ok(false);
+            }
+        };
+
+        if header_end > self.limits.max_headers_size {
+            connector_fail!(
+                ResponseError,
+                "exceeded {}-byte limit reading response headers",
+                self.limits.max_headers_size
+            );
+        }
+
+        let headers: Vec<u8> = self.buffer.drain(..header_end).collect();
+        self.parse_headers(str::from_utf8(&headers[..header_end - HEADER_DELIMITER.len()])?)?;
+
+        Ok(true)
+    }
+
+    /// Parse the status line and headers, determining how to read the body
+    fn parse_headers(&mut self, headers: &str) -> Result<(), ConnectorError> {
+        let mut lines = headers.split("\r\n");
+
+        let status_line = lines.next().unwrap();
+        let (version, status_code) = parse_status_line(status_line)?;
+
+        if status_code != 200 {
+            connector_fail!(
+                ResponseError,
+                "unexpected HTTP response status: \"{}\"",
+                status_line
+            );
+        }
+
+        // HTTP/1.1 connections are persistent by default, whereas HTTP/1.0
+        // connections are only persistent if explicitly requested
+        self.keep_alive = version == "HTTP/1.1";
+
+        let mut content_length = None;
+        let mut chunked = false;
+
+        for line in lines {
+            let colon = line
+                .find(':')
+                .ok_or_else(|| connector_err!(ResponseError, "malformed header: \"{}\"", line))?;
+
+            let name = line[..colon].trim();
+            let value = line[colon + 1..].trim();
+
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let length: usize = value.parse()?;
+
+                if content_length.is_some() && content_length != Some(length) {
+                    connector_fail!(ResponseError, "conflicting Content-Length headers");
+                }
+
+                content_length = Some(length);
+            } else if name.eq_ignore_ascii_case("Transfer-Encoding") {
+                for encoding in value.split(',').map(|e| e.trim()) {
+                    if encoding.eq_ignore_ascii_case("chunked") {
+                        chunked = true;
+                    } else if !encoding.eq_ignore_ascii_case("identity") {
+                        connector_fail!(
+                            ResponseError,
+                            "connector sent unsupported transfer encoding: {}",
+                            encoding
+                        );
+                    }
+                }
+            } else if name.eq_ignore_ascii_case("Connection") {
+                for option in value.split(',').map(|o| o.trim()) {
+                    if option.eq_ignore_ascii_case("close") {
+                        self.keep_alive = false;
+                    } else if option.eq_ignore_ascii_case("keep-alive") {
+                        self.keep_alive = true;
+                    }
+                }
+            }
+        }
+
+        // Transfer-Encoding overrides Content-Length (RFC 7230 Section 3.3.3)
+        self.state = if chunked {
+            State::ChunkSize
+        } else if let Some(length) = content_length {
+            if length > self.limits.max_body_size {
+                connector_fail!(
+                    ResponseError,
+                    "response body too large ({} bytes, max {})",
+                    length,
+                    self.limits.max_body_size
+                );
+            }
+
+            if length == 0 {
+                State::Done
+            } else {
+                State::Body(length)
+            }
+        } else {
+            // Without a length, the body is terminated by the connection closing
+            self.keep_alive = false;
+            State::UntilClose
+        };
+
+        Ok(())
+    }
+
+    /// Move up to `max_bytes` from the buffer into the body, returning the
+    /// number of bytes moved
+    fn read_body(&mut self, max_bytes: usize) -> Result<usize, ConnectorError> {
+        let nbytes = max_bytes.min(self.buffer.len());
+
+        if self.body.len() + nbytes > self.limits.max_body_size {
+            connector_fail!(
+                ResponseError,
+                "exceeded {}-byte limit reading response body",
+                self.limits.max_body_size
+            );
+        }
+
+        self.body.extend(self.buffer.drain(..nbytes));
+        Ok(nbytes)
+    }
+
+    /// Read a CRLF-terminated line from the buffer (if a complete one is available)
+    fn read_line(&mut self) -> Result<Option<String>, ConnectorError> {
+        match find(&self.buffer, LINE_DELIMITER) {
+            Some(pos) => {
+                let line = String::from_utf8(self.buffer[..pos].into())
+                    .map_err(|e| connector_err!(ResponseError, "{}", e))?;
+
+                self.buffer.drain(..pos + LINE_DELIMITER.len());
+                Ok(Some(line))
+            }
+            None => {
+                if self.buffer.len() > MAX_CHUNK_LINE_SIZE {
+                    connector_fail!(ResponseError, "chunked encoding line too long");
+                }
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Parse an HTTP status line, returning the version and status code.
+/// Any reason phrase is accepted.
+fn parse_status_line(status_line: &str) -> Result<(&str, u16), ConnectorError> {
+    let mut parts = status_line.splitn(3, ' ');
+
+    let version = parts.next().unwrap();
+
+    if version != "HTTP/1.1" && version != "HTTP/1.0" {
+        connector_fail!(
+            ResponseError,
+            "unsupported HTTP version in status line: \"{}\"",
+            status_line
+        );
+    }
+
+    let status_code = parts
+        .next()
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| connector_err!(ResponseError, "malformed status line: \"{}\"", status_line))?;
+
+    Ok((version, status_code))
+}
+
+/// Parse the size line of a chunk (ignoring any chunk extensions)
+fn parse_chunk_size(line: &str) -> Result<usize, ConnectorError> {
+    let size = line.split(';').next().unwrap().trim();
+
+    usize::from_str_radix(size, 16)
+        .map_err(|_| connector_err!(ResponseError, "invalid chunk size: \"{}\"", line))
+}
+
+/// Find the position of the given needle in a byte slice
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: ResponseLimits = ResponseLimits {
+        max_headers_size: 256,
+        max_body_size: 64,
+    };
+
+    /// Feed a response into a reader one byte at a time
+    fn parse_bytewise(response: &[u8]) -> Result<HttpResponse, ConnectorError> {
+        let mut reader = ResponseReader::new(LIMITS);
+
+        for byte in response {
+            if reader.feed(&[*byte])? {
+                return Ok(reader.into_response());
+            }
+        }
+
+        reader.finish()
+    }
+
+    #[test]
+    fn content_length_test() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+
+        let parsed = ResponseReader::new(LIMITS).read(&mut &response[..]).unwrap();
+        assert_eq!(parsed.body, b"hello");
+        assert!(parsed.keep_alive);
+
+        assert_eq!(parse_bytewise(response).unwrap().body, b"hello");
+    }
+
+    #[test]
+    fn reason_phrase_and_header_case_test() {
+        let response = parse_bytewise(
+            b"HTTP/1.1 200 Everything Is Fine\r\ncontent-length:  2\r\nCONNECTION: close\r\n\r\nok",
+        ).unwrap();
+
+        assert_eq!(response.body, b"ok");
+        assert!(!response.keep_alive);
+    }
+
+    #[test]
+    fn http_1_0_keep_alive_test() {
+        let response = parse_bytewise(b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        assert!(!response.keep_alive);
+
+        let response = parse_bytewise(
+            b"HTTP/1.0 200 OK\r\nConnection: keep-alive\r\nContent-Length: 0\r\n\r\n",
+        ).unwrap();
+        assert!(response.keep_alive);
+    }
+
+    #[test]
+    fn chunked_test() {
+        let response = parse_bytewise(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nX-Trailer: 1\r\n\r\n",
+        ).unwrap();
+
+        assert_eq!(response.body, b"hello, world");
+        assert!(response.keep_alive);
+    }
+
+    #[test]
+    fn until_close_test() {
+        let response = parse_bytewise(b"HTTP/1.1 200 OK\r\n\r\nuntil close").unwrap();
+        assert_eq!(response.body, b"until close");
+        assert!(!response.keep_alive);
+    }
+
+    #[test]
+    fn error_status_test() {
+        assert!(parse_bytewise(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").is_err());
+        assert!(parse_bytewise(b"SPDY/3 200 OK\r\nContent-Length: 0\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn truncated_response_test() {
+        assert!(parse_bytewise(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhel").is_err());
+        assert!(parse_bytewise(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello").is_err());
+    }
+
+    #[test]
+    fn size_limit_test() {
+        let mut large_headers = b"HTTP/1.1 200 OK\r\nX-Padding: ".to_vec();
+        large_headers.extend_from_slice(&[b'x'; 256]);
+        large_headers.extend_from_slice(b"\r\nContent-Length: 0\r\n\r\n");
+        assert!(parse_bytewise(&large_headers).is_err());
+
+        assert!(parse_bytewise(b"HTTP/1.1 200 OK\r\nContent-Length: 65\r\n\r\n").is_err());
+
+        let mut large_chunks = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for _ in 0..5 {
+            large_chunks.extend_from_slice(b"10\r\n0123456789abcdef\r\n");
+        }
+        large_chunks.extend_from_slice(b"0\r\n\r\n");
+        assert!(parse_bytewise(&large_chunks).is_err());
+    }
+}
@@ -1,14 +1,40 @@
 #[macro_use]
 mod error;
+#[cfg(feature = "async")]
+mod async_http_connector;
+mod failover_connector;
 mod http_connector;
+mod http_response;
+mod recording_connector;
+mod replay_connector;
 mod status;
+#[cfg(feature = "tls")]
+mod tls;
 
+#[cfg(feature = "async")]
+use futures::Future;
 use std::fmt::{Debug, Display};
 use uuid::Uuid;
 
+use securechannel::Challenge;
+
+#[cfg(feature = "async")]
+pub use self::async_http_connector::AsyncHttpConnector;
 pub use self::error::{ConnectorError, ConnectorErrorKind};
+pub use self::failover_connector::{
+    FailoverConfig, FailoverConnector, FailoverStrategy, DEFAULT_DOWN_INTERVAL_MILLIS,
+};
 pub use self::http_connector::{HttpConfig, HttpConnector};
+#[cfg(any(test, feature = "keylog"))]
+pub(crate) use self::recording_connector::decode_hex;
+pub(crate) use self::recording_connector::encode_hex;
+pub use self::recording_connector::{RecordingConfig, RecordingConnector};
+#[cfg(feature = "keylog")]
+pub(crate) use self::replay_connector::read_recording;
+pub use self::replay_connector::{ReplayConfig, ReplayConnector};
 pub use self::status::Status;
+#[cfg(feature = "tls")]
+pub use self::tls::TlsConfig;
 
 /// User-Agent string to supply
 pub const USER_AGENT: &str = concat!("yubihsm.rs ", env!("CARGO_PKG_VERSION"));
@@ -29,4 +55,31 @@ pub trait Connector: Sized + Send + Sync {
 
     /// POST /connector/api with a given command message and return the response message
     fn send_command(&self, uuid: Uuid, cmd: Vec<u8>) -> Result<Vec<u8>, ConnectorError>;
+
+    /// Host challenge to use when creating the next session, instead of a
+    /// random one. This is only needed to replay recorded sessions (see
+    /// `ReplayConnector`), and should otherwise be left unimplemented.
+    fn host_challenge(&self) -> Option<Challenge> {
+        None
+    }
+}
+
+/// Future returned by asynchronous connectors
+#[cfg(feature = "async")]
+pub type ConnectorFuture<T> = Box<Future<Item = T, Error = ConnectorError> + Send>;
+
+/// Asynchronous API for communicating with a yubihsm-connector
+#[cfg(feature = "async")]
+pub trait AsyncConnector: Sized + Send + Sync + 'static {
+    /// Configuration options for this connector
+    type Config: Debug + Default + Display;
+
+    /// Open a connection to a yubihsm-connector
+    fn open(config: Self::Config) -> ConnectorFuture<Self>;
+
+    /// GET /connector/status returning the result as connector::Status
+    fn status(&self) -> ConnectorFuture<Status>;
+
+    /// POST /connector/api with a given command message and return the response message
+    fn send_command(&self, uuid: Uuid, cmd: Vec<u8>) -> ConnectorFuture<Vec<u8>>;
 }
@@ -0,0 +1,141 @@
+//! Connector which records the messages exchanged with a `YubiHSM2`, so
+//! they can be replayed later with a `ReplayConnector` (e.g. to run
+//! regression tests in CI without access to real hardware).
+//!
+//! Recordings are text files containing one exchange per line: the UUID of
+//! the request, followed by the hex-encoded request and response messages.
+//! Lines which are empty or start with `#` are ignored.
+//!
+//! Recordings contain the raw bytes sent over the wire, so nothing inside an
+//! encrypted session is revealed by them. However, they *do* allow anyone
+//! who knows the auth key to decrypt the session, so recordings of sessions
+//! authenticated with production keys should be treated as sensitive.
+
+use std::{
+    fmt::{self, Write as FmtWrite},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    str,
+    sync::Mutex,
+};
+use uuid::Uuid;
+
+use super::{Connector, ConnectorError, HttpConfig, HttpConnector, Status};
+use securechannel::Challenge;
+
+/// Header written at the beginning of recordings
+const RECORDING_HEADER: &str = "# yubihsm.rs connector recording";
+
+/// Configuration options for `RecordingConnector`, generic over the
+/// configuration type of the connector being recorded
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RecordingConfig<T = HttpConfig> {
+    /// Configuration of the connector being recorded
+    pub connector: T,
+
+    /// Path to the file the recording is written to (replacing it if it exists)
+    pub path: PathBuf,
+}
+
+impl<T: fmt::Display> fmt::Display for RecordingConfig<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "recording({}) to {}", self.connector, self.path.display())
+    }
+}
+
+/// Connector which records every successful request to and response from
+/// the connector it wraps. Failed requests are not recorded.
+pub struct RecordingConnector<C = HttpConnector>
+where
+    C: Connector,
+{
+    /// Connector being recorded
+    connector: C,
+
+    /// File the recording is written to
+    file: Mutex<File>,
+}
+
+impl<C: Connector> Connector for RecordingConnector<C> {
+    type Config = RecordingConfig<C::Config>;
+
+    /// Open the wrapped connector and create the recording
+    fn open(config: Self::Config) -> Result<Self, ConnectorError> {
+        Self::new(C::open(config.connector)?, &config.path)
+    }
+
+    fn reconnect(&self) -> Result<(), ConnectorError> {
+        self.connector.reconnect()
+    }
+
+    fn status(&self) -> Result<Status, ConnectorError> {
+        self.connector.status()
+    }
+
+    /// Send a command with the wrapped connector, recording the exchange
+    fn send_command(&self, uuid: Uuid, cmd: Vec<u8>) -> Result<Vec<u8>, ConnectorError> {
+        let request = encode_hex(&cmd);
+        let response = self.connector.send_command(uuid, cmd)?;
+
+        let mut file = self.file.lock().unwrap();
+        writeln!(file, "{} {} {}", uuid, request, encode_hex(&response))?;
+        file.flush()?;
+
+        Ok(response)
+    }
+
+    fn host_challenge(&self) -> Option<Challenge> {
+        self.connector.host_challenge()
+    }
+}
+
+impl<C: Connector> RecordingConnector<C> {
+    /// Record the exchanges made with the given connector to a file at the
+    /// given path, replacing it if it already exists
+    pub fn new<P: AsRef<Path>>(connector: C, path: P) -> Result<Self, ConnectorError> {
+        let mut file = File::create(path.as_ref()).map_err(|e| {
+            connector_err!(
+                IoError,
+                "couldn't create {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+
+        writeln!(file, "{}", RECORDING_HEADER)?;
+
+        Ok(Self {
+            connector,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Borrow the connector being recorded
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+}
+
+/// Encode bytes as lower case hexadecimal
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        write!(result, "{:02x}", byte).unwrap();
+    }
+
+    result
+}
+
+/// Decode a hexadecimal string (if valid)
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(str::from_utf8(pair).unwrap(), 16).ok())
+        .collect()
+}
@@ -0,0 +1,219 @@
+//! Connector which replays a conversation with a `YubiHSM2` recorded by a
+//! `RecordingConnector`, without requiring access to the device.
+//!
+//! Requests must match the recording exactly, in order. Any divergence is
+//! reported as a `ConnectorErrorKind::RequestError`, and subsequent requests
+//! continue to fail. Request UUIDs are random, so they aren't compared.
+//!
+//! SCP03 sessions are stateful: the session keys are derived from a random
+//! challenge chosen by the host, which `ReplayConnector` pins to the one in
+//! the recording (see `Connector::host_challenge`). Everything else sent by
+//! a `Session` is deterministic, so replaying the same sequence of commands
+//! with the same auth key reproduces the recorded requests.
+
+use std::{
+    fmt,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use uuid::Uuid;
+
+use super::recording_connector::{decode_hex, encode_hex};
+use super::{Connector, ConnectorError, Status};
+use commands::CommandType;
+use securechannel::{Challenge, CHALLENGE_SIZE};
+
+/// Offset of the host challenge in `CreateSession` requests (after the
+/// command type, length, and auth key ID)
+const HOST_CHALLENGE_OFFSET: usize = 5;
+
+/// Configuration options for `ReplayConnector`
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ReplayConfig {
+    /// Path to a recording made by `RecordingConnector`
+    pub path: PathBuf,
+}
+
+impl fmt::Display for ReplayConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "replay({})", self.path.display())
+    }
+}
+
+/// Connector which serves the responses in a recording, in order
+pub struct ReplayConnector {
+    /// Mutable state of the replay
+    state: Mutex<ReplayState>,
+}
+
+/// Mutable state of a `ReplayConnector`
+struct ReplayState {
+    /// Recorded exchanges
+    exchanges: Vec<Exchange>,
+
+    /// Index of the next exchange to be replayed
+    position: usize,
+
+    /// Has the replay diverged from the recording?
+    diverged: bool,
+}
+
+/// A recorded request and the response to it
+struct Exchange {
+    /// Raw request message
+    request: Vec<u8>,
+
+    /// Raw response message
+    response: Vec<u8>,
+}
+
+impl Connector for ReplayConnector {
+    type Config = ReplayConfig;
+
+    /// Load the recording at the configured path
+    fn open(config: ReplayConfig) -> Result<Self, ConnectorError> {
+        Self::load(&config.path)
+    }
+
+    /// Recordings include messages sent after reconnecting, so there's
+    /// nothing to do here
+    fn reconnect(&self) -> Result<(), ConnectorError> {
+        Ok(())
+    }
+
+    fn status(&self) -> Result<Status, ConnectorError> {
+        Ok(Status {
+            message: "OK".to_owned(),
+            serial: None,
+            version: "replay".to_owned(),
+            pid: 0,
+        })
+    }
+
+    /// Serve the next recorded response, ensuring the request matches
+    fn send_command(&self, _uuid: Uuid, cmd: Vec<u8>) -> Result<Vec<u8>, ConnectorError> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.diverged {
+            connector_fail!(RequestError, "replay has already diverged from the recording");
+        }
+
+        let position = state.position;
+
+        let result = match state.exchanges.get(position) {
+            Some(exchange) if exchange.request == cmd => Ok(exchange.response.clone()),
+            Some(exchange) => Err(connector_err!(
+                RequestError,
+                "replay diverged at request #{}: expected {}, got {}",
+                position + 1,
+                encode_hex(&exchange.request),
+                encode_hex(&cmd)
+            )),
+            None => Err(connector_err!(
+                RequestError,
+                "replay diverged: unexpected request #{} after the end of the recording: {}",
+                position + 1,
+                encode_hex(&cmd)
+            )),
+        };
+
+        if result.is_ok() {
+            state.position += 1;
+        } else {
+            state.diverged = true;
+        }
+
+        result
+    }
+
+    /// Pin the host challenge if the next recorded request creates a session
+    fn host_challenge(&self) -> Option<Challenge> {
+        let state = self.state.lock().unwrap();
+        let request = &state.exchanges.get(state.position)?.request;
+
+        if request.first() != Some(&CommandType::CreateSession.to_u8())
+            || request.len() < HOST_CHALLENGE_OFFSET + CHALLENGE_SIZE
+        {
+            return None;
+        }
+
+        Some(Challenge::from_slice(
+            &request[HOST_CHALLENGE_OFFSET..HOST_CHALLENGE_OFFSET + CHALLENGE_SIZE],
+        ))
+    }
+}
+
+impl ReplayConnector {
+    /// Load a recording made by `RecordingConnector` from the given path
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConnectorError> {
+        let exchanges = read_recording(path)?
+            .into_iter()
+            .map(|(request, response)| Exchange { request, response })
+            .collect();
+
+        Ok(Self {
+            state: Mutex::new(ReplayState {
+                exchanges,
+                position: 0,
+                diverged: false,
+            }),
+        })
+    }
+
+    /// Number of recorded exchanges which haven't been replayed yet
+    pub fn remaining(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.exchanges.len() - state.position
+    }
+}
+
+/// Read the request/response pairs in a recording made by `RecordingConnector`
+pub(crate) fn read_recording<P>(path: P) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ConnectorError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut recording = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut recording))
+        .map_err(|e| connector_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+    let mut exchanges = vec![];
+
+    for (i, line) in recording.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        exchanges.push(parse_exchange(line).ok_or_else(|| {
+            connector_err!(
+                IoError,
+                "malformed recording {} (line {})",
+                path.display(),
+                i + 1
+            )
+        })?);
+    }
+
+    Ok(exchanges)
+}
+
+/// Parse a line of a recording: a UUID, request, and response
+fn parse_exchange(line: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut fields = line.split_whitespace();
+
+    Uuid::parse_str(fields.next()?).ok()?;
+    let request = decode_hex(fields.next()?)?;
+    let response = decode_hex(fields.next()?)?;
+
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some((request, response))
+}
@@ -0,0 +1,211 @@
+//! TLS support for connecting to yubihsm-connector over HTTPS.
+//!
+//! The connector's certificate is verified against the configured CA roots
+//! (and optionally the operating system's trust store). If any certificate
+//! pins are configured, the SHA-256 fingerprint of the connector's (DER
+//! encoded) certificate must additionally match one of them.
+
+use native_tls::{self, Certificate, Identity, TlsConnector, TlsStream};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::Read,
+    net::TcpStream,
+    path::{Path, PathBuf},
+};
+
+use super::ConnectorError;
+
+/// Size of a SHA-256 certificate fingerprint
+const FINGERPRINT_SIZE: usize = 32;
+
+/// TLS options for connecting to yubihsm-connector over HTTPS
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Domain name to verify the connector's certificate against. Defaults to
+    /// the `addr` of the `HttpConfig`.
+    pub domain: Option<String>,
+
+    /// Paths to CA certificates (PEM or DER) to trust
+    pub ca_certs: Vec<PathBuf>,
+
+    /// Trust the operating system's root certificates in addition to `ca_certs`
+    pub use_system_roots: bool,
+
+    /// Path to a PKCS#12 archive containing a client certificate and private
+    /// key to authenticate to the connector with
+    pub client_identity: Option<PathBuf>,
+
+    /// Password for the `client_identity` archive
+    pub client_identity_password: String,
+
+    /// Hex-encoded SHA-256 fingerprints of the connector's certificate. If
+    /// any are given, the connector's certificate must match one of them.
+    /// Fingerprints may contain `:` separators as printed by e.g.
+    /// `openssl x509 -fingerprint -sha256`.
+    pub pinned_certs: Vec<String>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            domain: None,
+            ca_certs: vec![],
+            use_system_roots: true,
+            client_identity: None,
+            client_identity_password: String::new(),
+            pinned_certs: vec![],
+        }
+    }
+}
+
+/// Parsed TLS configuration used to establish connections
+pub(super) struct TlsContext {
+    /// Configured TLS connector
+    connector: TlsConnector,
+
+    /// Domain name to verify the connector's certificate against
+    domain: String,
+
+    /// SHA-256 fingerprints of acceptable connector certificates
+    pinned_certs: Vec<[u8; FINGERPRINT_SIZE]>,
+}
+
+impl TlsContext {
+    /// Load the certificates and keys referenced by the given `TlsConfig`
+    pub fn new(config: &TlsConfig, addr: &str) -> Result<Self, ConnectorError> {
+        let mut builder = TlsConnector::builder();
+        builder.disable_built_in_roots(!config.use_system_roots);
+
+        for path in &config.ca_certs {
+            let cert_bytes = read_file(path)?;
+
+            let cert = Certificate::from_pem(&cert_bytes)
+                .or_else(|_| Certificate::from_der(&cert_bytes))
+                .map_err(|e| {
+                    connector_err!(
+                        ConfigError,
+                        "couldn't parse CA certificate {}: {}",
+                        path.display(),
+                        e
+                    )
+                })?;
+
+            builder.add_root_certificate(cert);
+        }
+
+        if let Some(ref path) = config.client_identity {
+            let identity =
+                Identity::from_pkcs12(&read_file(path)?, &config.client_identity_password)
+                    .map_err(|e| {
+                        connector_err!(
+                            ConfigError,
+                            "couldn't parse client identity {}: {}",
+                            path.display(),
+                            e
+                        )
+                    })?;
+
+            builder.identity(identity);
+        }
+
+        let pinned_certs = config
+            .pinned_certs
+            .iter()
+            .map(|pin| parse_fingerprint(pin))
+            .collect::<Result<_, _>>()?;
+
+        let connector = builder
+            .build()
+            .map_err(|e| connector_err!(ConnectionFailed, "error initializing TLS: {}", e))?;
+
+        Ok(Self {
+            connector,
+            domain: config.domain.clone().unwrap_or_else(|| addr.to_owned()),
+            pinned_certs,
+        })
+    }
+
+    /// Perform a TLS handshake over the given socket, verifying the
+    /// connector's certificate is pinned (if pinning is configured)
+    pub fn connect(&self, socket: TcpStream) -> Result<TlsStream<TcpStream>, ConnectorError> {
+        let stream = self
+            .connector
+            .connect(&self.domain, socket)
+            .map_err(|e| connector_err!(ConnectionFailed, "TLS handshake failed: {}", e))?;
+
+        if !self.pinned_certs.is_empty() {
+            self.verify_pin(&stream)?;
+        }
+
+        Ok(stream)
+    }
+
+    /// Ensure the connector's certificate matches one of the pinned fingerprints
+    fn verify_pin(&self, stream: &TlsStream<TcpStream>) -> Result<(), ConnectorError> {
+        let cert = stream
+            .peer_certificate()
+            .map_err(tls_error)?
+            .ok_or_else(|| connector_err!(ConnectionFailed, "connector sent no certificate"))?;
+
+        let fingerprint = Sha256::digest(&cert.to_der().map_err(tls_error)?);
+
+        if self
+            .pinned_certs
+            .iter()
+            .any(|pin| pin[..] == fingerprint[..])
+        {
+            Ok(())
+        } else {
+            connector_fail!(
+                ConnectionFailed,
+                "connector certificate doesn't match any pinned certificate (SHA-256: {})",
+                fingerprint
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>()
+            )
+        }
+    }
+}
+
+/// Read the contents of a certificate or key file
+fn read_file(path: &Path) -> Result<Vec<u8>, ConnectorError> {
+    let mut bytes = vec![];
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|e| connector_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+    Ok(bytes)
+}
+
+/// Parse a hex-encoded SHA-256 certificate fingerprint
+fn parse_fingerprint(pin: &str) -> Result<[u8; FINGERPRINT_SIZE], ConnectorError> {
+    let digits: Vec<u8> = pin.bytes().filter(|&b| b != b':').collect();
+
+    if digits.len() != FINGERPRINT_SIZE * 2 {
+        connector_fail!(ConfigError, "malformed certificate pin: {}", pin);
+    }
+
+    let mut fingerprint = [0u8; FINGERPRINT_SIZE];
+
+    for (byte, pair) in fingerprint.iter_mut().zip(digits.chunks(2)) {
+        *byte = decode_hex_byte(pair)
+            .ok_or_else(|| connector_err!(ConfigError, "malformed certificate pin: {}", pin))?;
+    }
+
+    Ok(fingerprint)
+}
+
+/// Decode a pair of hex digits
+fn decode_hex_byte(pair: &[u8]) -> Option<u8> {
+    let digit = |b: u8| (b as char).to_digit(16);
+    Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8)
+}
+
+/// Convert a `native_tls::Error` into a `ConnectorError`
+fn tls_error(e: native_tls::Error) -> ConnectorError {
+    connector_err!(ConnectionFailed, "TLS error: {}", e)
+}
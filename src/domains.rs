@@ -93,6 +93,18 @@ impl<'de> Deserialize<'de> for Domain {
             {
                 Domain::from_bits(value).ok_or_else(|| E::custom("invalid domain bitflags"))
             }
+
+            // Self-describing formats (e.g. JSON) deserialize all integers as u64
+            fn visit_u64<E>(self, value: u64) -> Result<Domain, E>
+            where
+                E: de::Error,
+            {
+                if value > 0xffff {
+                    return Err(E::custom("invalid domain bitflags"));
+                }
+
+                self.visit_u16(value as u16)
+            }
         }
 
         deserializer.deserialize_u16(DomainVisitor)
@@ -0,0 +1,106 @@
+//! Base64 encoding (RFC 4648), in both the standard and URL-safe alphabets,
+//! and decoding in the standard alphabet
+
+use clear_on_drop::clear::Clear;
+
+use super::EncodingError;
+
+/// Standard base64 alphabet
+const STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// URL and filename safe base64 alphabet
+const URL_SAFE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode bytes as padded base64 with the standard alphabet
+pub fn encode(bytes: &[u8]) -> String {
+    encode_with(bytes, STANDARD, true)
+}
+
+/// Encode bytes as unpadded base64 with the URL-safe alphabet (a.k.a.
+/// "base64url", as used by JOSE)
+pub fn encode_url(bytes: &[u8]) -> String {
+    encode_with(bytes, URL_SAFE, false)
+}
+
+/// Decode padded base64 in the standard alphabet, ignoring whitespace.
+///
+/// Since the decoded data may be secret (e.g. a private key), it's cleared
+/// if the input turns out to be invalid.
+pub fn decode(encoded: &str) -> Result<Vec<u8>, EncodingError> {
+    let mut result = Vec::with_capacity(encoded.len() / 4 * 3);
+
+    if let Err(e) = decode_into(encoded, &mut result) {
+        result.as_mut_slice().clear();
+        return Err(e);
+    }
+
+    Ok(result)
+}
+
+/// Decode padded base64 in the standard alphabet into the given buffer
+fn decode_into(encoded: &str, output: &mut Vec<u8>) -> Result<(), EncodingError> {
+    let mut n = 0u32;
+    let mut digits = 0;
+    let mut padding = 0;
+
+    for &byte in encoded.as_bytes() {
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
+
+        let value = if byte == b'=' {
+            padding += 1;
+            0
+        } else if padding > 0 {
+            encoding_fail!(ParseError, "invalid base64: data after padding");
+        } else {
+            match STANDARD.iter().position(|&c| c == byte) {
+                Some(value) => value as u32,
+                None => encoding_fail!(ParseError, "invalid base64 character: {:?}", byte as char),
+            }
+        };
+
+        n = n << 6 | value;
+        digits += 1;
+
+        if digits == 4 {
+            if padding > 2 {
+                encoding_fail!(ParseError, "invalid base64: too much padding");
+            }
+
+            let block = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+            output.extend_from_slice(&block[..3 - padding]);
+
+            n = 0;
+            digits = 0;
+        }
+    }
+
+    if digits != 0 {
+        encoding_fail!(ParseError, "invalid base64: truncated input");
+    }
+
+    Ok(())
+}
+
+/// Encode bytes as base64 with the given alphabet
+fn encode_with(bytes: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut result = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+
+        let n = (u32::from(block[0]) << 16) | (u32::from(block[1]) << 8) | u32::from(block[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(alphabet[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else if pad {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}
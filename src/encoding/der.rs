@@ -0,0 +1,294 @@
+//! Minimal encoder and decoder for ASN.1 Distinguished Encoding Rules (DER),
+//! supporting the handful of types used by key, signature, and certificate formats
+
+use super::EncodingError;
+
+/// Tag for ASN.1 BOOLEAN
+#[cfg(feature = "x509")]
+pub const BOOLEAN: u8 = 0x01;
+
+/// Tag for ASN.1 INTEGER
+pub const INTEGER: u8 = 0x02;
+
+/// Tag for ASN.1 BIT STRING
+pub const BIT_STRING: u8 = 0x03;
+
+/// Tag for ASN.1 OCTET STRING
+pub const OCTET_STRING: u8 = 0x04;
+
+/// Tag for ASN.1 NULL
+pub const NULL: u8 = 0x05;
+
+/// Tag for ASN.1 OBJECT IDENTIFIER
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+
+/// Tag for ASN.1 UTF8String
+#[cfg(feature = "x509")]
+pub const UTF8_STRING: u8 = 0x0c;
+
+/// Tag for ASN.1 PrintableString
+#[cfg(feature = "x509")]
+pub const PRINTABLE_STRING: u8 = 0x13;
+
+/// Tag for ASN.1 UTCTime
+#[cfg(feature = "x509")]
+pub const UTC_TIME: u8 = 0x17;
+
+/// Tag for ASN.1 GeneralizedTime
+#[cfg(feature = "x509")]
+pub const GENERALIZED_TIME: u8 = 0x18;
+
+/// Tag for ASN.1 SEQUENCE (constructed)
+pub const SEQUENCE: u8 = 0x30;
+
+/// Tag for ASN.1 SET (constructed)
+#[cfg(feature = "x509")]
+pub const SET: u8 = 0x31;
+
+/// Tag class bit for context-specific tags (e.g. `[0]`)
+pub const CONTEXT_SPECIFIC: u8 = 0x80;
+
+/// Tag bit for constructed values (e.g. `[0] EXPLICIT`)
+pub const CONSTRUCTED: u8 = 0x20;
+
+/// Encode a value with the given tag and contents
+pub fn encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(contents.len() + 6);
+    result.push(tag);
+
+    let len = contents.len();
+
+    if len < 0x80 {
+        result.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = (0..4)
+            .rev()
+            .map(|i| (len >> (i * 8)) as u8)
+            .skip_while(|&b| b == 0)
+            .collect();
+
+        result.push(0x80 | len_bytes.len() as u8);
+        result.extend_from_slice(&len_bytes);
+    }
+
+    result.extend_from_slice(contents);
+    result
+}
+
+/// Encode a SEQUENCE of already-encoded values
+pub fn sequence(values: &[&[u8]]) -> Vec<u8> {
+    encode(SEQUENCE, &values.concat())
+}
+
+/// Encode a SET OF already-encoded values, sorted as DER requires
+#[cfg(feature = "x509")]
+pub fn set_of(values: &[&[u8]]) -> Vec<u8> {
+    let mut values = values.to_vec();
+    values.sort();
+    encode(SET, &values.concat())
+}
+
+/// Encode a BOOLEAN
+#[cfg(feature = "x509")]
+pub fn boolean(value: bool) -> Vec<u8> {
+    encode(BOOLEAN, &[if value { 0xff } else { 0 }])
+}
+
+/// Encode a big endian unsigned integer as an INTEGER
+pub fn integer(bytes: &[u8]) -> Vec<u8> {
+    // Remove redundant leading zeroes, then ensure the value isn't
+    // interpreted as negative
+    let first_nonzero = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[first_nonzero..];
+
+    let mut contents = Vec::with_capacity(bytes.len() + 1);
+
+    if bytes.is_empty() || bytes[0] & 0x80 != 0 {
+        contents.push(0);
+    }
+
+    contents.extend_from_slice(bytes);
+    encode(INTEGER, &contents)
+}
+
+/// Encode bytes as a BIT STRING (with no unused bits)
+pub fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(bytes.len() + 1);
+    contents.push(0);
+    contents.extend_from_slice(bytes);
+    encode(BIT_STRING, &contents)
+}
+
+/// Encode a NULL
+pub fn null() -> Vec<u8> {
+    encode(NULL, &[])
+}
+
+/// Encode an OBJECT IDENTIFIER from its arcs
+pub fn object_identifier(arcs: &[u32]) -> Vec<u8> {
+    encode(OBJECT_IDENTIFIER, &object_identifier_contents(arcs))
+}
+
+/// Does the contents of a decoded OBJECT IDENTIFIER match the given arcs?
+pub fn object_identifier_eq(contents: &[u8], arcs: &[u32]) -> bool {
+    contents == object_identifier_contents(arcs).as_slice()
+}
+
+/// Encode the arcs of an OBJECT IDENTIFIER (without its tag and length)
+fn object_identifier_contents(arcs: &[u32]) -> Vec<u8> {
+    assert!(arcs.len() >= 2, "OIDs have at least two arcs");
+
+    let mut contents = vec![];
+    push_base128(&mut contents, arcs[0] * 40 + arcs[1]);
+
+    for &arc in &arcs[2..] {
+        push_base128(&mut contents, arc);
+    }
+
+    contents
+}
+
+/// Append an OID arc in base 128, most significant group first
+fn push_base128(output: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+
+    while rest != 0 {
+        groups.push(0x80 | (rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+
+    output.extend(groups.iter().rev());
+}
+
+/// Decoder for a series of DER-encoded values, e.g. the contents of a SEQUENCE
+pub struct Decoder<'a> {
+    /// Remaining input
+    input: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    /// Create a decoder for the given input
+    pub fn new(input: &'a [u8]) -> Self {
+        Decoder { input }
+    }
+
+    /// Decode the next value, which must have the given tag, returning its contents
+    pub fn decode(&mut self, tag: u8) -> Result<&'a [u8], EncodingError> {
+        if self.input.len() < 2 {
+            encoding_fail!(ParseError, "truncated DER value");
+        }
+
+        if self.input[0] != tag {
+            encoding_fail!(
+                ParseError,
+                "expected DER tag 0x{:02x} (got 0x{:02x})",
+                tag,
+                self.input[0]
+            );
+        }
+
+        // Only definite lengths in their shortest form are valid DER
+        let (len, header_len) = match self.input[1] {
+            len if len < 0x80 => (len as usize, 2),
+            0x81 if self.input.len() >= 3 && self.input[2] >= 0x80 => (self.input[2] as usize, 3),
+            0x82 if self.input.len() >= 4 && self.input[2] != 0 => (
+                (self.input[2] as usize) << 8 | self.input[3] as usize,
+                4,
+            ),
+            other => encoding_fail!(ParseError, "invalid DER length: 0x{:02x}", other),
+        };
+
+        if self.input.len() - header_len < len {
+            encoding_fail!(ParseError, "truncated DER value");
+        }
+
+        let contents = &self.input[header_len..header_len + len];
+        self.input = &self.input[header_len + len..];
+        Ok(contents)
+    }
+
+    /// Decode the next value if it has the given tag, e.g. an OPTIONAL field
+    pub fn optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, EncodingError> {
+        if self.input.first() == Some(&tag) {
+            self.decode(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Decode the next value, which must have the given tag, returning its
+    /// entire encoding (tag, length, and contents), e.g. to verify a signature
+    /// over it
+    #[cfg(feature = "attestation")]
+    pub fn encoded(&mut self, tag: u8) -> Result<&'a [u8], EncodingError> {
+        let input = self.input;
+        self.decode(tag)?;
+        Ok(&input[..input.len() - self.input.len()])
+    }
+
+    /// Decode a SEQUENCE, returning a decoder for its contents
+    pub fn sequence(&mut self) -> Result<Decoder<'a>, EncodingError> {
+        Ok(Decoder::new(self.decode(SEQUENCE)?))
+    }
+
+    /// Decode a non-negative INTEGER as a big endian unsigned integer
+    /// (without any leading zeroes)
+    pub fn integer(&mut self) -> Result<&'a [u8], EncodingError> {
+        let contents = self.decode(INTEGER)?;
+
+        if contents.is_empty() {
+            encoding_fail!(ParseError, "empty DER INTEGER");
+        }
+
+        if contents[0] & 0x80 != 0 {
+            encoding_fail!(ParseError, "negative DER INTEGER");
+        }
+
+        if contents[0] == 0 && contents.len() > 1 {
+            if contents[1] & 0x80 == 0 {
+                encoding_fail!(ParseError, "non-minimal DER INTEGER");
+            }
+
+            return Ok(&contents[1..]);
+        }
+
+        Ok(contents)
+    }
+
+    /// Decode a BIT STRING with no unused bits, returning its bytes
+    pub fn bit_string(&mut self) -> Result<&'a [u8], EncodingError> {
+        let contents = self.decode(BIT_STRING)?;
+
+        match contents.split_first() {
+            Some((&0, bytes)) => Ok(bytes),
+            Some(_) => encoding_fail!(ParseError, "unexpected unused bits in DER BIT STRING"),
+            None => encoding_fail!(ParseError, "empty DER BIT STRING"),
+        }
+    }
+
+    /// Decode an OBJECT IDENTIFIER, returning its encoded arcs (see
+    /// `object_identifier_eq`)
+    pub fn object_identifier(&mut self) -> Result<&'a [u8], EncodingError> {
+        self.decode(OBJECT_IDENTIFIER)
+    }
+
+    /// Has all of the input been consumed?
+    #[cfg(feature = "attestation")]
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Ensure all of the input has been consumed
+    pub fn finish(self) -> Result<(), EncodingError> {
+        if !self.input.is_empty() {
+            encoding_fail!(
+                ParseError,
+                "{} bytes of trailing data after DER value",
+                self.input.len()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+//! Conversions between the DER-encoded ECDSA signatures returned by
+//! `sign_ecdsa*` and fixed-width `r || s` encodings (as used by e.g. JWS and
+//! Bitcoin/Ethereum), along with low-S normalization
+
+use std::cmp::Ordering;
+
+use super::{der, EncodingError};
+use commands::sign_ecdsa::ECDSASignature;
+use AsymmetricAlgorithm;
+
+/// Order of the NIST P-256 group
+const P256_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x51,
+];
+
+/// Order of the NIST P-384 group
+const P384_ORDER: [u8; 48] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xc7, 0x63, 0x4d, 0x81, 0xf4, 0x37, 0x2d, 0xdf,
+    0x58, 0x1a, 0x0d, 0xb2, 0x48, 0xb0, 0xa7, 0x7a, 0xec, 0xec, 0x19, 0x6a, 0xcc, 0xc5, 0x29, 0x73,
+];
+
+/// Order of the NIST P-521 group
+const P521_ORDER: [u8; 66] = [
+    0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xfa, 0x51, 0x86, 0x87, 0x83, 0xbf, 0x2f, 0x96, 0x6b, 0x7f, 0xcc, 0x01, 0x48, 0xf7, 0x09,
+    0xa5, 0xd0, 0x3b, 0xb5, 0xc9, 0xb8, 0x89, 0x9c, 0x47, 0xae, 0xbb, 0x6f, 0xb7, 0x1e, 0x91, 0x38,
+    0x64, 0x09,
+];
+
+/// Order of the secp256k1 group
+const K256_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Order of the brainpoolP256r1 group
+const BP256_ORDER: [u8; 32] = [
+    0xa9, 0xfb, 0x57, 0xdb, 0xa1, 0xee, 0xa9, 0xbc, 0x3e, 0x66, 0x0a, 0x90, 0x9d, 0x83, 0x8d, 0x71,
+    0x8c, 0x39, 0x7a, 0xa3, 0xb5, 0x61, 0xa6, 0xf7, 0x90, 0x1e, 0x0e, 0x82, 0x97, 0x48, 0x56, 0xa7,
+];
+
+/// Order of the brainpoolP384r1 group
+const BP384_ORDER: [u8; 48] = [
+    0x8c, 0xb9, 0x1e, 0x82, 0xa3, 0x38, 0x6d, 0x28, 0x0f, 0x5d, 0x6f, 0x7e, 0x50, 0xe6, 0x41, 0xdf,
+    0x15, 0x2f, 0x71, 0x09, 0xed, 0x54, 0x56, 0xb3, 0x1f, 0x16, 0x6e, 0x6c, 0xac, 0x04, 0x25, 0xa7,
+    0xcf, 0x3a, 0xb6, 0xaf, 0x6b, 0x7f, 0xc3, 0x10, 0x3b, 0x88, 0x32, 0x02, 0xe9, 0x04, 0x65, 0x65,
+];
+
+/// Order of the brainpoolP512r1 group
+const BP512_ORDER: [u8; 64] = [
+    0xaa, 0xdd, 0x9d, 0xb8, 0xdb, 0xe9, 0xc4, 0x8b, 0x3f, 0xd4, 0xe6, 0xae, 0x33, 0xc9, 0xfc, 0x07,
+    0xcb, 0x30, 0x8d, 0xb3, 0xb3, 0xc9, 0xd2, 0x0e, 0xd6, 0x63, 0x9c, 0xca, 0x70, 0x33, 0x08, 0x70,
+    0x55, 0x3e, 0x5c, 0x41, 0x4c, 0xa9, 0x26, 0x19, 0x41, 0x86, 0x61, 0x19, 0x7f, 0xac, 0x10, 0x47,
+    0x1d, 0xb1, 0xd3, 0x81, 0x08, 0x5d, 0xda, 0xdd, 0xb5, 0x87, 0x96, 0x82, 0x9c, 0xa9, 0x00, 0x69,
+];
+
+/// The `r` and `s` components of an ECDSA signature, as big endian integers
+/// of the curve's scalar size
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ECDSASignatureComponents {
+    /// Curve of the key which produced the signature
+    algorithm: AsymmetricAlgorithm,
+
+    /// `r` component
+    r: Vec<u8>,
+
+    /// `s` component
+    s: Vec<u8>,
+}
+
+impl ECDSASignatureComponents {
+    /// Create signature components from big endian `r` and `s` values,
+    /// ensuring both are in the range `1..n` for the curve's order `n`
+    pub fn new(
+        algorithm: AsymmetricAlgorithm,
+        r: &[u8],
+        s: &[u8],
+    ) -> Result<Self, EncodingError> {
+        let order = curve_order(algorithm)?;
+
+        Ok(ECDSASignatureComponents {
+            algorithm,
+            r: scalar(order, "r", r)?,
+            s: scalar(order, "s", s)?,
+        })
+    }
+
+    /// Parse an ASN.1 DER `ECDSA-Sig-Value` (RFC 3279), i.e. the format of
+    /// signatures returned by the `YubiHSM2`
+    pub fn from_der(algorithm: AsymmetricAlgorithm, bytes: &[u8]) -> Result<Self, EncodingError> {
+        let mut decoder = der::Decoder::new(bytes);
+        let mut sequence = decoder.sequence()?;
+        decoder.finish()?;
+
+        let r = sequence.integer()?;
+        let s = sequence.integer()?;
+        sequence.finish()?;
+
+        Self::new(algorithm, r, s)
+    }
+
+    /// Parse a fixed-width `r || s` signature, e.g. a JWS `ES256` signature
+    pub fn from_fixed(algorithm: AsymmetricAlgorithm, bytes: &[u8]) -> Result<Self, EncodingError> {
+        let scalar_len = curve_order(algorithm)?.len();
+
+        if bytes.len() != scalar_len * 2 {
+            encoding_fail!(
+                ParseError,
+                "expected {}-byte {:?} signature (got {})",
+                scalar_len * 2,
+                algorithm,
+                bytes.len()
+            );
+        }
+
+        let (r, s) = bytes.split_at(scalar_len);
+        Self::new(algorithm, r, s)
+    }
+
+    /// Curve of the key which produced the signature
+    pub fn algorithm(&self) -> AsymmetricAlgorithm {
+        self.algorithm
+    }
+
+    /// Borrow the `r` component
+    pub fn r(&self) -> &[u8] {
+        &self.r
+    }
+
+    /// Borrow the `s` component
+    pub fn s(&self) -> &[u8] {
+        &self.s
+    }
+
+    /// Encode these components as an ASN.1 DER `ECDSA-Sig-Value`
+    pub fn to_der(&self) -> ECDSASignature {
+        ECDSASignature(der::sequence(&[
+            &der::integer(&self.r),
+            &der::integer(&self.s),
+        ]))
+    }
+
+    /// Encode these components as a fixed-width `r || s` signature
+    pub fn to_fixed(&self) -> Vec<u8> {
+        [self.r.as_slice(), self.s.as_slice()].concat()
+    }
+
+    /// Is `s` at most half the curve's order? Signatures with a "high" `s`
+    /// are rejected by e.g. Bitcoin's consensus rules, since `(r, n - s)` is
+    /// an equally valid signature for the same message.
+    pub fn is_low_s(&self) -> bool {
+        let half_order = half(curve_order(self.algorithm).unwrap());
+        self.s.as_slice().cmp(&half_order) != Ordering::Greater
+    }
+
+    /// Replace a high `s` with `n - s`, where `n` is the curve's order
+    pub fn normalize_s(&mut self) {
+        if !self.is_low_s() {
+            self.s = subtract(curve_order(self.algorithm).unwrap(), &self.s);
+        }
+    }
+}
+
+impl ECDSASignature {
+    /// Parse this signature into its `r` and `s` components. The algorithm
+    /// is that of the signing key (e.g. from `get_pubkey`).
+    pub fn components(
+        &self,
+        algorithm: AsymmetricAlgorithm,
+    ) -> Result<ECDSASignatureComponents, EncodingError> {
+        ECDSASignatureComponents::from_der(algorithm, self.as_slice())
+    }
+
+    /// Convert a fixed-width `r || s` signature to DER
+    pub fn from_fixed(algorithm: AsymmetricAlgorithm, bytes: &[u8]) -> Result<Self, EncodingError> {
+        Ok(ECDSASignatureComponents::from_fixed(algorithm, bytes)?.to_der())
+    }
+
+    /// Convert this signature to a fixed-width `r || s` encoding
+    pub fn to_fixed(&self, algorithm: AsymmetricAlgorithm) -> Result<Vec<u8>, EncodingError> {
+        Ok(self.components(algorithm)?.to_fixed())
+    }
+
+    /// Normalize this signature so `s` is at most half the curve's order
+    pub fn to_low_s(&self, algorithm: AsymmetricAlgorithm) -> Result<Self, EncodingError> {
+        let mut components = self.components(algorithm)?;
+        components.normalize_s();
+        Ok(components.to_der())
+    }
+}
+
+/// Get the order of the group used for ECDSA signatures with the given curve
+pub(super) fn curve_order(
+    algorithm: AsymmetricAlgorithm,
+) -> Result<&'static [u8], EncodingError> {
+    Ok(match algorithm {
+        AsymmetricAlgorithm::EC_P256 => &P256_ORDER,
+        AsymmetricAlgorithm::EC_P384 => &P384_ORDER,
+        AsymmetricAlgorithm::EC_P521 => &P521_ORDER,
+        AsymmetricAlgorithm::EC_K256 => &K256_ORDER,
+        AsymmetricAlgorithm::EC_BP256 => &BP256_ORDER,
+        AsymmetricAlgorithm::EC_BP384 => &BP384_ORDER,
+        AsymmetricAlgorithm::EC_BP512 => &BP512_ORDER,
+        algorithm => encoding_fail!(
+            UnsupportedAlgorithm,
+            "expected an ECDSA key (got {:?})",
+            algorithm
+        ),
+    })
+}
+
+/// Left-pad a big endian integer to the size of the order, ensuring it's
+/// in the range `1..order`
+fn scalar(order: &[u8], name: &str, bytes: &[u8]) -> Result<Vec<u8>, EncodingError> {
+    let first_nonzero = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[first_nonzero..];
+
+    if bytes.is_empty() {
+        encoding_fail!(InvalidSignature, "{} is zero", name);
+    }
+
+    if bytes.len() > order.len() {
+        encoding_fail!(InvalidSignature, "{} is larger than the curve order", name);
+    }
+
+    let mut result = vec![0u8; order.len() - bytes.len()];
+    result.extend_from_slice(bytes);
+
+    if result.as_slice() >= order {
+        encoding_fail!(InvalidSignature, "{} is larger than the curve order", name);
+    }
+
+    Ok(result)
+}
+
+/// Divide a big endian integer by two
+fn half(value: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(value.len());
+    let mut carry = 0;
+
+    for &byte in value {
+        result.push(carry << 7 | byte >> 1);
+        carry = byte & 1;
+    }
+
+    result
+}
+
+/// Subtract two big endian integers of the same size, where `lhs >= rhs`
+fn subtract(lhs: &[u8], rhs: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(lhs.len(), rhs.len());
+
+    let mut result = vec![0u8; lhs.len()];
+    let mut borrow = 0i16;
+
+    for i in (0..lhs.len()).rev() {
+        let mut difference = i16::from(lhs[i]) - i16::from(rhs[i]) - borrow;
+        borrow = 0;
+
+        if difference < 0 {
+            difference += 0x100;
+            borrow = 1;
+        }
+
+        result[i] = difference as u8;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connector::decode_hex;
+    use EncodingErrorKind;
+
+    /// secp256k1 signature with a high `s`
+    const K256_HIGH_S_SIGNATURE: &str = concat!(
+        "30460221009d6ea09e9c58b7f7894129242981f2b7ab2f257f425c874248401eef7bae1c30",
+        "0221008ffb7ea0e0dda189d6674c6f5eb0a47039f243eb4c228df9889f96749f195f6b",
+    );
+
+    /// `K256_HIGH_S_SIGNATURE` normalized to a low `s`
+    const K256_LOW_S_SIGNATURE: &str = concat!(
+        "30450221009d6ea09e9c58b7f7894129242981f2b7ab2f257f425c874248401eef7bae1c30",
+        "02207004815f1f225e762998b390a14f5b8e80bc98fb632612423732c818311ce1d6",
+    );
+
+    /// P-521 signature whose `r` is shorter than the curve's scalar size
+    const P521_SIGNATURE: &str = concat!(
+        "30818602403a12020ae54fd3a3a3df6aa4c3fc0a8975dcb069c32e8b05ca1dc2ad98518e8720",
+        "2b714edfba0bc88affa8e7c43f1a0ca4cbf99ec9f7c68d4d549145291aa83b02420091d4eb3c",
+        "1dabd47dcc3238faad6f5d2c2b0d40addbfe206649b110b547602fcc8f9125dfce4425733",
+        "1d06638203cae2b2d6593190e1c66bc7c15da55f77384477b",
+    );
+
+    /// `P521_SIGNATURE` as fixed-width `r || s`
+    const P521_FIXED_SIGNATURE: &str = concat!(
+        "00003a12020ae54fd3a3a3df6aa4c3fc0a8975dcb069c32e8b05ca1dc2ad98518e87202b714e",
+        "dfba0bc88affa8e7c43f1a0ca4cbf99ec9f7c68d4d549145291aa83b0091d4eb3c1dabd47dcc",
+        "3238faad6f5d2c2b0d40addbfe206649b110b547602fcc8f9125dfce44257331d06638203cae",
+        "2b2d6593190e1c66bc7c15da55f77384477b",
+    );
+
+    fn signature(hex: &str) -> ECDSASignature {
+        ECDSASignature(decode_hex(hex).unwrap())
+    }
+
+    #[test]
+    fn secp256k1_low_s_test() {
+        let high_s = signature(K256_HIGH_S_SIGNATURE);
+        let components = high_s.components(AsymmetricAlgorithm::EC_K256).unwrap();
+
+        assert_eq!(
+            components.r(),
+            decode_hex("9d6ea09e9c58b7f7894129242981f2b7ab2f257f425c874248401eef7bae1c30")
+                .unwrap()
+                .as_slice()
+        );
+        assert!(!components.is_low_s());
+
+        let low_s = high_s.to_low_s(AsymmetricAlgorithm::EC_K256).unwrap();
+        assert_eq!(low_s.as_slice(), signature(K256_LOW_S_SIGNATURE).as_slice());
+
+        // Normalizing is idempotent
+        let components = low_s.components(AsymmetricAlgorithm::EC_K256).unwrap();
+        assert!(components.is_low_s());
+        assert_eq!(
+            low_s.to_low_s(AsymmetricAlgorithm::EC_K256).unwrap().as_slice(),
+            low_s.as_slice()
+        );
+    }
+
+    #[test]
+    fn p521_fixed_test() {
+        let der = signature(P521_SIGNATURE);
+        let fixed = decode_hex(P521_FIXED_SIGNATURE).unwrap();
+
+        assert_eq!(der.to_fixed(AsymmetricAlgorithm::EC_P521).unwrap(), fixed);
+        assert_eq!(
+            ECDSASignature::from_fixed(AsymmetricAlgorithm::EC_P521, &fixed)
+                .unwrap()
+                .as_slice(),
+            der.as_slice()
+        );
+
+        assert_eq!(
+            ECDSASignature::from_fixed(AsymmetricAlgorithm::EC_P521, &fixed[1..])
+                .err()
+                .unwrap()
+                .kind(),
+            EncodingErrorKind::ParseError
+        );
+    }
+
+    #[test]
+    fn invalid_signature_test() {
+        // Trailing data after the signature
+        let mut bytes = decode_hex(K256_LOW_S_SIGNATURE).unwrap();
+        bytes.push(0);
+        assert_eq!(
+            ECDSASignature(bytes)
+                .components(AsymmetricAlgorithm::EC_K256)
+                .err()
+                .unwrap()
+                .kind(),
+            EncodingErrorKind::ParseError
+        );
+
+        // `s` is equal to the curve order
+        assert_eq!(
+            ECDSASignatureComponents::new(AsymmetricAlgorithm::EC_K256, &[1], &K256_ORDER)
+                .err()
+                .unwrap()
+                .kind(),
+            EncodingErrorKind::InvalidSignature
+        );
+
+        // Ed25519 signatures aren't ECDSA signatures
+        assert_eq!(
+            signature(K256_LOW_S_SIGNATURE)
+                .components(AsymmetricAlgorithm::EC_ED25519)
+                .err()
+                .unwrap()
+                .kind(),
+            EncodingErrorKind::UnsupportedAlgorithm
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Error types for encoding and decoding keys
+
+use error::Error;
+
+/// Key encoding errors
+pub type EncodingError = Error<EncodingErrorKind>;
+
+/// Key encoding error kinds
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+pub enum EncodingErrorKind {
+    /// The requested encoding isn't defined for the key's algorithm
+    #[fail(display = "unsupported algorithm")]
+    UnsupportedAlgorithm,
+
+    /// The key is malformed (e.g. it's the wrong size for its algorithm)
+    #[fail(display = "invalid key")]
+    InvalidKey,
+
+    /// The input couldn't be decoded (e.g. it's malformed DER)
+    #[fail(display = "parse error")]
+    ParseError,
+
+    /// The signature is malformed (e.g. its components are out of range)
+    #[fail(display = "invalid signature")]
+    InvalidSignature,
+
+    /// The key is encrypted, and must be decrypted before it can be imported
+    #[fail(display = "encrypted key")]
+    EncryptedKey,
+}
+
+/// Create a new encoding error with a formatted message
+macro_rules! encoding_err {
+    ($kind:ident, $msg:expr) => {
+        ::encoding::EncodingError::new(
+            ::encoding::EncodingErrorKind::$kind,
+            Some($msg.to_owned())
+        )
+    };
+    ($kind:ident, $fmt:expr, $($arg:tt)+) => {
+        ::encoding::EncodingError::new(
+            ::encoding::EncodingErrorKind::$kind,
+            Some(format!($fmt, $($arg)+))
+        )
+    };
+}
+
+/// Create and return an encoding error with a formatted message
+macro_rules! encoding_fail {
+    ($kind:ident, $msg:expr) => {
+        return Err(encoding_err!($kind, $msg).into());
+    };
+    ($kind:ident, $fmt:expr, $($arg:tt)+) => {
+        return Err(encoding_err!($kind, $fmt, $($arg)+).into());
+    };
+}
@@ -0,0 +1,18 @@
+//! Encoders for standard key formats (DER, PEM, SEC1, OpenSSH, and JWK),
+//! decoders for private keys, and conversions between ECDSA signature formats
+
+#[macro_use]
+mod error;
+
+mod base64;
+pub(crate) mod der;
+mod ecdsa;
+pub(crate) mod oid;
+pub(crate) mod pem;
+mod private_key;
+mod public_key;
+
+pub use self::ecdsa::ECDSASignatureComponents;
+pub use self::error::{EncodingError, EncodingErrorKind};
+pub use self::private_key::PrivateKey;
+pub use self::public_key::RSA_PUBLIC_EXPONENT;
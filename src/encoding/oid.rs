@@ -0,0 +1,145 @@
+//! ASN.1 object identifiers (as arcs) for the algorithms supported by the
+//! `YubiHSM2`
+
+use AsymmetricAlgorithm;
+
+/// rsaEncryption (PKCS#1)
+pub const RSA_ENCRYPTION: &[u32] = &[1, 2, 840, 113_549, 1, 1, 1];
+
+/// id-ecPublicKey (SEC1 / RFC 5480)
+pub const EC_PUBLIC_KEY: &[u32] = &[1, 2, 840, 10_045, 2, 1];
+
+/// id-Ed25519 (RFC 8410)
+pub const ED25519: &[u32] = &[1, 3, 101, 112];
+
+/// NIST P-256 (a.k.a. secp256r1, prime256v1)
+pub const SECP256R1: &[u32] = &[1, 2, 840, 10_045, 3, 1, 7];
+
+/// NIST P-384 (a.k.a. secp384r1)
+pub const SECP384R1: &[u32] = &[1, 3, 132, 0, 34];
+
+/// NIST P-521 (a.k.a. secp521r1)
+pub const SECP521R1: &[u32] = &[1, 3, 132, 0, 35];
+
+/// secp256k1
+pub const SECP256K1: &[u32] = &[1, 3, 132, 0, 10];
+
+/// brainpoolP256r1 (RFC 5639)
+pub const BRAINPOOL_P256R1: &[u32] = &[1, 3, 36, 3, 3, 2, 8, 1, 1, 7];
+
+/// brainpoolP384r1 (RFC 5639)
+pub const BRAINPOOL_P384R1: &[u32] = &[1, 3, 36, 3, 3, 2, 8, 1, 1, 11];
+
+/// brainpoolP512r1 (RFC 5639)
+pub const BRAINPOOL_P512R1: &[u32] = &[1, 3, 36, 3, 3, 2, 8, 1, 1, 13];
+
+/// ecdsa-with-SHA256 (RFC 5758)
+#[cfg(feature = "x509")]
+pub const ECDSA_WITH_SHA256: &[u32] = &[1, 2, 840, 10_045, 4, 3, 2];
+
+/// ecdsa-with-SHA384 (RFC 5758)
+#[cfg(feature = "x509")]
+pub const ECDSA_WITH_SHA384: &[u32] = &[1, 2, 840, 10_045, 4, 3, 3];
+
+/// ecdsa-with-SHA512 (RFC 5758)
+#[cfg(feature = "x509")]
+pub const ECDSA_WITH_SHA512: &[u32] = &[1, 2, 840, 10_045, 4, 3, 4];
+
+/// sha256WithRSAEncryption (PKCS#1)
+#[cfg(any(feature = "attestation", all(feature = "rsa", feature = "x509")))]
+pub const SHA256_WITH_RSA_ENCRYPTION: &[u32] = &[1, 2, 840, 113_549, 1, 1, 11];
+
+/// sha384WithRSAEncryption (PKCS#1)
+#[cfg(feature = "attestation")]
+pub const SHA384_WITH_RSA_ENCRYPTION: &[u32] = &[1, 2, 840, 113_549, 1, 1, 12];
+
+/// sha512WithRSAEncryption (PKCS#1)
+#[cfg(feature = "attestation")]
+pub const SHA512_WITH_RSA_ENCRYPTION: &[u32] = &[1, 2, 840, 113_549, 1, 1, 13];
+
+/// id-at-commonName (X.520)
+#[cfg(feature = "x509")]
+pub const COMMON_NAME: &[u32] = &[2, 5, 4, 3];
+
+/// id-at-countryName (X.520)
+#[cfg(feature = "x509")]
+pub const COUNTRY_NAME: &[u32] = &[2, 5, 4, 6];
+
+/// id-at-localityName (X.520)
+#[cfg(feature = "x509")]
+pub const LOCALITY_NAME: &[u32] = &[2, 5, 4, 7];
+
+/// id-at-stateOrProvinceName (X.520)
+#[cfg(feature = "x509")]
+pub const STATE_OR_PROVINCE_NAME: &[u32] = &[2, 5, 4, 8];
+
+/// id-at-organizationName (X.520)
+#[cfg(feature = "x509")]
+pub const ORGANIZATION_NAME: &[u32] = &[2, 5, 4, 10];
+
+/// id-at-organizationalUnitName (X.520)
+#[cfg(feature = "x509")]
+pub const ORGANIZATIONAL_UNIT_NAME: &[u32] = &[2, 5, 4, 11];
+
+/// id-ce-subjectKeyIdentifier (RFC 5280)
+#[cfg(feature = "x509")]
+pub const SUBJECT_KEY_IDENTIFIER: &[u32] = &[2, 5, 29, 14];
+
+/// id-ce-keyUsage (RFC 5280)
+#[cfg(feature = "x509")]
+pub const KEY_USAGE: &[u32] = &[2, 5, 29, 15];
+
+/// id-ce-subjectAltName (RFC 5280)
+#[cfg(feature = "x509")]
+pub const SUBJECT_ALT_NAME: &[u32] = &[2, 5, 29, 17];
+
+/// id-ce-basicConstraints (RFC 5280)
+#[cfg(feature = "x509")]
+pub const BASIC_CONSTRAINTS: &[u32] = &[2, 5, 29, 19];
+
+/// id-ce-authorityKeyIdentifier (RFC 5280)
+#[cfg(feature = "x509")]
+pub const AUTHORITY_KEY_IDENTIFIER: &[u32] = &[2, 5, 29, 35];
+
+/// pkcs-9-at-extensionRequest (RFC 2985)
+#[cfg(feature = "x509")]
+pub const EXTENSION_REQUEST: &[u32] = &[1, 2, 840, 113_549, 1, 9, 14];
+
+/// `YubiHSM2` attestation extension: firmware version of the device
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_FIRMWARE_VERSION: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 1];
+
+/// `YubiHSM2` attestation extension: serial number of the device
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_SERIAL_NUMBER: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 2];
+
+/// `YubiHSM2` attestation extension: origin of the attested key
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_ORIGIN: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 3];
+
+/// `YubiHSM2` attestation extension: domains of the attested key
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_DOMAINS: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 4];
+
+/// `YubiHSM2` attestation extension: capabilities of the attested key
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_CAPABILITIES: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 5];
+
+/// `YubiHSM2` attestation extension: object ID of the attested key
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_OBJECT_ID: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 6];
+
+/// `YubiHSM2` attestation extension: label of the attested key
+#[cfg(feature = "attestation")]
+pub const YUBIHSM_LABEL: &[u32] = &[1, 3, 6, 1, 4, 1, 41_482, 4, 9];
+
+/// Named elliptic curves (other than Ed25519) supported by the `YubiHSM2`
+pub const CURVES: &[(AsymmetricAlgorithm, &[u32])] = &[
+    (AsymmetricAlgorithm::EC_P256, SECP256R1),
+    (AsymmetricAlgorithm::EC_P384, SECP384R1),
+    (AsymmetricAlgorithm::EC_P521, SECP521R1),
+    (AsymmetricAlgorithm::EC_K256, SECP256K1),
+    (AsymmetricAlgorithm::EC_BP256, BRAINPOOL_P256R1),
+    (AsymmetricAlgorithm::EC_BP384, BRAINPOOL_P384R1),
+    (AsymmetricAlgorithm::EC_BP512, BRAINPOOL_P512R1),
+];
@@ -0,0 +1,65 @@
+//! Privacy-Enhanced Mail (PEM) encoding of DER documents (RFC 7468)
+
+use clear_on_drop::clear::Clear;
+
+use super::{base64, EncodingError};
+
+/// Maximum length of lines of base64 in a PEM document
+const LINE_LENGTH: usize = 64;
+
+/// Encode a DER document as PEM with the given label (e.g. "PUBLIC KEY")
+pub fn encode(label: &str, der: &[u8]) -> String {
+    let mut result = format!("-----BEGIN {}-----\n", label);
+    let encoded = base64::encode(der);
+
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        // base64 is ASCII, so lines are always valid UTF-8
+        result.push_str(&String::from_utf8_lossy(line));
+        result.push('\n');
+    }
+
+    result.push_str(&format!("-----END {}-----\n", label));
+    result
+}
+
+/// Decode the first PEM document in the given input, returning its label
+/// and DER contents.
+///
+/// Legacy encapsulated headers (RFC 1421, e.g. OpenSSL's `Proc-Type` for
+/// encrypted keys) are unsupported.
+pub fn decode(pem: &str) -> Result<(String, Vec<u8>), EncodingError> {
+    let mut lines = pem.lines().map(str::trim).skip_while(|line| line.is_empty());
+
+    let label = match lines.next() {
+        Some(line) if line.starts_with("-----BEGIN ") && line.ends_with("-----") => {
+            line["-----BEGIN ".len()..line.len() - "-----".len()].to_owned()
+        }
+        _ => encoding_fail!(ParseError, "missing PEM BEGIN line"),
+    };
+
+    let end = format!("-----END {}-----", label);
+    let mut encoded = String::with_capacity(pem.len());
+    let mut result = Err(encoding_err!(ParseError, "missing PEM END line for {}", label));
+
+    for line in lines {
+        if line == end {
+            result = base64::decode(&encoded);
+            break;
+        }
+
+        if line.starts_with("Proc-Type:") {
+            result = Err(encoding_err!(EncryptedKey, "encrypted legacy PEM keys are unsupported"));
+            break;
+        }
+
+        if line.contains(':') {
+            result = Err(encoding_err!(ParseError, "unsupported PEM header: {}", line));
+            break;
+        }
+
+        encoded.push_str(line);
+    }
+
+    encoded.as_mut_str().clear();
+    Ok((label, result?))
+}
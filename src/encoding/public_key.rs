@@ -0,0 +1,560 @@
+//! Standard encodings of the public keys returned by `get_pubkey`
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use super::der::{self, Decoder};
+use super::{base64, oid, pem, EncodingError};
+use commands::get_pubkey::PublicKey;
+use AsymmetricAlgorithm;
+
+/// Public exponent of all RSA keys generated or imported by the `YubiHSM2`
+pub const RSA_PUBLIC_EXPONENT: u32 = 65_537;
+
+impl PublicKey {
+    /// Decode a DER `SubjectPublicKeyInfo` (RFC 5280) for a key type the
+    /// `YubiHSM2` supports, e.g. the public key of an X.509 certificate
+    pub fn from_spki_der(bytes: &[u8]) -> Result<Self, EncodingError> {
+        let mut decoder = Decoder::new(bytes);
+        let mut spki = decoder.sequence()?;
+        decoder.finish()?;
+
+        let mut algorithm_id = spki.sequence()?;
+        let algorithm_oid = algorithm_id.object_identifier()?;
+        let key = spki.bit_string()?;
+        spki.finish()?;
+
+        if der::object_identifier_eq(algorithm_oid, oid::RSA_ENCRYPTION) {
+            algorithm_id.optional(der::NULL)?;
+            algorithm_id.finish()?;
+            return rsa_public_key(key);
+        }
+
+        let algorithm = if der::object_identifier_eq(algorithm_oid, oid::ED25519) {
+            algorithm_id.finish()?;
+            AsymmetricAlgorithm::EC_ED25519
+        } else if der::object_identifier_eq(algorithm_oid, oid::EC_PUBLIC_KEY) {
+            let algorithm = curve(algorithm_id.object_identifier()?)?;
+            algorithm_id.finish()?;
+            algorithm
+        } else {
+            encoding_fail!(UnsupportedAlgorithm, "unsupported public key algorithm");
+        };
+
+        let bytes = if algorithm == AsymmetricAlgorithm::EC_ED25519 {
+            key
+        } else {
+            match key.split_first() {
+                Some((&0x04, point)) => point,
+                _ => encoding_fail!(InvalidKey, "expected an uncompressed SEC1 point"),
+            }
+        };
+
+        let public_key = PublicKey {
+            algorithm,
+            bytes: bytes.to_vec(),
+        };
+
+        // Ensure the key is the expected size for its algorithm
+        if algorithm == AsymmetricAlgorithm::EC_ED25519 {
+            public_key.ed25519_key()?;
+        } else {
+            public_key.ec_point()?;
+        }
+
+        Ok(public_key)
+    }
+
+    /// Encode this key as a DER `SubjectPublicKeyInfo` (RFC 5280), i.e. the
+    /// form used by X.509 certificates and OpenSSL's "PUBLIC KEY" PEM files
+    pub fn to_spki_der(&self) -> Result<Vec<u8>, EncodingError> {
+        let (algorithm_id, key) = match self.algorithm {
+            AsymmetricAlgorithm::RSA2048
+            | AsymmetricAlgorithm::RSA3072
+            | AsymmetricAlgorithm::RSA4096 => (
+                der::sequence(&[&der::object_identifier(oid::RSA_ENCRYPTION), &der::null()]),
+                self.to_pkcs1_der()?,
+            ),
+            AsymmetricAlgorithm::EC_ED25519 => (
+                der::sequence(&[&der::object_identifier(oid::ED25519)]),
+                self.ed25519_key()?.to_vec(),
+            ),
+            _ => (
+                der::sequence(&[
+                    &der::object_identifier(oid::EC_PUBLIC_KEY),
+                    &der::object_identifier(self.curve_oid()?),
+                ]),
+                self.to_sec1_uncompressed()?,
+            ),
+        };
+
+        Ok(der::sequence(&[&algorithm_id, &der::bit_string(&key)]))
+    }
+
+    /// Encode this key as a PEM `SubjectPublicKeyInfo` ("PUBLIC KEY")
+    pub fn to_spki_pem(&self) -> Result<String, EncodingError> {
+        Ok(pem::encode("PUBLIC KEY", &self.to_spki_der()?))
+    }
+
+    /// Encode an RSA key as a DER PKCS#1 `RSAPublicKey` (RFC 8017), i.e. the
+    /// modulus along with the public exponent
+    pub fn to_pkcs1_der(&self) -> Result<Vec<u8>, EncodingError> {
+        let modulus = self.rsa_modulus()?;
+        let exponent = rsa_public_exponent();
+
+        Ok(der::sequence(&[
+            &der::integer(modulus),
+            &der::integer(&exponent),
+        ]))
+    }
+
+    /// Encode an elliptic curve key (other than Ed25519) as an uncompressed
+    /// SEC1 point: `0x04 || X || Y`
+    pub fn to_sec1_uncompressed(&self) -> Result<Vec<u8>, EncodingError> {
+        let (x, y) = self.ec_point()?;
+
+        let mut result = Vec::with_capacity(1 + x.len() + y.len());
+        result.push(0x04);
+        result.extend_from_slice(x);
+        result.extend_from_slice(y);
+        Ok(result)
+    }
+
+    /// Encode an elliptic curve key (other than Ed25519) as a compressed
+    /// SEC1 point: `0x02 || X` if Y is even, or `0x03 || X` if Y is odd
+    pub fn to_sec1_compressed(&self) -> Result<Vec<u8>, EncodingError> {
+        let (x, y) = self.ec_point()?;
+
+        let mut result = Vec::with_capacity(1 + x.len());
+        result.push(0x02 | (y[y.len() - 1] & 1));
+        result.extend_from_slice(x);
+        Ok(result)
+    }
+
+    /// Encode this key as an OpenSSH public key, i.e. a line for an
+    /// `authorized_keys` file. The comment is omitted if it's empty.
+    ///
+    /// OpenSSH supports RSA, Ed25519, and the NIST P-256, P-384, and P-521
+    /// curves.
+    pub fn to_openssh(&self, comment: &str) -> Result<String, EncodingError> {
+        let mut blob = vec![];
+
+        let key_type = match self.algorithm {
+            AsymmetricAlgorithm::RSA2048
+            | AsymmetricAlgorithm::RSA3072
+            | AsymmetricAlgorithm::RSA4096 => {
+                ssh_string(&mut blob, b"ssh-rsa");
+                ssh_mpint(&mut blob, &rsa_public_exponent());
+                ssh_mpint(&mut blob, self.rsa_modulus()?);
+                "ssh-rsa"
+            }
+            AsymmetricAlgorithm::EC_ED25519 => {
+                ssh_string(&mut blob, b"ssh-ed25519");
+                ssh_string(&mut blob, self.ed25519_key()?);
+                "ssh-ed25519"
+            }
+            AsymmetricAlgorithm::EC_P256
+            | AsymmetricAlgorithm::EC_P384
+            | AsymmetricAlgorithm::EC_P521 => {
+                let (key_type, curve) = match self.algorithm {
+                    AsymmetricAlgorithm::EC_P256 => ("ecdsa-sha2-nistp256", "nistp256"),
+                    AsymmetricAlgorithm::EC_P384 => ("ecdsa-sha2-nistp384", "nistp384"),
+                    _ => ("ecdsa-sha2-nistp521", "nistp521"),
+                };
+
+                ssh_string(&mut blob, key_type.as_bytes());
+                ssh_string(&mut blob, curve.as_bytes());
+                ssh_string(&mut blob, &self.to_sec1_uncompressed()?);
+                key_type
+            }
+            algorithm => encoding_fail!(
+                UnsupportedAlgorithm,
+                "OpenSSH doesn't support {:?} keys",
+                algorithm
+            ),
+        };
+
+        let mut result = format!("{} {}", key_type, base64::encode(&blob));
+
+        if !comment.is_empty() {
+            result.push(' ');
+            result.push_str(comment);
+        }
+
+        Ok(result)
+    }
+
+    /// Encode this key as a JSON Web Key (RFC 7517)
+    ///
+    /// JWK supports RSA (RFC 7518), Ed25519 (RFC 8037), the NIST P-256,
+    /// P-384, and P-521 curves (RFC 7518), and secp256k1 (RFC 8812).
+    pub fn to_jwk(&self) -> Result<String, EncodingError> {
+        Ok(match self.algorithm {
+            AsymmetricAlgorithm::RSA2048
+            | AsymmetricAlgorithm::RSA3072
+            | AsymmetricAlgorithm::RSA4096 => format!(
+                r#"{{"kty":"RSA","n":"{}","e":"{}"}}"#,
+                base64::encode_url(self.rsa_modulus()?),
+                base64::encode_url(&rsa_public_exponent())
+            ),
+            AsymmetricAlgorithm::EC_ED25519 => format!(
+                r#"{{"kty":"OKP","crv":"Ed25519","x":"{}"}}"#,
+                base64::encode_url(self.ed25519_key()?)
+            ),
+            AsymmetricAlgorithm::EC_P256
+            | AsymmetricAlgorithm::EC_P384
+            | AsymmetricAlgorithm::EC_P521
+            | AsymmetricAlgorithm::EC_K256 => {
+                let curve = match self.algorithm {
+                    AsymmetricAlgorithm::EC_P256 => "P-256",
+                    AsymmetricAlgorithm::EC_P384 => "P-384",
+                    AsymmetricAlgorithm::EC_P521 => "P-521",
+                    _ => "secp256k1",
+                };
+
+                let (x, y) = self.ec_point()?;
+
+                format!(
+                    r#"{{"kty":"EC","crv":"{}","x":"{}","y":"{}"}}"#,
+                    curve,
+                    base64::encode_url(x),
+                    base64::encode_url(y)
+                )
+            }
+            algorithm => encoding_fail!(
+                UnsupportedAlgorithm,
+                "JWK doesn't support {:?} keys",
+                algorithm
+            ),
+        })
+    }
+
+    /// Borrow the modulus of an RSA key
+    fn rsa_modulus(&self) -> Result<&[u8], EncodingError> {
+        match self.algorithm {
+            AsymmetricAlgorithm::RSA2048
+            | AsymmetricAlgorithm::RSA3072
+            | AsymmetricAlgorithm::RSA4096 => self.checked_bytes(self.algorithm.key_len()),
+            algorithm => encoding_fail!(
+                UnsupportedAlgorithm,
+                "expected an RSA key (got {:?})",
+                algorithm
+            ),
+        }
+    }
+
+    /// Borrow an Ed25519 key
+    fn ed25519_key(&self) -> Result<&[u8], EncodingError> {
+        match self.algorithm {
+            AsymmetricAlgorithm::EC_ED25519 => self.checked_bytes(self.algorithm.key_len()),
+            algorithm => encoding_fail!(
+                UnsupportedAlgorithm,
+                "expected an Ed25519 key (got {:?})",
+                algorithm
+            ),
+        }
+    }
+
+    /// Split an elliptic curve key (other than Ed25519) into its coordinates
+    fn ec_point(&self) -> Result<(&[u8], &[u8]), EncodingError> {
+        // Ensure the key is an elliptic curve key
+        self.curve_oid()?;
+
+        let bytes = self.checked_bytes(self.algorithm.key_len() * 2)?;
+        Ok(bytes.split_at(self.algorithm.key_len()))
+    }
+
+    /// Get the OID of the named curve of an elliptic curve key
+    fn curve_oid(&self) -> Result<&'static [u32], EncodingError> {
+        match oid::CURVES.iter().find(|&&(algorithm, _)| algorithm == self.algorithm) {
+            Some(&(_, arcs)) => Ok(arcs),
+            None => encoding_fail!(
+                UnsupportedAlgorithm,
+                "expected a (non-Ed25519) elliptic curve key (got {:?})",
+                self.algorithm
+            ),
+        }
+    }
+
+    /// Borrow the key bytes, ensuring they're the expected length
+    fn checked_bytes(&self, expected_len: usize) -> Result<&[u8], EncodingError> {
+        if self.bytes.len() != expected_len {
+            encoding_fail!(
+                InvalidKey,
+                "expected {}-byte {:?} key (got {})",
+                expected_len,
+                self.algorithm,
+                self.bytes.len()
+            );
+        }
+
+        Ok(&self.bytes)
+    }
+}
+
+/// Create an RSA key from a DER PKCS#1 `RSAPublicKey`
+fn rsa_public_key(bytes: &[u8]) -> Result<PublicKey, EncodingError> {
+    let mut decoder = Decoder::new(bytes);
+    let mut rsa_public_key = decoder.sequence()?;
+    decoder.finish()?;
+
+    let modulus = rsa_public_key.integer()?;
+    let public_exponent = rsa_public_key.integer()?;
+    rsa_public_key.finish()?;
+
+    let algorithm = match modulus.len() {
+        256 => AsymmetricAlgorithm::RSA2048,
+        384 => AsymmetricAlgorithm::RSA3072,
+        512 => AsymmetricAlgorithm::RSA4096,
+        len => encoding_fail!(
+            UnsupportedAlgorithm,
+            "unsupported RSA key size: {} bits",
+            len * 8
+        ),
+    };
+
+    if public_exponent != rsa_public_exponent().as_slice() {
+        encoding_fail!(
+            UnsupportedAlgorithm,
+            "unsupported RSA public exponent (expected {})",
+            RSA_PUBLIC_EXPONENT
+        );
+    }
+
+    Ok(PublicKey {
+        algorithm,
+        bytes: modulus.to_vec(),
+    })
+}
+
+/// RSA public exponent as a minimal big endian integer
+pub(super) fn rsa_public_exponent() -> Vec<u8> {
+    let mut result = vec![];
+    result.write_u32::<BigEndian>(RSA_PUBLIC_EXPONENT).unwrap();
+
+    let first_nonzero = result.iter().position(|&b| b != 0).unwrap();
+    result.split_off(first_nonzero)
+}
+
+/// Find the algorithm for the named curve with the given (encoded) OID
+pub(super) fn curve(contents: &[u8]) -> Result<AsymmetricAlgorithm, EncodingError> {
+    match oid::CURVES
+        .iter()
+        .find(|&&(_, arcs)| der::object_identifier_eq(contents, arcs))
+    {
+        Some(&(algorithm, _)) => Ok(algorithm),
+        None => encoding_fail!(UnsupportedAlgorithm, "unsupported elliptic curve"),
+    }
+}
+
+/// Append an SSH `string` (RFC 4251): a length-prefixed bytestring
+fn ssh_string(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.write_u32::<BigEndian>(bytes.len() as u32).unwrap();
+    output.extend_from_slice(bytes);
+}
+
+/// Append an SSH `mpint` (RFC 4251) from a big endian unsigned integer
+fn ssh_mpint(output: &mut Vec<u8>, bytes: &[u8]) {
+    let first_nonzero = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[first_nonzero..];
+
+    if !bytes.is_empty() && bytes[0] & 0x80 != 0 {
+        output.write_u32::<BigEndian>(bytes.len() as u32 + 1).unwrap();
+        output.push(0);
+        output.extend_from_slice(bytes);
+    } else {
+        ssh_string(output, bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connector::decode_hex;
+    use EncodingErrorKind;
+
+    /// NIST P-256 public key
+    const P256_KEY: &str = concat!(
+        "471c3e758c4904285bba7e53118ed0f524adeb0757d25bd2f8e7b0d76dfa714c",
+        "dd520f7aca8a8b917acc37f51de8f0c9bbe3ad858382e702dc25a12d09f7a858",
+    );
+
+    /// secp256k1 public key (with an odd Y coordinate)
+    const K256_KEY: &str = concat!(
+        "fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556",
+        "ae12777aacfbb620f3be96017f45c560de80f0f6518fe4a03c870c36b075f297",
+    );
+
+    /// Ed25519 public key from RFC 8410 section 10.1
+    const ED25519_KEY: &str = "19bf44096984cdfe8541bac167dc3b96c85086aa30b6b6cb0c5c38ad703166e1";
+
+    /// RSA-2048 public modulus
+    const RSA_MODULUS: &str = concat!(
+        "9f83c76379c8426f36a606ca0e89f23f1270d4ca695945f24bafcfe37c9812ab",
+        "0a16bb2bace5241fb4c7e93f25188c17dda7df5413c27b095864e8c1a8b81ffe",
+        "29fef5336f11a51064efd897da3440ffee03c800fb05d5d6cc0f9e6089415978",
+        "c6f91d64eb96bea3842d63f067a38ff829ba978822c3597e8d53951f8c824382",
+        "53028105347d7e01b552cb3a586501a56ec7b3a994e14aec884681664a2f7a7a",
+        "22243f5ffbbf68b2f62893b7ca1768f98b7744374b8deb157424694f901f6db0",
+        "33465ecba7e2e8c23af57f543a54a587b279b826349164821959d6ff03f5927e",
+        "bffd5813ea35d72af7e777db71173cc031d25d1511dbaa5cd2494068db4a277d",
+    );
+
+    fn public_key(algorithm: AsymmetricAlgorithm, hex: &str) -> PublicKey {
+        PublicKey {
+            algorithm,
+            bytes: decode_hex(hex).unwrap(),
+        }
+    }
+
+    #[test]
+    fn p256_test() {
+        let key = public_key(AsymmetricAlgorithm::EC_P256, P256_KEY);
+
+        assert_eq!(
+            key.to_spki_pem().unwrap(),
+            "-----BEGIN PUBLIC KEY-----\n\
+             MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAERxw+dYxJBChbun5TEY7Q9SSt6wdX\n\
+             0lvS+Oew1236cUzdUg96yoqLkXrMN/Ud6PDJu+OthYOC5wLcJaEtCfeoWA==\n\
+             -----END PUBLIC KEY-----\n"
+        );
+
+        assert_eq!(
+            key.to_sec1_uncompressed().unwrap(),
+            decode_hex(&format!("04{}", P256_KEY)).unwrap()
+        );
+
+        assert_eq!(
+            key.to_sec1_compressed().unwrap(),
+            decode_hex(&format!("02{}", &P256_KEY[..64])).unwrap()
+        );
+
+        assert_eq!(
+            key.to_openssh("test@example.com").unwrap(),
+            "ecdsa-sha2-nistp256 \
+             AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBEccPnWMSQQoW7p+UxGO0PUkresHV9Jb\
+             0vjnsNdt+nFM3VIPesqKi5F6zDf1HejwybvjrYWDgucC3CWhLQn3qFg= test@example.com"
+        );
+
+        assert_eq!(
+            key.to_jwk().unwrap(),
+            r#"{"kty":"EC","crv":"P-256","x":"Rxw-dYxJBChbun5TEY7Q9SSt6wdX0lvS-Oew1236cUw","#
+                .to_owned()
+                + r#""y":"3VIPesqKi5F6zDf1HejwybvjrYWDgucC3CWhLQn3qFg"}"#
+        );
+    }
+
+    #[test]
+    fn secp256k1_test() {
+        let key = public_key(AsymmetricAlgorithm::EC_K256, K256_KEY);
+
+        assert_eq!(
+            key.to_spki_der().unwrap(),
+            decode_hex(&format!(
+                "3056301006072a8648ce3d020106052b8104000a03420004{}",
+                K256_KEY
+            )).unwrap()
+        );
+
+        assert_eq!(
+            key.to_sec1_compressed().unwrap(),
+            decode_hex(&format!("03{}", &K256_KEY[..64])).unwrap()
+        );
+
+        assert_eq!(
+            key.to_jwk().unwrap(),
+            r#"{"kty":"EC","crv":"secp256k1","x":"__l71XVe7qQgRToUNVI104L2Ry-FaKGLLwV6FGApdVY","#
+                .to_owned()
+                + r#""y":"rhJ3eqz7tiDzvpYBf0XFYN6A8PZRj-SgPIcMNrB18pc"}"#
+        );
+
+        assert_eq!(
+            key.to_openssh("").err().unwrap().kind(),
+            EncodingErrorKind::UnsupportedAlgorithm
+        );
+    }
+
+    #[test]
+    fn ed25519_test() {
+        let key = public_key(AsymmetricAlgorithm::EC_ED25519, ED25519_KEY);
+
+        assert_eq!(
+            key.to_spki_pem().unwrap(),
+            "-----BEGIN PUBLIC KEY-----\n\
+             MCowBQYDK2VwAyEAGb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuE=\n\
+             -----END PUBLIC KEY-----\n"
+        );
+
+        assert_eq!(
+            key.to_openssh("").unwrap(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIBm/RAlphM3+hUG6wWfcO5bIUIaqMLa2ywxcOK1wMWbh"
+        );
+
+        assert_eq!(
+            key.to_jwk().unwrap(),
+            r#"{"kty":"OKP","crv":"Ed25519","x":"Gb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuE"}"#
+        );
+
+        assert_eq!(
+            key.to_sec1_uncompressed().err().unwrap().kind(),
+            EncodingErrorKind::UnsupportedAlgorithm
+        );
+    }
+
+    #[test]
+    fn rsa_test() {
+        let key = public_key(AsymmetricAlgorithm::RSA2048, RSA_MODULUS);
+
+        let pkcs1 = key.to_pkcs1_der().unwrap();
+        assert_eq!(
+            pkcs1,
+            decode_hex(&format!("3082010a0282010100{}0203010001", RSA_MODULUS)).unwrap()
+        );
+
+        let mut spki = decode_hex("30820122300d06092a864886f70d01010105000382010f00").unwrap();
+        spki.extend_from_slice(&pkcs1);
+        assert_eq!(key.to_spki_der().unwrap(), spki);
+
+        let openssh = key.to_openssh("").unwrap();
+        assert!(openssh.starts_with("ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCfg8djechCbzamBsoO"));
+        assert!(openssh.ends_with("MAx0l0VEduqXNJJQGjbSid9"));
+
+        let jwk = key.to_jwk().unwrap();
+        assert!(jwk.starts_with(r#"{"kty":"RSA","n":"n4PHY3nIQm82pgbKDonyPxJw1MppWUXyS6_P43yY"#));
+        assert!(jwk.ends_with(r#"dJdFRHbqlzSSUBo20onfQ","e":"AQAB"}"#));
+    }
+
+    #[test]
+    fn spki_decode_test() {
+        for key in &[
+            public_key(AsymmetricAlgorithm::EC_P256, P256_KEY),
+            public_key(AsymmetricAlgorithm::EC_K256, K256_KEY),
+            public_key(AsymmetricAlgorithm::EC_ED25519, ED25519_KEY),
+            public_key(AsymmetricAlgorithm::RSA2048, RSA_MODULUS),
+        ] {
+            let decoded = PublicKey::from_spki_der(&key.to_spki_der().unwrap()).unwrap();
+            assert_eq!(decoded.algorithm, key.algorithm);
+            assert_eq!(decoded.bytes, key.bytes);
+        }
+
+        // Compressed points aren't supported
+        let spki = decode_hex(&format!(
+            "3039301306072a8648ce3d020106082a8648ce3d03010703220002{}",
+            &P256_KEY[..64]
+        )).unwrap();
+
+        assert_eq!(
+            PublicKey::from_spki_der(&spki).err().unwrap().kind(),
+            EncodingErrorKind::InvalidKey
+        );
+    }
+
+    #[test]
+    fn invalid_key_test() {
+        let key = public_key(AsymmetricAlgorithm::EC_P256, &P256_KEY[..64]);
+
+        assert_eq!(
+            key.to_spki_der().err().unwrap().kind(),
+            EncodingErrorKind::InvalidKey
+        );
+    }
+}
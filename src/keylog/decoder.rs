@@ -0,0 +1,303 @@
+//! Offline decoder for captured traffic, using session keys from a key log
+
+use std::{collections::HashMap, fmt, path::Path};
+use subtle::ConstantTimeEq;
+
+use super::{KeyLogError, KeyLogFile, SessionKeys};
+use commands::{
+    create_session::{CreateSessionCommand, CreateSessionResponse},
+    CommandType,
+};
+use connector::{encode_hex, read_recording};
+use securechannel::{
+    Channel, CommandMessage, Context, ResponseCode, ResponseMessage, SessionId,
+};
+use serializers::deserialize;
+
+/// Decodes a captured stream of command and response messages (i.e. the
+/// traffic between a host and yubihsm-connector), decrypting the messages
+/// sent in sessions whose keys are in a key log.
+///
+/// Encrypted sessions are stateful, so exchanges must be decoded in the
+/// order they were captured, starting from the `CreateSession` command for
+/// each session of interest.
+pub struct KeyLogDecoder {
+    /// Logged session keys
+    keys: Vec<SessionKeys>,
+
+    /// Sessions which have been created but not yet authenticated
+    pending: HashMap<SessionId, Channel>,
+
+    /// Authenticated sessions
+    channels: HashMap<SessionId, Channel>,
+}
+
+impl KeyLogDecoder {
+    /// Create a decoder for sessions with the given keys
+    pub fn new(keys: Vec<SessionKeys>) -> Self {
+        Self {
+            keys,
+            pending: HashMap::new(),
+            channels: HashMap::new(),
+        }
+    }
+
+    /// Create a decoder for the sessions in the key log at the given path
+    pub fn load<P: AsRef<Path>>(key_log_path: P) -> Result<Self, KeyLogError> {
+        Ok(Self::new(KeyLogFile::read(key_log_path)?))
+    }
+
+    /// Decode a request sent to yubihsm-connector along with its response
+    pub fn decode(
+        &mut self,
+        request: &[u8],
+        response: &[u8],
+    ) -> Result<DecodedExchange, KeyLogError> {
+        let command = CommandMessage::parse(request.into())?;
+        let response = ResponseMessage::parse(response.into())?;
+
+        match command.command_type {
+            CommandType::CreateSession => self.create_session(command, response),
+            CommandType::AuthSession => self.authenticate_session(command, response),
+            CommandType::SessionMessage => self.session_message(command, response),
+            _ => Ok(DecodedExchange::new(None, false, command, &response)),
+        }
+    }
+
+    /// Decode every exchange in a recording made by `RecordingConnector`.
+    /// Exchanges which couldn't be decoded (e.g. because their session keys
+    /// weren't logged) are returned as errors.
+    pub fn decode_recording<P>(
+        &mut self,
+        path: P,
+    ) -> Result<Vec<Result<DecodedExchange, KeyLogError>>, KeyLogError>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(read_recording(path)?
+            .iter()
+            .map(|&(ref request, ref response)| self.decode(request, response))
+            .collect())
+    }
+
+    /// Find the keys for a newly created session
+    fn create_session(
+        &mut self,
+        command: CommandMessage,
+        response: ResponseMessage,
+    ) -> Result<DecodedExchange, KeyLogError> {
+        if response.is_err() {
+            return Ok(DecodedExchange::new(None, false, command, &response));
+        }
+
+        let session_id = response
+            .session_id
+            .ok_or_else(|| keylog_err!(ProtocolError, "no session ID in response"))?;
+
+        let create_command: CreateSessionCommand = deserialize(&command.data)?;
+        let create_response: CreateSessionResponse = deserialize(&response.data)?;
+
+        let context = Context::from_challenges(
+            create_command.host_challenge,
+            create_response.card_challenge,
+        );
+
+        // Forget any earlier session with the same ID
+        self.pending.remove(&session_id);
+        self.channels.remove(&session_id);
+
+        let channel = self
+            .keys
+            .iter()
+            .find(|keys| keys.context() == context.as_slice())
+            .map(|keys| Channel::from_session_keys(session_id, keys))
+            .ok_or_else(|| {
+                keylog_err!(
+                    MissingKeys,
+                    "session {} was created, but there are no keys for its context: {}",
+                    session_id.to_u8(),
+                    encode_hex(context.as_slice())
+                )
+            })?;
+
+        if channel
+            .card_cryptogram()
+            .ct_eq(&create_response.card_cryptogram)
+            .unwrap_u8() != 1
+        {
+            keylog_fail!(
+                VerifyFailed,
+                "card cryptogram mismatch for session {}",
+                session_id.to_u8()
+            );
+        }
+
+        self.pending.insert(session_id, channel);
+
+        Ok(DecodedExchange::new(
+            Some(session_id),
+            false,
+            command,
+            &response,
+        ))
+    }
+
+    /// Verify the host's authentication of a session
+    fn authenticate_session(
+        &mut self,
+        command: CommandMessage,
+        response: ResponseMessage,
+    ) -> Result<DecodedExchange, KeyLogError> {
+        let session_id = command.session_id.unwrap();
+
+        let mut channel = self
+            .pending
+            .remove(&session_id)
+            .ok_or_else(|| missing_keys(session_id))?;
+
+        channel.verify_authenticate_session(&command)?;
+
+        if !response.is_err() {
+            self.channels.insert(session_id, channel);
+        }
+
+        Ok(DecodedExchange::new(
+            Some(session_id),
+            false,
+            command,
+            &response,
+        ))
+    }
+
+    /// Decrypt a command sent in an encrypted session along with its response
+    fn session_message(
+        &mut self,
+        encrypted_command: CommandMessage,
+        encrypted_response: ResponseMessage,
+    ) -> Result<DecodedExchange, KeyLogError> {
+        let session_id = encrypted_command.session_id.unwrap();
+
+        // Sessions can't be used again after an error: remove the channel
+        // now and put it back if everything checks out
+        let mut channel = self
+            .channels
+            .remove(&session_id)
+            .ok_or_else(|| missing_keys(session_id))?;
+
+        let command = channel.decrypt_command(encrypted_command)?;
+
+        if encrypted_response.is_err() {
+            return Ok(DecodedExchange::new(
+                Some(session_id),
+                true,
+                command,
+                &encrypted_response,
+            ));
+        }
+
+        if encrypted_response.session_id != Some(session_id) {
+            keylog_fail!(
+                ProtocolError,
+                "session ID mismatch: expected {}, got {:?}",
+                session_id.to_u8(),
+                encrypted_response.session_id
+            );
+        }
+
+        let response = channel.decrypt_response(encrypted_response)?;
+
+        if command.command_type != CommandType::CloseSession || response.is_err() {
+            self.channels.insert(session_id, channel);
+        }
+
+        Ok(DecodedExchange::new(
+            Some(session_id),
+            true,
+            command,
+            &response,
+        ))
+    }
+}
+
+/// Error for messages in sessions without logged keys
+fn missing_keys(session_id: SessionId) -> KeyLogError {
+    keylog_err!(
+        MissingKeys,
+        "no keys for session {} (was it created before the capture started?)",
+        session_id.to_u8()
+    )
+}
+
+/// A decoded request and response. Messages sent in encrypted sessions are
+/// decrypted.
+#[derive(Clone, Debug)]
+pub struct DecodedExchange {
+    /// Session the exchange belongs to (if any)
+    pub session_id: Option<SessionId>,
+
+    /// Were the command and response encrypted?
+    pub encrypted: bool,
+
+    /// Type of the command
+    pub command_type: CommandType,
+
+    /// Command payload
+    pub command_data: Vec<u8>,
+
+    /// Response code, i.e. success or the error which occurred
+    pub response_code: ResponseCode,
+
+    /// Response payload
+    pub response_data: Vec<u8>,
+}
+
+impl DecodedExchange {
+    /// Create a decoded exchange from a (decrypted) command and response
+    fn new(
+        session_id: Option<SessionId>,
+        encrypted: bool,
+        command: CommandMessage,
+        response: &ResponseMessage,
+    ) -> Self {
+        let response_code = response.error_code();
+
+        Self {
+            session_id,
+            encrypted,
+            command_type: command.command_type,
+            command_data: command.data,
+            response_code,
+            response_data: if response.is_err() {
+                vec![]
+            } else {
+                response.data.clone()
+            },
+        }
+    }
+
+    /// Did the command succeed?
+    pub fn is_ok(&self) -> bool {
+        self.response_code == ResponseCode::Success(self.command_type)
+    }
+}
+
+impl fmt::Display for DecodedExchange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(session_id) = self.session_id {
+            write!(f, "session={} ", session_id.to_u8())?;
+        }
+
+        if self.encrypted {
+            write!(f, "encrypted ")?;
+        }
+
+        write!(
+            f,
+            "{:?} [{}] -> {:?} [{}]",
+            self.command_type,
+            encode_hex(&self.command_data),
+            self.response_code,
+            encode_hex(&self.response_data)
+        )
+    }
+}
@@ -0,0 +1,88 @@
+//! Error types for key logs
+
+use std::io;
+
+use connector::ConnectorError;
+use error::Error;
+use securechannel::{SecureChannelError, SecureChannelErrorKind};
+use serializers::SerializationError;
+
+/// Key log errors
+pub type KeyLogError = Error<KeyLogErrorKind>;
+
+/// Key log error kinds
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+pub enum KeyLogErrorKind {
+    /// Input/output error reading or writing a key log or recording
+    #[fail(display = "I/O error")]
+    IoError,
+
+    /// Malformed key log entry
+    #[fail(display = "parse error")]
+    ParseError,
+
+    /// No keys logged for the session a message belongs to
+    #[fail(display = "missing session keys")]
+    MissingKeys,
+
+    /// Malformed or unexpected message
+    #[fail(display = "protocol error")]
+    ProtocolError,
+
+    /// Logged keys don't match the session (MAC or cryptogram mismatch)
+    #[fail(display = "verification failed")]
+    VerifyFailed,
+}
+
+/// Create a new key log error with a formatted message
+macro_rules! keylog_err {
+    ($kind:ident, $msg:expr) => {
+        ::keylog::KeyLogError::new(
+            ::keylog::KeyLogErrorKind::$kind,
+            Some($msg.to_owned())
+        )
+    };
+    ($kind:ident, $fmt:expr, $($arg:tt)+) => {
+        ::keylog::KeyLogError::new(
+            ::keylog::KeyLogErrorKind::$kind,
+            Some(format!($fmt, $($arg)+))
+        )
+    };
+}
+
+/// Create and return a key log error with a formatted message
+macro_rules! keylog_fail {
+    ($kind:ident, $msg:expr) => {
+        return Err(keylog_err!($kind, $msg).into());
+    };
+    ($kind:ident, $fmt:expr, $($arg:tt)+) => {
+        return Err(keylog_err!($kind, $fmt, $($arg)+).into());
+    };
+}
+
+impl From<io::Error> for KeyLogError {
+    fn from(err: io::Error) -> Self {
+        keylog_err!(IoError, err.to_string())
+    }
+}
+
+impl From<ConnectorError> for KeyLogError {
+    fn from(err: ConnectorError) -> Self {
+        keylog_err!(IoError, err.to_string())
+    }
+}
+
+impl From<SecureChannelError> for KeyLogError {
+    fn from(err: SecureChannelError) -> Self {
+        match err.kind() {
+            SecureChannelErrorKind::VerifyFailed => keylog_err!(VerifyFailed, err.to_string()),
+            _ => keylog_err!(ProtocolError, err.to_string()),
+        }
+    }
+}
+
+impl From<SerializationError> for KeyLogError {
+    fn from(err: SerializationError) -> Self {
+        keylog_err!(ProtocolError, err.to_string())
+    }
+}
@@ -0,0 +1,245 @@
+//! **WARNING: key logs are dangerous!** Anyone who obtains a key log can
+//! decrypt every message sent in the logged sessions (including any key
+//! material imported or exported in them) and forge commands for as long as
+//! the sessions remain open. Only enable key logging while debugging, never
+//! against HSMs holding production keys, and delete key logs afterwards.
+//!
+//! Key logging is disabled unless a `KeyLogFile` is explicitly attached to a
+//! `Session` (or `SessionPool`) with `set_key_log`. Once attached, the
+//! session keys (S-ENC, S-MAC, and S-RMAC) derived for every channel the
+//! session opens are appended to the file, along with their context (i.e.
+//! the host and card challenges), one session per line:
+//!
+//! ```text
+//! SCP03_SESSION_KEYS <context> <S-ENC> <S-MAC> <S-RMAC>
+//! ```
+//!
+//! `KeyLogDecoder` uses a key log to decrypt a captured stream of messages,
+//! e.g. a recording made with `RecordingConnector`.
+
+#[macro_use]
+mod error;
+
+mod decoder;
+
+pub use self::decoder::{DecodedExchange, KeyLogDecoder};
+pub use self::error::{KeyLogError, KeyLogErrorKind};
+
+use clear_on_drop::clear::Clear;
+use std::{
+    env, fmt,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use connector::{decode_hex, encode_hex};
+use securechannel::{Challenge, Context, SessionId, CHALLENGE_SIZE, CONTEXT_SIZE, KEY_SIZE};
+
+/// Environment variable which `KeyLogFile::from_env` reads the path to a key
+/// log from
+pub const KEY_LOG_ENV_VAR: &str = "YUBIHSM_KEYLOGFILE";
+
+/// Label at the beginning of each key log entry
+const KEY_LOG_LABEL: &str = "SCP03_SESSION_KEYS";
+
+/// Keys for a single SCP03 session, along with the context they were
+/// derived from. Keys are wiped from memory when dropped.
+pub struct SessionKeys {
+    /// Derivation context (host challenge followed by card challenge)
+    pub(crate) context: Context,
+
+    /// Session encryption key (S-ENC)
+    pub(crate) enc_key: [u8; KEY_SIZE],
+
+    /// Session Command MAC key (S-MAC)
+    pub(crate) mac_key: [u8; KEY_SIZE],
+
+    /// Session Respose MAC key (S-RMAC)
+    pub(crate) rmac_key: [u8; KEY_SIZE],
+}
+
+impl SessionKeys {
+    /// Borrow the context (host challenge followed by card challenge) these
+    /// keys were derived from, which identifies the session
+    pub fn context(&self) -> &[u8] {
+        self.context.as_slice()
+    }
+
+    /// Parse a key log entry
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+
+        if fields.next()? != KEY_LOG_LABEL {
+            return None;
+        }
+
+        let context = decode_hex(fields.next()?)?;
+
+        if context.len() != CONTEXT_SIZE {
+            return None;
+        }
+
+        let keys = Self {
+            context: Context::from_challenges(
+                Challenge::from_slice(&context[..CHALLENGE_SIZE]),
+                Challenge::from_slice(&context[CHALLENGE_SIZE..]),
+            ),
+            enc_key: parse_key(fields.next()?)?,
+            mac_key: parse_key(fields.next()?)?,
+            rmac_key: parse_key(fields.next()?)?,
+        };
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(keys)
+    }
+}
+
+impl fmt::Debug for SessionKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Avoid leaking the keys themselves in debug output
+        write!(f, "SessionKeys {{ context: {}, ... }}", encode_hex(self.context()))
+    }
+}
+
+impl Drop for SessionKeys {
+    fn drop(&mut self) {
+        self.enc_key.clear();
+        self.mac_key.clear();
+        self.rmac_key.clear();
+    }
+}
+
+/// Parse a hex-encoded session key
+fn parse_key(hex: &str) -> Option<[u8; KEY_SIZE]> {
+    let mut bytes = decode_hex(hex)?;
+
+    let result = if bytes.len() == KEY_SIZE {
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&bytes);
+        Some(key)
+    } else {
+        None
+    };
+
+    bytes.as_mut_slice().clear();
+    result
+}
+
+/// File which session keys are appended to.
+///
+/// **WARNING:** anyone with access to this file can decrypt and forge
+/// messages in the logged sessions! See the module documentation.
+pub struct KeyLogFile {
+    /// Path to the key log
+    path: PathBuf,
+
+    /// Key log opened for appending
+    file: Mutex<File>,
+}
+
+impl KeyLogFile {
+    /// Open the key log at the given path for appending, creating it (only
+    /// readable by the current user, on Unix) if it doesn't exist
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, KeyLogError> {
+        let path = path.as_ref();
+        let mut options = OpenOptions::new();
+        options.append(true).create(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let file = options
+            .open(path)
+            .map_err(|e| keylog_err!(IoError, "couldn't open {}: {}", path.display(), e))?;
+
+        warn!(
+            "yubihsm: logging session keys to {}! Anyone with access to this file can \
+             decrypt logged sessions",
+            path.display()
+        );
+
+        Ok(Self {
+            path: path.to_owned(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Open the key log at the path given by the `YUBIHSM_KEYLOGFILE`
+    /// environment variable, if it's set
+    pub fn from_env() -> Result<Option<Self>, KeyLogError> {
+        match env::var_os(KEY_LOG_ENV_VAR) {
+            Some(ref path) if !path.is_empty() => Self::create(path).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Borrow the path to this key log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the keys for the given session to the key log
+    pub fn write(&self, session_id: SessionId, keys: &SessionKeys) -> Result<(), KeyLogError> {
+        debug!(
+            "yubihsm: session={} logging session keys to {}",
+            session_id.to_u8(),
+            self.path.display()
+        );
+
+        let mut entry = format!(
+            "{} {} {} {} {}\n",
+            KEY_LOG_LABEL,
+            encode_hex(keys.context()),
+            encode_hex(&keys.enc_key),
+            encode_hex(&keys.mac_key),
+            encode_hex(&keys.rmac_key)
+        );
+
+        let mut file = self.file.lock().unwrap();
+        let result = file.write_all(entry.as_bytes()).and_then(|()| file.flush());
+        entry.as_mut_str().clear();
+
+        Ok(result?)
+    }
+
+    /// Read all of the session keys in the key log at the given path.
+    /// Lines which are empty or start with `#` are ignored.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<SessionKeys>, KeyLogError> {
+        let path = path.as_ref();
+        let mut key_log = String::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut key_log))
+            .map_err(|e| keylog_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+        let mut result = vec![];
+
+        for (i, line) in key_log.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            result.push(SessionKeys::parse(line).ok_or_else(|| {
+                keylog_err!(
+                    ParseError,
+                    "malformed key log {} (line {})",
+                    path.display(),
+                    i + 1
+                )
+            })?);
+        }
+
+        key_log.as_mut_str().clear();
+        Ok(result)
+    }
+}
@@ -56,26 +56,50 @@ extern crate block_modes;
 extern crate byteorder;
 extern crate clear_on_drop;
 extern crate cmac;
+#[cfg(feature = "crypto-bigint")]
+extern crate crypto_bigint;
+#[cfg(feature = "ecdsa")]
+extern crate ecdsa;
+#[cfg(feature = "ed25519")]
+extern crate ed25519;
 #[macro_use]
 extern crate failure;
 #[macro_use]
 extern crate failure_derive;
+#[cfg(feature = "futures")]
+extern crate futures;
 #[cfg(feature = "hmac")]
 extern crate hmac;
+#[cfg(feature = "k256")]
+extern crate k256;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "native-tls")]
+extern crate native_tls;
+#[cfg(test)]
+extern crate openssl;
+#[cfg(feature = "p256")]
+extern crate p256;
 #[cfg(feature = "pbkdf2")]
 extern crate pbkdf2;
 extern crate rand;
 #[cfg(feature = "ring")]
 extern crate ring;
+#[cfg(feature = "rustls")]
+extern crate rustls;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "sha-1")]
+extern crate sha1;
 #[cfg(feature = "sha2")]
 extern crate sha2;
+#[cfg(feature = "signature")]
+extern crate signature;
 extern crate subtle;
-#[cfg(feature = "mockhsm")]
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "untrusted")]
 extern crate untrusted;
 extern crate uuid;
 
@@ -107,9 +131,16 @@ pub mod commands;
 /// Client for the `yubihsm-connector` service
 pub mod connector;
 
+/// Standard encodings of keys and signatures
+mod encoding;
+
 /// Logical partitions within the `YubiHSM2`, allowing several applications to share the device
 pub mod domains;
 
+/// Session key logs for debugging encrypted traffic, in the spirit of `SSLKEYLOGFILE`
+#[cfg(feature = "keylog")]
+pub mod keylog;
+
 #[cfg(feature = "mockhsm")]
 /// Software simulation of the `YubiHSM2` for integration testing
 pub mod mockhsm;
@@ -128,6 +159,23 @@ mod securechannel;
 /// See <https://developers.yubico.com/YubiHSM2/Concepts/Session.html>
 pub mod session;
 
+/// rustls signing keys backed by keys stored in the `YubiHSM2`
+#[cfg(feature = "rustls-signer")]
+pub mod rustls_signer;
+
+/// Signers for keys stored in the `YubiHSM2` which implement the `Signer`
+/// trait from the `signature` crate
+#[cfg(feature = "signatory")]
+pub mod signatory;
+
+/// Decoder for the `YubiHSM2` wire protocol
+pub mod wire;
+
+/// X.509 certificates and certificate signing requests signed by keys stored
+/// in the `YubiHSM2`
+#[cfg(feature = "x509")]
+pub mod x509;
+
 pub use algorithm::*;
 pub use auth_key::*;
 pub use capabilities::Capability;
@@ -138,12 +186,33 @@ pub use commands::{
     get_object_info::*, get_opaque::*, get_pubkey::*, hmac::*, import_wrapped::*, list_objects::*,
     put_asymmetric_key::*, put_auth_key::*, put_hmac_key::*, put_opaque::*, put_otp_aead_key::*,
     put_wrap_key::*, reset::*, set_log_index::*, sign_ecdsa::*, sign_eddsa::*, storage_status::*,
-    unwrap_data::*, verify_hmac::*, wrap_data::*, CommandType,
+    unwrap_data::*, verify_hmac::*, wrap_data::*, Command, CommandType, Response,
 };
+#[cfg(feature = "async")]
+pub use commands::async_commands;
 #[cfg(feature = "rsa")]
 pub use commands::{sign_rsa_pkcs1v15::*, sign_rsa_pss::*};
-pub use connector::{Connector, HttpConfig, HttpConnector};
+#[cfg(feature = "async")]
+pub use connector::{AsyncConnector, AsyncHttpConnector};
+pub use connector::{
+    Connector, FailoverConfig, FailoverConnector, FailoverStrategy, HttpConfig, HttpConnector,
+    RecordingConnector, ReplayConnector,
+};
+#[cfg(feature = "tls")]
+pub use connector::TlsConfig;
 pub use domains::Domain;
+pub use encoding::{
+    ECDSASignatureComponents, EncodingError, EncodingErrorKind, PrivateKey, RSA_PUBLIC_EXPONENT,
+};
+#[cfg(feature = "keylog")]
+pub use keylog::{KeyLogDecoder, KeyLogFile};
 pub use object::*;
-pub use securechannel::SessionId;
-pub use session::{Session, SessionError};
+#[cfg(feature = "rustls-signer")]
+pub use rustls_signer::{RustlsSigner, RustlsSigningKey};
+pub use securechannel::{Challenge, ResponseCode, SessionId};
+#[cfg(feature = "async")]
+pub use session::AsyncSession;
+pub use session::{
+    Keepalive, KeepaliveConfig, ReconnectPolicy, Session, SessionError, SessionErrorKind,
+    SessionObserver, SessionPool,
+};
@@ -0,0 +1,183 @@
+//! Attestation certificates issued by the `MockHSM`'s device attestation key
+
+use byteorder::{BigEndian, ByteOrder};
+use ring::rand::SystemRandom;
+
+use super::objects::{ECDSAKeyPair, Object};
+use commands::get_pubkey::PublicKey;
+use encoding::{der, oid};
+use x509::extension;
+use AsymmetricAlgorithm;
+
+/// Common name of the device attestation certificate (i.e. its subject, and
+/// the issuer of attestation certificates)
+const DEVICE_CERTIFICATE_NAME: &str = "MockHSM Attestation";
+
+/// Firmware version reported in attestation certificates (as `device_info`)
+const FIRMWARE_VERSION: [u8; 3] = [2, 0, 0];
+
+/// Serial number reported in attestation certificates (as `device_info`)
+const SERIAL_NUMBER: u32 = 2_000_000;
+
+/// keyUsage of the device attestation certificate: keyCertSign
+const KEY_CERT_SIGN: u8 = 0x04;
+
+/// Device attestation key, which signs attestation certificates
+pub(crate) struct AttestationKey {
+    /// NIST P-256 keypair
+    keypair: ECDSAKeyPair,
+
+    /// Self-signed CA certificate of the keypair (DER)
+    certificate: Vec<u8>,
+}
+
+impl AttestationKey {
+    /// Generate a device attestation key and its certificate
+    pub fn generate() -> Self {
+        let keypair = ECDSAKeyPair::generate(AsymmetricAlgorithm::EC_P256, &SystemRandom::new());
+
+        let public_key = PublicKey {
+            algorithm: AsymmetricAlgorithm::EC_P256,
+            bytes: keypair.public_key_bytes.clone(),
+        };
+
+        // BasicConstraints ::= SEQUENCE { cA BOOLEAN, ... }
+        let basic_constraints = der::sequence(&[&der::boolean(true)]);
+        let key_usage = der::encode(der::BIT_STRING, &[2, KEY_CERT_SIGN]);
+
+        let tbs_certificate = tbs_certificate(
+            1,
+            DEVICE_CERTIFICATE_NAME,
+            &public_key,
+            &[
+                extension(oid::BASIC_CONSTRAINTS, true, &basic_constraints),
+                extension(oid::KEY_USAGE, true, &key_usage),
+            ],
+        );
+
+        let certificate = sign(&keypair, &tbs_certificate);
+
+        Self {
+            keypair,
+            certificate,
+        }
+    }
+
+    /// Borrow the device attestation certificate
+    pub fn certificate(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    /// Issue an attestation certificate for the given asymmetric key,
+    /// recording its properties in the extensions the `YubiHSM2` uses
+    pub fn attest(&self, object: &Object) -> Vec<u8> {
+        let info = &object.object_info;
+
+        let public_key = PublicKey {
+            algorithm: AsymmetricAlgorithm::from_algorithm(object.algorithm()).unwrap(),
+            bytes: object.payload.public_key_bytes().unwrap(),
+        };
+
+        let mut serial_number = [0u8; 4];
+        BigEndian::write_u32(&mut serial_number, SERIAL_NUMBER);
+
+        let mut domains = [0u8; 2];
+        BigEndian::write_u16(&mut domains, info.domains.bits());
+
+        let mut capabilities = [0u8; 8];
+        BigEndian::write_u64(&mut capabilities, info.capabilities.bits());
+
+        let mut object_id = [0u8; 2];
+        BigEndian::write_u16(&mut object_id, info.object_id);
+
+        // Labels are recorded up to their first NUL byte
+        let label = match info.label.iter().position(|&b| b == 0) {
+            Some(len) => &info.label[..len],
+            None => &info.label[..],
+        };
+
+        let extensions = [
+            (
+                oid::YUBIHSM_FIRMWARE_VERSION,
+                der::encode(der::OCTET_STRING, &FIRMWARE_VERSION),
+            ),
+            (oid::YUBIHSM_SERIAL_NUMBER, der::integer(&serial_number)),
+            (oid::YUBIHSM_ORIGIN, der::bit_string(&[info.origin.to_u8()])),
+            (oid::YUBIHSM_DOMAINS, der::bit_string(&domains)),
+            (oid::YUBIHSM_CAPABILITIES, der::bit_string(&capabilities)),
+            (oid::YUBIHSM_OBJECT_ID, der::integer(&object_id)),
+            (oid::YUBIHSM_LABEL, der::encode(der::UTF8_STRING, label)),
+        ];
+
+        let extensions: Vec<Vec<u8>> = extensions
+            .iter()
+            .map(|&(id, ref value)| extension(id, false, value))
+            .collect();
+
+        let tbs_certificate = tbs_certificate(
+            u32::from(info.object_id) + 2,
+            &format!("YubiHSM Attestation id:0x{:04x}", info.object_id),
+            &public_key,
+            &extensions,
+        );
+
+        sign(&self.keypair, &tbs_certificate)
+    }
+}
+
+/// Encode a `TBSCertificate` issued by the device attestation key
+fn tbs_certificate(
+    serial_number: u32,
+    subject: &str,
+    public_key: &PublicKey,
+    extensions: &[Vec<u8>],
+) -> Vec<u8> {
+    let mut serial = [0u8; 4];
+    BigEndian::write_u32(&mut serial, serial_number);
+
+    let extensions: Vec<&[u8]> = extensions.iter().map(Vec::as_slice).collect();
+
+    der::sequence(&[
+        // version [0] EXPLICIT: v3
+        &der::encode(der::CONTEXT_SPECIFIC | der::CONSTRUCTED, &der::integer(&[2])),
+        &der::integer(&serial),
+        &signature_algorithm(),
+        &name(DEVICE_CERTIFICATE_NAME),
+        // Valid from 2000 onwards, with no well-defined expiration (RFC 5280)
+        &der::sequence(&[
+            &der::encode(der::UTC_TIME, b"000101000000Z"),
+            &der::encode(der::GENERALIZED_TIME, b"99991231235959Z"),
+        ]),
+        &name(subject),
+        &public_key.to_spki_der().unwrap(),
+        // extensions [3] EXPLICIT
+        &der::encode(
+            der::CONTEXT_SPECIFIC | der::CONSTRUCTED | 3,
+            &der::sequence(&extensions),
+        ),
+    ])
+}
+
+/// Encode a `Name` consisting of a common name
+fn name(common_name: &str) -> Vec<u8> {
+    der::sequence(&[&der::set_of(&[&der::sequence(&[
+        &der::object_identifier(oid::COMMON_NAME),
+        &der::encode(der::UTF8_STRING, common_name.as_bytes()),
+    ])])])
+}
+
+/// Sign a `TBSCertificate` with the given key
+fn sign(keypair: &ECDSAKeyPair, tbs_certificate: &[u8]) -> Vec<u8> {
+    let signature = keypair.sign(tbs_certificate);
+
+    der::sequence(&[
+        tbs_certificate,
+        &signature_algorithm(),
+        &der::bit_string(signature.as_ref()),
+    ])
+}
+
+/// `AlgorithmIdentifier` for ECDSA with SHA-256
+fn signature_algorithm() -> Vec<u8> {
+    der::sequence(&[&der::object_identifier(oid::ECDSA_WITH_SHA256)])
+}
@@ -7,6 +7,8 @@ use sha2::Sha256;
 use untrusted;
 
 use algorithm::{Algorithm, AsymmetricAlgorithm, HMACAlgorithm};
+#[cfg(feature = "attestation")]
+use commands::attest_asymmetric::{AttestAsymmetricCommand, AttestationCertificate};
 use commands::{
     blink::BlinkResponse,
     close_session::CloseSessionResponse,
@@ -37,10 +39,10 @@ use commands::{
     sign_eddsa::{ED25519_SIGNATURE_SIZE, Ed25519Signature, SignDataEdDSACommand},
     storage_status::StorageStatusResponse,
     verify_hmac::{VerifyHMACCommand, VerifyHMACResponse},
-    CommandType, Response,
+    CommandType, SerializeResponse,
 };
 use connector::ConnectorError;
-use securechannel::{CommandMessage, ResponseMessage};
+use securechannel::{CommandMessage, ResponseCode, ResponseMessage};
 use serializers::deserialize;
 use {Capability, ObjectType, SessionId, WrapMessage, WrapNonce};
 
@@ -100,6 +102,8 @@ pub(crate) fn session_message(
         .decrypt_command(encrypted_command);
 
     let response = match command.command_type {
+        #[cfg(feature = "attestation")]
+        CommandType::AttestAsymmetric => attest_asymmetric(state, &command.data),
         CommandType::Blink => BlinkResponse {}.serialize(),
         CommandType::CloseSession => return close_session(state, session_id),
         CommandType::DeleteObject => delete_object(state, &command.data),
@@ -137,6 +141,30 @@ pub(crate) fn session_message(
         .into())
 }
 
+/// Generate an attestation certificate for an asymmetric key, signed by the
+/// device attestation key (the only attestation key the `MockHSM` supports)
+#[cfg(feature = "attestation")]
+fn attest_asymmetric(state: &State, cmd_data: &[u8]) -> ResponseMessage {
+    let command: AttestAsymmetricCommand = deserialize(cmd_data)
+        .unwrap_or_else(|e| panic!("error parsing CommandType::AttestAsymmetric: {:?}", e));
+
+    if command.attestation_key_id != 0 {
+        return ResponseMessage::error(
+            ResponseCode::DeviceInvalidData,
+            "MockHSM only supports attestation with the device attestation key",
+        );
+    }
+
+    if let Some(obj) = state.objects.get(command.key_id, ObjectType::AsymmetricKey) {
+        AttestationCertificate(state.attestation_key.attest(obj)).serialize()
+    } else {
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.key_id),
+        )
+    }
+}
+
 /// Close an active session
 fn close_session(state: &mut State, session_id: SessionId) -> Result<Vec<u8>, ConnectorError> {
     let response = state
@@ -159,7 +187,10 @@ fn delete_object(state: &mut State, cmd_data: &[u8]) -> ResponseMessage {
     {
         DeleteObjectResponse {}.serialize()
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.object_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.object_id),
+        )
     }
 }
 
@@ -245,7 +276,10 @@ fn export_wrapped(state: &mut State, cmd_data: &[u8]) -> ResponseMessage {
         .wrap(wrap_key_id, object_id, object_type, &nonce)
     {
         Ok(ciphertext) => ExportWrappedResponse(WrapMessage { nonce, ciphertext }).serialize(),
-        Err(e) => ResponseMessage::error(&format!("error wrapping object: {}", e)),
+        Err(e) => ResponseMessage::error(
+            ResponseCode::DeviceInvalidData,
+            &format!("error wrapping object: {}", e),
+        ),
     }
 }
 
@@ -334,7 +368,10 @@ fn get_object_info(state: &State, cmd_data: &[u8]) -> ResponseMessage {
     {
         GetObjectInfoResponse(obj.object_info.clone()).serialize()
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.0.object_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.0.object_id),
+        )
     }
 }
 
@@ -343,13 +380,21 @@ fn get_opaque(state: &State, cmd_data: &[u8]) -> ResponseMessage {
     let command: GetOpaqueCommand = deserialize(cmd_data)
         .unwrap_or_else(|e| panic!("error parsing CommandType::GetOpaqueObject: {:?}", e));
 
+    // Opaque object 0 is the certificate of the device attestation key
+    #[cfg(feature = "attestation")]
+    {
+        if command.object_id == 0 {
+            return GetOpaqueResponse(state.attestation_key.certificate().into()).serialize();
+        }
+    }
+
     if let Some(obj) = state.objects.get(command.object_id, ObjectType::Opaque) {
         GetOpaqueResponse(obj.payload.as_ref().into()).serialize()
     } else {
-        ResponseMessage::error(&format!(
-            "no such opaque object ID: {:?}",
-            command.object_id
-        ))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such opaque object ID: {:?}", command.object_id),
+        )
     }
 }
 
@@ -376,7 +421,10 @@ fn get_pubkey(state: &State, cmd_data: &[u8]) -> ResponseMessage {
             bytes: obj.payload.public_key_bytes().unwrap(),
         }.serialize()
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.key_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.key_id),
+        )
     }
 }
 
@@ -393,10 +441,16 @@ fn hmac_data(state: &State, cmd_data: &[u8]) -> ResponseMessage {
             let tag = mac.result();
             HMACTag(tag.code().as_ref().into()).serialize()
         } else {
-            ResponseMessage::error(&format!("not an HMAC key: {:?}", obj.algorithm()))
+            ResponseMessage::error(
+                ResponseCode::DeviceInvalidData,
+                &format!("not an HMAC key: {:?}", obj.algorithm()),
+            )
         }
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.key_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.key_id),
+        )
     }
 }
 
@@ -414,7 +468,10 @@ fn import_wrapped(state: &mut State, cmd_data: &[u8]) -> ResponseMessage {
             object_type: obj.object_type,
             object_id: obj.object_id,
         }.serialize(),
-        Err(e) => ResponseMessage::error(&format!("error unwrapping object: {}", e)),
+        Err(e) => ResponseMessage::error(
+            ResponseCode::DeviceInvalidData,
+            &format!("error unwrapping object: {}", e),
+        ),
     }
 }
 
@@ -450,7 +507,7 @@ fn put_asymmetric_key(state: &mut State, cmd_data: &[u8]) -> ResponseMessage {
         params.capabilities,
         Capability::default(),
         params.domains,
-        &data,
+        data.as_ref(),
     );
 
     PutAsymmetricKeyResponse { key_id: params.id }.serialize()
@@ -503,6 +560,13 @@ fn put_opaque(state: &mut State, cmd_data: &[u8]) -> ResponseMessage {
     let PutOpaqueCommand { params, data } = deserialize(cmd_data)
         .unwrap_or_else(|e| panic!("error parsing CommandType::PutOpaqueObject: {:?}", e));
 
+    if state.objects.get(params.id, ObjectType::Opaque).is_some() {
+        return ResponseMessage::error(
+            ResponseCode::DeviceObjectExists,
+            &format!("object already exists: {:?}", params.id),
+        );
+    }
+
     state.objects.put(
         params.id,
         ObjectType::Opaque,
@@ -560,13 +624,22 @@ fn sign_data_ecdsa(state: &State, cmd_data: &[u8]) -> ResponseMessage {
         .unwrap_or_else(|e| panic!("error parsing CommandType::SignDataEdDSA: {:?}", e));
 
     if let Some(obj) = state.objects.get(command.key_id, ObjectType::AsymmetricKey) {
-        if let Payload::ECDSAKeyPair(ref key) = obj.payload {
-            ECDSASignature(key.sign(command.digest).as_ref().into()).serialize()
-        } else {
-            ResponseMessage::error(&format!("not an ECDSA key: {:?}", obj.algorithm()))
+        match obj.payload {
+            Payload::ECDSAKeyPair(ref key) => {
+                ECDSASignature(key.sign(command.digest).as_ref().into()).serialize()
+            }
+            // Unlike *ring* keys, secp256k1 keys sign the digest as-is, like the `YubiHSM2`
+            Payload::Secp256k1KeyPair(ref key) => key.sign_digest(&command.digest).serialize(),
+            _ => ResponseMessage::error(
+                ResponseCode::DeviceInvalidData,
+                &format!("not an ECDSA key: {:?}", obj.algorithm()),
+            ),
         }
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.key_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.key_id),
+        )
     }
 }
 
@@ -585,10 +658,16 @@ fn sign_data_eddsa(state: &State, cmd_data: &[u8]) -> ResponseMessage {
 
             Ed25519Signature(signature_bytes).serialize()
         } else {
-            ResponseMessage::error(&format!("not an Ed25519 key: {:?}", obj.algorithm()))
+            ResponseMessage::error(
+                ResponseCode::DeviceInvalidData,
+                &format!("not an Ed25519 key: {:?}", obj.algorithm()),
+            )
         }
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.key_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.key_id),
+        )
     }
 }
 
@@ -623,9 +702,15 @@ fn verify_hmac(state: &State, cmd_data: &[u8]) -> ResponseMessage {
 
             VerifyHMACResponse(is_ok as u8).serialize()
         } else {
-            ResponseMessage::error(&format!("not an HMAC key: {:?}", obj.algorithm()))
+            ResponseMessage::error(
+                ResponseCode::DeviceInvalidData,
+                &format!("not an HMAC key: {:?}", obj.algorithm()),
+            )
         }
     } else {
-        ResponseMessage::error(&format!("no such object ID: {:?}", command.key_id))
+        ResponseMessage::error(
+            ResponseCode::DeviceObjNotFound,
+            &format!("no such object ID: {:?}", command.key_id),
+        )
     }
 }
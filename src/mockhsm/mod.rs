@@ -1,7 +1,11 @@
+#[cfg(feature = "async")]
+use futures::future;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+#[cfg(feature = "attestation")]
+mod attestation;
 mod commands;
 mod objects;
 mod session;
@@ -10,10 +14,14 @@ mod state;
 use self::state::State;
 use auth_key::AuthKey;
 use commands::CommandType;
+#[cfg(feature = "async")]
+use connector::{AsyncConnector, ConnectorFuture};
 use connector::{Connector, ConnectorError, ConnectorErrorKind, Status};
 use object::ObjectId;
 use securechannel::CommandMessage;
-use session::{Session, SessionError};
+#[cfg(feature = "async")]
+use session::{AsyncSession, CreateSessionFuture};
+use session::{ReconnectPolicy, Session, SessionError, SessionPool};
 
 /// Software simulation of a `YubiHSM2` intended for testing
 /// implemented as a `yubihsm::Connector` (skipping HTTP transport)
@@ -27,6 +35,12 @@ impl MockHSM {
         MockHSM(Arc::new(Mutex::new(State::new())))
     }
 
+    /// Create a connector to this MockHSM, e.g. for use as an endpoint of a
+    /// `FailoverConnector`
+    pub fn connector(&self) -> MockConnector {
+        MockConnector(self.0.clone())
+    }
+
     /// Create a simulated session with a MockHSM
     pub fn create_session<K: Into<AuthKey>>(
         &self,
@@ -37,11 +51,52 @@ impl MockHSM {
             MockConnector(self.0.clone()),
             auth_key_id,
             auth_key.into(),
-            false,
+            retain_credentials(),
+        )
+    }
+
+    /// Create a simulated asynchronous session with a MockHSM
+    #[cfg(feature = "async")]
+    pub fn create_async_session<K: Into<AuthKey>>(
+        &self,
+        auth_key_id: ObjectId,
+        auth_key: K,
+    ) -> CreateSessionFuture<MockConnector> {
+        AsyncSession::new(
+            MockConnector(self.0.clone()),
+            auth_key_id,
+            auth_key.into(),
+            retain_credentials(),
+        )
+    }
+
+    /// Create a pool of simulated sessions with a MockHSM
+    pub fn create_session_pool<K: Into<AuthKey>>(
+        &self,
+        auth_key_id: ObjectId,
+        auth_key: K,
+        max_sessions: usize,
+    ) -> SessionPool<MockConnector> {
+        let state = self.0.clone();
+
+        SessionPool::new(
+            move || Ok(MockConnector(state.clone())),
+            auth_key_id,
+            auth_key.into(),
+            max_sessions,
         )
     }
 }
 
+/// Policy for MockHSM sessions, which don't reconnect, but retain their
+/// credentials so they can be rekeyed
+fn retain_credentials() -> ReconnectPolicy {
+    ReconnectPolicy {
+        retain_credentials: true,
+        ..ReconnectPolicy::disabled()
+    }
+}
+
 impl Default for MockHSM {
     fn default() -> Self {
         Self::new()
@@ -52,7 +107,7 @@ impl Default for MockHSM {
 pub struct MockConnector(Arc<Mutex<State>>);
 
 /// Fake config
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct MockConfig;
 
 impl fmt::Display for MockConfig {
@@ -110,3 +165,28 @@ impl Connector for MockConnector {
         }
     }
 }
+
+/// The MockHSM processes commands synchronously, so its asynchronous
+/// connector API returns futures which are already resolved
+#[cfg(feature = "async")]
+impl AsyncConnector for MockConnector {
+    type Config = MockConfig;
+
+    /// MockHSM connectors can't be opened from a config: returns an error
+    fn open(_config: MockConfig) -> ConnectorFuture<Self> {
+        Box::new(future::err(ConnectorError::new(
+            ConnectorErrorKind::ConnectionFailed,
+            Some("use MockHSM::create_async_session() to open a MockHSM session".to_owned()),
+        )))
+    }
+
+    /// GET /connector/status returning the result as connector::Status
+    fn status(&self) -> ConnectorFuture<Status> {
+        Box::new(future::result(Connector::status(self)))
+    }
+
+    /// POST /connector/api with a given command message and return the response message
+    fn send_command(&self, uuid: Uuid, body: Vec<u8>) -> ConnectorFuture<Vec<u8>> {
+        Box::new(future::result(Connector::send_command(self, uuid, body)))
+    }
+}
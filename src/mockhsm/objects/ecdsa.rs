@@ -5,17 +5,11 @@ use ring::rand::{SecureRandom, SystemRandom};
 // TODO: ideally *ring* could do everything our `ECDSAKeyPair` type is doing.
 // This is the biggest blocker: https://github.com/briansmith/ring/issues/672
 use ring::signature::ECDSAKeyPair as ECDSAPrivateKey;
-use ring::signature::{ECDSA_P256_SHA256_ASN1_SIGNING, Signature};
+use ring::signature::{Signature, ECDSA_P256_SHA256_ASN1_SIGNING, ECDSA_P384_SHA384_ASN1_SIGNING};
 use untrusted;
 
 use AsymmetricAlgorithm;
 
-/// Size of a NIST P-256 keypair
-pub(crate) const ECDSA_KEY_PAIR_SIZE: usize = 96;
-
-/// Size of a raw NIST P-256 uncompressed public key (i.e. sans DER OCTET STRING tag)
-pub(crate) const ECDSA_PUBLIC_KEY_SIZE: usize = 64;
-
 /// ECDSA keypairs (TODO: use upstream *ring* functionality for this when it becomes available)
 pub(crate) struct ECDSAKeyPair {
     /// *ring* SigningAlgorithm
@@ -33,6 +27,7 @@ impl ECDSAKeyPair {
     pub fn generate(algorithm: AsymmetricAlgorithm, csprng: &SecureRandom) -> Self {
         let signing_algorithm = match algorithm {
             AsymmetricAlgorithm::EC_P256 => &ECDSA_P256_SHA256_ASN1_SIGNING,
+            AsymmetricAlgorithm::EC_P384 => &ECDSA_P384_SHA384_ASN1_SIGNING,
             _ => panic!("unsupported ECDSA algorithm: {:?}", algorithm),
         };
 
@@ -43,8 +38,10 @@ impl ECDSAKeyPair {
         );
         let private_key_len = private_key_bytes.len();
 
+        // Raw uncompressed public key (i.e. sans DER OCTET STRING tag)
+        let public_key_len = algorithm.key_len() * 2;
         let public_key_bytes =
-            Vec::from(&private_key_bytes[(private_key_len - ECDSA_PUBLIC_KEY_SIZE)..]);
+            Vec::from(&private_key_bytes[(private_key_len - public_key_len)..]);
 
         Self {
             algorithm,
@@ -53,10 +50,17 @@ impl ECDSAKeyPair {
         }
     }
 
-    /// Sign a message with this key, returning an ASN.1 DER encoded signature
+    /// Size of the keypair (i.e. private scalar and public point)
+    pub fn len(&self) -> usize {
+        self.algorithm.key_len() * 3
+    }
+
+    /// Sign a message with this key, returning an ASN.1 DER encoded signature.
+    /// The message is hashed with SHA-256 for P-256 keys, and SHA-384 for P-384.
     pub fn sign<T: AsRef<[u8]>>(&self, message: T) -> Signature {
         let signing_algorithm = match self.algorithm {
             AsymmetricAlgorithm::EC_P256 => &ECDSA_P256_SHA256_ASN1_SIGNING,
+            AsymmetricAlgorithm::EC_P384 => &ECDSA_P384_SHA384_ASN1_SIGNING,
             _ => panic!("unsupported ECDSA algorithm: {:?}", self.algorithm),
         };
 
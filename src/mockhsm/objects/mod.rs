@@ -4,12 +4,15 @@
 
 mod ecdsa;
 mod payload;
+mod secp256k1;
 
 use failure::Error;
 use ring::aead::{self, AES_128_GCM, AES_256_GCM, OpeningKey, SealingKey};
 use std::collections::hash_map::Iter as HashMapIter;
 use std::collections::HashMap;
 
+#[cfg(feature = "attestation")]
+pub(crate) use self::ecdsa::ECDSAKeyPair;
 pub(crate) use self::payload::Payload;
 use auth_key::{AuthKey, AUTH_KEY_DEFAULT_ID, AUTH_KEY_SIZE};
 use serializers::{deserialize, serialize};
@@ -5,7 +5,8 @@ use ring::rand::{SecureRandom, SystemRandom};
 use ring::signature::Ed25519KeyPair;
 use untrusted;
 
-use super::ecdsa::{ECDSAKeyPair, ECDSA_KEY_PAIR_SIZE};
+use super::ecdsa::ECDSAKeyPair;
+use super::secp256k1::Secp256k1KeyPair;
 use algorithm::{Algorithm, AsymmetricAlgorithm, HMACAlgorithm, OpaqueAlgorithm, WrapAlgorithm};
 use auth_key::{AuthKey, AUTH_KEY_SIZE};
 
@@ -29,6 +30,9 @@ pub(crate) enum Payload {
     /// Opaque data
     Opaque(OpaqueAlgorithm, Vec<u8>),
 
+    /// secp256k1 ECDSA signing keys
+    Secp256k1KeyPair(Secp256k1KeyPair),
+
     /// Wrapping (i.e. symmetric encryption keys)
     // TODO: actually simulate AES-CCM. Instead we use GCM because *ring* has it
     WrapKey(WrapAlgorithm, Vec<u8>),
@@ -49,6 +53,7 @@ impl Payload {
                 bytes.copy_from_slice(data);
                 Payload::Ed25519KeyPair(bytes)
             }
+            Algorithm::EC_K256 => Payload::Secp256k1KeyPair(Secp256k1KeyPair::from_bytes(data)),
             Algorithm::HMAC_SHA1
             | Algorithm::HMAC_SHA256
             | Algorithm::HMAC_SHA384
@@ -76,13 +81,14 @@ impl Payload {
                 csprng.fill(&mut bytes).unwrap();
                 Payload::WrapKey(wrap_alg, bytes)
             }
-            Algorithm::EC_P256 => {
+            Algorithm::EC_P256 | Algorithm::EC_P384 => {
                 let keypair = ECDSAKeyPair::generate(
                     AsymmetricAlgorithm::from_algorithm(algorithm).unwrap(),
                     &csprng,
                 );
                 Payload::ECDSAKeyPair(keypair)
             }
+            Algorithm::EC_K256 => Payload::Secp256k1KeyPair(Secp256k1KeyPair::generate(&csprng)),
             Algorithm::EC_ED25519 => {
                 let mut bytes = [0u8; ED25519_SEED_SIZE];
                 csprng.fill(&mut bytes).unwrap();
@@ -108,10 +114,11 @@ impl Payload {
     pub fn algorithm(&self) -> Algorithm {
         match *self {
             Payload::AuthKey(_) => Algorithm::YUBICO_AES_AUTH,
-            Payload::ECDSAKeyPair(_) => Algorithm::EC_P256,
+            Payload::ECDSAKeyPair(ref k) => k.algorithm.into(),
             Payload::Ed25519KeyPair(_) => Algorithm::EC_ED25519,
             Payload::HMACKey(alg, _) => alg.into(),
             Payload::Opaque(alg, _) => alg.into(),
+            Payload::Secp256k1KeyPair(_) => Algorithm::EC_K256,
             Payload::WrapKey(alg, _) => alg.into(),
         }
     }
@@ -120,10 +127,11 @@ impl Payload {
     pub fn len(&self) -> u16 {
         let l = match *self {
             Payload::AuthKey(_) => AUTH_KEY_SIZE,
-            Payload::ECDSAKeyPair(_) => ECDSA_KEY_PAIR_SIZE,
+            Payload::ECDSAKeyPair(ref k) => k.len(),
             Payload::Ed25519KeyPair(_) => ED25519_SEED_SIZE,
             Payload::HMACKey(_, ref data) => data.len(),
             Payload::Opaque(_, ref data) => data.len(),
+            Payload::Secp256k1KeyPair(ref k) => k.len(),
             Payload::WrapKey(_, ref data) => data.len(),
         };
         l as u16
@@ -139,6 +147,7 @@ impl Payload {
                     .public_key_bytes()
                     .into(),
             ),
+            Payload::Secp256k1KeyPair(ref k) => Some(k.public_key_bytes.clone()),
             _ => None,
         }
     }
@@ -160,6 +169,7 @@ impl AsRef<[u8]> for Payload {
             Payload::Ed25519KeyPair(ref k) => k.as_ref(),
             Payload::HMACKey(_, ref data) => data,
             Payload::Opaque(_, ref data) => data,
+            Payload::Secp256k1KeyPair(ref k) => &k.private_key_bytes,
             Payload::WrapKey(_, ref data) => data,
         }
     }
@@ -0,0 +1,353 @@
+//! secp256k1 ECDSA keypairs, which *ring* doesn't support.
+//!
+//! This is a minimal (and variable-time!) implementation of the curve which
+//! is only intended for testing. Don't use it with keys that matter.
+
+use crypto_bigint::{Concat, Encoding, Split, U256, U512};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use commands::sign_ecdsa::ECDSASignature;
+use encoding::ECDSASignatureComponents;
+use AsymmetricAlgorithm;
+
+/// Size of a secp256k1 private scalar (and each coordinate of a public point)
+const KEY_SIZE: usize = 32;
+
+/// Prime modulus of the secp256k1 base field
+const FIELD_MODULUS: U256 =
+    U256::from_be_hex("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F");
+
+/// `2^256 mod p`, used to fold the high half of products into the low half
+const FIELD_FOLD: U256 = U256::from_u64(0x1_0000_03D1);
+
+/// Order of the secp256k1 group
+const ORDER: U256 =
+    U256::from_be_hex("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+
+/// x-coordinate of the secp256k1 generator
+const GENERATOR_X: U256 =
+    U256::from_be_hex("79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798");
+
+/// y-coordinate of the secp256k1 generator
+const GENERATOR_Y: U256 =
+    U256::from_be_hex("483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8");
+
+/// secp256k1 ECDSA keypairs
+pub(crate) struct Secp256k1KeyPair {
+    /// Private scalar (big endian)
+    pub private_key_bytes: Vec<u8>,
+
+    /// Public key bytes (raw uncompressed point, i.e. `x || y`)
+    pub public_key_bytes: Vec<u8>,
+}
+
+impl Secp256k1KeyPair {
+    /// Generate a new secp256k1 keypair
+    pub fn generate(csprng: &SecureRandom) -> Self {
+        Self::from_scalar(&random_scalar(csprng))
+    }
+
+    /// Create a keypair from a big endian private scalar
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), KEY_SIZE, "invalid secp256k1 private key length");
+        let scalar = U256::from_be_slice(bytes);
+        assert!(
+            scalar != U256::ZERO && scalar < ORDER,
+            "invalid secp256k1 private key"
+        );
+
+        Self::from_scalar(&scalar)
+    }
+
+    /// Compute the public key for the given private scalar
+    fn from_scalar(scalar: &U256) -> Self {
+        let (x, y) = ProjectivePoint::mul_generator(scalar).to_affine();
+
+        Self {
+            private_key_bytes: scalar.to_be_bytes().to_vec(),
+            public_key_bytes: [x.to_be_bytes(), y.to_be_bytes()].concat(),
+        }
+    }
+
+    /// Size of the keypair (i.e. private scalar and public point)
+    pub fn len(&self) -> usize {
+        KEY_SIZE * 3
+    }
+
+    /// Sign a digest with this key, returning an ASN.1 DER encoded signature.
+    ///
+    /// Like the `YubiHSM2`, digests longer than the key are truncated to their
+    /// leftmost 256 bits.
+    pub fn sign_digest(&self, digest: &[u8]) -> ECDSASignature {
+        let csprng = SystemRandom::new();
+        let secret = U256::from_be_slice(&self.private_key_bytes);
+
+        let mut digest_bytes = [0u8; KEY_SIZE];
+        if digest.len() > KEY_SIZE {
+            digest_bytes.copy_from_slice(&digest[..KEY_SIZE]);
+        } else {
+            digest_bytes[KEY_SIZE - digest.len()..].copy_from_slice(digest);
+        }
+
+        let z = reduce(&U256::ZERO.concat(&U256::from_be_slice(&digest_bytes)), &ORDER);
+
+        loop {
+            let k = random_scalar(&csprng);
+            let (x, _) = ProjectivePoint::mul_generator(&k).to_affine();
+            let r = reduce(&U256::ZERO.concat(&x), &ORDER);
+
+            // s = k^-1 * (z + r * secret)
+            let s = mul_mod(
+                &invert(&k, &ORDER),
+                &z.add_mod(&mul_mod(&r, &secret, &ORDER), &ORDER),
+                &ORDER,
+            );
+
+            if r != U256::ZERO && s != U256::ZERO {
+                return ECDSASignatureComponents::new(
+                    AsymmetricAlgorithm::EC_K256,
+                    &r.to_be_bytes(),
+                    &s.to_be_bytes(),
+                ).unwrap()
+                    .to_der();
+            }
+        }
+    }
+}
+
+/// Points on the curve in Jacobian coordinates, i.e. `(X / Z^2, Y / Z^3)`
+#[derive(Copy, Clone)]
+struct ProjectivePoint {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+impl ProjectivePoint {
+    /// The point at infinity
+    const IDENTITY: ProjectivePoint = ProjectivePoint {
+        x: U256::ONE,
+        y: U256::ONE,
+        z: U256::ZERO,
+    };
+
+    /// Multiply the generator by the given scalar (with double-and-add)
+    fn mul_generator(scalar: &U256) -> Self {
+        let mut result = Self::IDENTITY;
+
+        for byte in scalar.to_be_bytes().iter() {
+            for i in (0..8).rev() {
+                result = result.double();
+
+                if (byte >> i) & 1 == 1 {
+                    result = result.add_affine(&GENERATOR_X, &GENERATOR_Y);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Double this point (`dbl-2009-l` for curves with `a = 0`)
+    fn double(&self) -> Self {
+        let a = field_mul(&self.x, &self.x);
+        let b = field_mul(&self.y, &self.y);
+        let c = field_mul(&b, &b);
+        let x_plus_b = field_add(&self.x, &b);
+        let d = field_sub(&field_sub(&field_mul(&x_plus_b, &x_plus_b), &a), &c);
+        let d = field_add(&d, &d);
+        let e = field_add(&field_add(&a, &a), &a);
+        let f = field_mul(&e, &e);
+        let x = field_sub(&f, &field_add(&d, &d));
+        let c8 = field_add(&c, &c);
+        let c8 = field_add(&c8, &c8);
+        let c8 = field_add(&c8, &c8);
+        let y = field_sub(&field_mul(&e, &field_sub(&d, &x)), &c8);
+        let yz = field_mul(&self.y, &self.z);
+        let z = field_add(&yz, &yz);
+
+        ProjectivePoint { x, y, z }
+    }
+
+    /// Add an affine point to this point (`madd-2007-bl`)
+    fn add_affine(&self, x2: &U256, y2: &U256) -> Self {
+        if self.z == U256::ZERO {
+            return ProjectivePoint {
+                x: *x2,
+                y: *y2,
+                z: U256::ONE,
+            };
+        }
+
+        let z1z1 = field_mul(&self.z, &self.z);
+        let u2 = field_mul(x2, &z1z1);
+        let s2 = field_mul(y2, &field_mul(&self.z, &z1z1));
+        let h = field_sub(&u2, &self.x);
+        let r = field_sub(&s2, &self.y);
+
+        if h == U256::ZERO {
+            return if r == U256::ZERO {
+                self.double()
+            } else {
+                Self::IDENTITY
+            };
+        }
+
+        let r = field_add(&r, &r);
+        let hh = field_mul(&h, &h);
+        let i = field_add(&hh, &hh);
+        let i = field_add(&i, &i);
+        let j = field_mul(&h, &i);
+        let v = field_mul(&self.x, &i);
+        let x = field_sub(&field_sub(&field_mul(&r, &r), &j), &field_add(&v, &v));
+        let y1j = field_mul(&self.y, &j);
+        let y = field_sub(&field_mul(&r, &field_sub(&v, &x)), &field_add(&y1j, &y1j));
+        let z1_plus_h = field_add(&self.z, &h);
+        let z = field_sub(&field_sub(&field_mul(&z1_plus_h, &z1_plus_h), &z1z1), &hh);
+
+        ProjectivePoint { x, y, z }
+    }
+
+    /// Convert this point to affine `(x, y)` coordinates
+    fn to_affine(self) -> (U256, U256) {
+        assert!(self.z != U256::ZERO, "point at infinity");
+
+        let z_inv = invert(&self.z, &FIELD_MODULUS);
+        let z_inv2 = field_mul(&z_inv, &z_inv);
+        let z_inv3 = field_mul(&z_inv2, &z_inv);
+
+        (field_mul(&self.x, &z_inv2), field_mul(&self.y, &z_inv3))
+    }
+}
+
+/// Generate a random scalar in the range `1..n`
+fn random_scalar(csprng: &SecureRandom) -> U256 {
+    loop {
+        let mut bytes = [0u8; KEY_SIZE];
+        csprng.fill(&mut bytes).unwrap();
+
+        let scalar = U256::from_be_slice(&bytes);
+
+        if scalar != U256::ZERO && scalar < ORDER {
+            return scalar;
+        }
+    }
+}
+
+/// Add two field elements
+fn field_add(a: &U256, b: &U256) -> U256 {
+    a.add_mod(b, &FIELD_MODULUS)
+}
+
+/// Subtract two field elements
+fn field_sub(a: &U256, b: &U256) -> U256 {
+    a.sub_mod(b, &FIELD_MODULUS)
+}
+
+/// Multiply two field elements
+fn field_mul(a: &U256, b: &U256) -> U256 {
+    mul_mod(a, b, &FIELD_MODULUS)
+}
+
+/// Compute `a * b mod m`
+fn mul_mod(a: &U256, b: &U256, modulus: &U256) -> U256 {
+    let (lo, hi) = a.mul_wide(b);
+
+    if *modulus == FIELD_MODULUS {
+        field_reduce(&hi.concat(&lo))
+    } else {
+        reduce(&hi.concat(&lo), modulus)
+    }
+}
+
+/// Reduce a wide integer modulo the field's prime `p`, which is close enough
+/// to `2^256` that the high half can be folded into the low half
+fn field_reduce(value: &U512) -> U256 {
+    let mut value = *value;
+
+    loop {
+        let (hi, lo) = value.split();
+
+        if hi == U256::ZERO {
+            break;
+        }
+
+        let (folded_lo, folded_hi) = hi.mul_wide(&FIELD_FOLD);
+        value = U256::ZERO
+            .concat(&lo)
+            .wrapping_add(&folded_hi.concat(&folded_lo));
+    }
+
+    let (_, mut result) = value.split();
+
+    while result >= FIELD_MODULUS {
+        result = result.wrapping_sub(&FIELD_MODULUS);
+    }
+
+    result
+}
+
+/// Reduce a wide integer modulo `m` (with binary long division)
+fn reduce(value: &U512, modulus: &U256) -> U256 {
+    let modulus = U256::ZERO.concat(modulus);
+    let mut remainder = U512::ZERO;
+
+    for byte in value.to_be_bytes().iter() {
+        for i in (0..8).rev() {
+            remainder = remainder
+                .shl_vartime(1)
+                .wrapping_add(&U512::from_u8((byte >> i) & 1));
+
+            if remainder >= modulus {
+                remainder = remainder.wrapping_sub(&modulus);
+            }
+        }
+    }
+
+    let (_, lo) = remainder.split();
+    lo
+}
+
+/// Compute `a^-1 mod m` for a prime `m` (as `a^(m - 2)`, per Fermat's little theorem)
+fn invert(a: &U256, modulus: &U256) -> U256 {
+    let exponent = modulus.wrapping_sub(&U256::from_u8(2));
+    let mut result = U256::ONE;
+
+    for byte in exponent.to_be_bytes().iter() {
+        for i in (0..8).rev() {
+            result = mul_mod(&result, &result, modulus);
+
+            if (byte >> i) & 1 == 1 {
+                result = mul_mod(&result, a, modulus);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key_test() {
+        let mut scalar = [0u8; KEY_SIZE];
+        scalar[KEY_SIZE - 1] = 3;
+
+        let keypair = Secp256k1KeyPair::from_bytes(&scalar);
+
+        // 3 * G
+        assert_eq!(
+            keypair.public_key_bytes,
+            [
+                U256::from_be_hex(
+                    "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"
+                ).to_be_bytes(),
+                U256::from_be_hex(
+                    "388F7B0F632DE8140FE337E62A37F3566500A99934C2231B6CB9FD7584B8E672"
+                ).to_be_bytes(),
+            ].concat()
+        );
+    }
+}
@@ -7,6 +7,8 @@ use connector::{ConnectorError, ConnectorErrorKind};
 use object::{ObjectId, ObjectType};
 use securechannel::{Challenge, Channel, SessionId};
 
+#[cfg(feature = "attestation")]
+use super::attestation::AttestationKey;
 use super::objects::Objects;
 use super::session::Session;
 
@@ -14,6 +16,8 @@ use super::session::Session;
 pub(crate) struct State {
     sessions: BTreeMap<SessionId, Session>,
     pub objects: Objects,
+    #[cfg(feature = "attestation")]
+    pub attestation_key: AttestationKey,
 }
 
 impl State {
@@ -22,6 +26,8 @@ impl State {
         Self {
             sessions: BTreeMap::new(),
             objects: Objects::default(),
+            #[cfg(feature = "attestation")]
+            attestation_key: AttestationKey::generate(),
         }
     }
 
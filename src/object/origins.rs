@@ -66,6 +66,17 @@ impl<'de> Deserialize<'de> for Origin {
             {
                 Origin::from_u8(value).or_else(|e| Err(E::custom(format!("{}", e))))
             }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Origin, E>
+            where
+                E: de::Error,
+            {
+                if value > 0xff {
+                    return Err(E::custom(format!("invalid object origin: {}", value)));
+                }
+
+                self.visit_u8(value as u8)
+            }
         }
 
         deserializer.deserialize_u8(OriginVisitor)
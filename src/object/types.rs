@@ -82,8 +82,11 @@ impl<'de> Deserialize<'de> for Type {
             where
                 E: de::Error,
             {
-                assert!(value < 255);
-                Type::from_u8(value as u8).or_else(|e| Err(E::custom(format!("{}", e))))
+                if value > 0xff {
+                    return Err(E::custom(format!("invalid object type: {}", value)));
+                }
+
+                self.visit_u8(value as u8)
             }
         }
 
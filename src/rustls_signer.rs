@@ -0,0 +1,254 @@
+//! Terminate TLS with a certificate whose private key lives in the `YubiHSM2`
+//! by wrapping a shared session in a `RustlsSigningKey` and putting it in a
+//! `rustls::sign::CertifiedKey` (e.g. from a `ResolvesServerCert`).
+//!
+//! Signing keys only advertise the signature schemes which the key's
+//! algorithm and capabilities allow:
+//!
+//! - ECDSA (P-256, P-384, P-521): the scheme for the curve's hash function
+//! - Ed25519: `ED25519`
+//! - RSA: RSASSA-PSS with SHA-256, SHA-384, or SHA-512
+
+use rustls::sign::{Signer, SigningKey};
+use rustls::{self, SignatureAlgorithm, SignatureScheme};
+use std::sync::{Arc, Mutex};
+
+use commands::get_object_info::get_object_info;
+use commands::get_pubkey::get_pubkey;
+use commands::sign_ecdsa::sign_ecdsa;
+use commands::sign_eddsa::sign_ed25519;
+use commands::sign_rsa_pss::sign_rsa_pss;
+use connector::HttpConnector;
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use mockhsm::MockConnector;
+use session::lock;
+use {
+    AsymmetricAlgorithm, Capability, Connector, ECDSAAlgorithm, ObjectId, ObjectType,
+    RSAPSSAlgorithm, Session, SessionError, SessionErrorKind,
+};
+
+/// rustls signing key backed by a key stored in the `YubiHSM2`
+pub struct RustlsSigningKey<C = HttpConnector>
+where
+    C: Connector,
+{
+    /// Session shared with other signing keys (or the rest of the application)
+    session: Arc<Mutex<Session<C>>>,
+
+    /// ID of the signing key
+    key_id: ObjectId,
+
+    /// Algorithm of the signing key
+    algorithm: AsymmetricAlgorithm,
+
+    /// Signature schemes supported by the key, in order of preference
+    schemes: Vec<SignatureScheme>,
+}
+
+impl<C: Connector> RustlsSigningKey<C> {
+    /// Create a signing key for the given key ID, looking up its algorithm
+    /// (with `get_pubkey`) and capabilities (with `get_object_info`)
+    pub fn create(session: Arc<Mutex<Session<C>>>, key_id: ObjectId) -> Result<Self, SessionError> {
+        let (algorithm, capabilities) = {
+            let mut s = lock(&session)?;
+            let public_key = get_pubkey(&mut s, key_id)?;
+            let info = get_object_info(&mut s, key_id, ObjectType::AsymmetricKey)?;
+            (public_key.algorithm, info.capabilities)
+        };
+
+        let schemes = supported_schemes(algorithm, capabilities);
+
+        if schemes.is_empty() {
+            return Err(SessionError::new(
+                SessionErrorKind::ProtocolError,
+                Some(format!(
+                    "key ID {} ({:?}, capabilities: {:?}) can't sign with any TLS signature scheme",
+                    key_id, algorithm, capabilities
+                )),
+            ));
+        }
+
+        Ok(Self {
+            session,
+            key_id,
+            algorithm,
+            schemes,
+        })
+    }
+
+    /// Get the ID of the signing key
+    pub fn key_id(&self) -> ObjectId {
+        self.key_id
+    }
+
+    /// Get the algorithm of the signing key
+    pub fn algorithm(&self) -> AsymmetricAlgorithm {
+        self.algorithm
+    }
+
+    /// Get the signature schemes supported by the signing key
+    pub fn schemes(&self) -> &[SignatureScheme] {
+        &self.schemes
+    }
+}
+
+impl<C> SigningKey for RustlsSigningKey<C>
+where
+    C: Connector + 'static,
+    RustlsSigner<C>: Signer,
+{
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<Signer>> {
+        let scheme = *self
+            .schemes
+            .iter()
+            .find(|scheme| offered.contains(scheme))?;
+
+        Some(Box::new(RustlsSigner {
+            session: Arc::clone(&self.session),
+            key_id: self.key_id,
+            scheme,
+        }))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        match self.algorithm {
+            AsymmetricAlgorithm::EC_ED25519 => SignatureAlgorithm::ED25519,
+            AsymmetricAlgorithm::RSA2048
+            | AsymmetricAlgorithm::RSA3072
+            | AsymmetricAlgorithm::RSA4096 => SignatureAlgorithm::RSA,
+            _ => SignatureAlgorithm::ECDSA,
+        }
+    }
+}
+
+/// Signer for the signature scheme chosen by a `RustlsSigningKey`
+pub struct RustlsSigner<C = HttpConnector>
+where
+    C: Connector,
+{
+    /// Session shared with the signing key
+    session: Arc<Mutex<Session<C>>>,
+
+    /// ID of the signing key
+    key_id: ObjectId,
+
+    /// Signature scheme to sign with
+    scheme: SignatureScheme,
+}
+
+#[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+impl<C: Connector> Signer for RustlsSigner<C> {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, rustls::Error> {
+        let mut session = lock(&self.session).map_err(tls_error)?;
+
+        match ecdsa_algorithm(self.scheme) {
+            Some(algorithm) => sign_ecdsa(&mut session, self.key_id, algorithm, message)
+                .map(|signature| signature.into_vec()),
+            None => sign(&mut session, self.key_id, self.scheme, message),
+        }.map_err(tls_error)
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+impl Signer for RustlsSigner<MockConnector> {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, rustls::Error> {
+        let mut session = lock(&self.session).map_err(tls_error)?;
+
+        match ecdsa_algorithm(self.scheme) {
+            Some(algorithm) => sign_ecdsa(&mut session, self.key_id, algorithm, message)
+                .map(|signature| signature.into_vec()),
+            None => sign(&mut session, self.key_id, self.scheme, message),
+        }.map_err(tls_error)
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+/// Signature schemes supported by a key with the given algorithm and
+/// capabilities, in order of preference
+fn supported_schemes(
+    algorithm: AsymmetricAlgorithm,
+    capabilities: Capability,
+) -> Vec<SignatureScheme> {
+    let (capability, schemes): (Capability, &[SignatureScheme]) = match algorithm {
+        AsymmetricAlgorithm::EC_P256 => (
+            Capability::ASYMMETRIC_SIGN_ECDSA,
+            &[SignatureScheme::ECDSA_NISTP256_SHA256],
+        ),
+        AsymmetricAlgorithm::EC_P384 => (
+            Capability::ASYMMETRIC_SIGN_ECDSA,
+            &[SignatureScheme::ECDSA_NISTP384_SHA384],
+        ),
+        AsymmetricAlgorithm::EC_P521 => (
+            Capability::ASYMMETRIC_SIGN_ECDSA,
+            &[SignatureScheme::ECDSA_NISTP521_SHA512],
+        ),
+        AsymmetricAlgorithm::EC_ED25519 => {
+            (Capability::ASYMMETRIC_SIGN_EDDSA, &[SignatureScheme::ED25519])
+        }
+        AsymmetricAlgorithm::RSA2048
+        | AsymmetricAlgorithm::RSA3072
+        | AsymmetricAlgorithm::RSA4096 => (
+            Capability::ASYMMETRIC_SIGN_PSS,
+            &[
+                SignatureScheme::RSA_PSS_SHA512,
+                SignatureScheme::RSA_PSS_SHA384,
+                SignatureScheme::RSA_PSS_SHA256,
+            ],
+        ),
+        _ => return vec![],
+    };
+
+    if capabilities.contains(capability) {
+        schemes.to_vec()
+    } else {
+        vec![]
+    }
+}
+
+/// ECDSA algorithm for the given signature scheme, if it's an ECDSA scheme
+fn ecdsa_algorithm(scheme: SignatureScheme) -> Option<ECDSAAlgorithm> {
+    match scheme {
+        SignatureScheme::ECDSA_NISTP256_SHA256 => Some(ECDSAAlgorithm::EC_ECDSA_SHA256),
+        SignatureScheme::ECDSA_NISTP384_SHA384 => Some(ECDSAAlgorithm::EC_ECDSA_SHA384),
+        SignatureScheme::ECDSA_NISTP521_SHA512 => Some(ECDSAAlgorithm::EC_ECDSA_SHA512),
+        _ => None,
+    }
+}
+
+/// Sign the given message with a non-ECDSA signature scheme
+fn sign<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    scheme: SignatureScheme,
+    message: &[u8],
+) -> Result<Vec<u8>, SessionError> {
+    let algorithm = match scheme {
+        SignatureScheme::ED25519 => {
+            return sign_ed25519(session, key_id, message).map(|sig| sig.as_ref().to_vec())
+        }
+        SignatureScheme::RSA_PSS_SHA256 => RSAPSSAlgorithm::RSA_PSS_SHA256,
+        SignatureScheme::RSA_PSS_SHA384 => RSAPSSAlgorithm::RSA_PSS_SHA384,
+        SignatureScheme::RSA_PSS_SHA512 => RSAPSSAlgorithm::RSA_PSS_SHA512,
+        _ => {
+            return Err(SessionError::new(
+                SessionErrorKind::ProtocolError,
+                Some(format!("unsupported signature scheme: {:?}", scheme)),
+            ))
+        }
+    };
+
+    sign_rsa_pss(session, key_id, algorithm, message).map(|signature| signature.into_vec())
+}
+
+/// Convert a session error into a rustls error
+fn tls_error(error: SessionError) -> rustls::Error {
+    rustls::Error::General(error.to_string())
+}
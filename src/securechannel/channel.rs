@@ -9,7 +9,7 @@ use byteorder::{BigEndian, ByteOrder};
 use clear_on_drop::clear::Clear;
 use cmac::crypto_mac::Mac as CryptoMac;
 use cmac::Cmac;
-#[cfg(feature = "mockhsm")]
+#[cfg(any(feature = "mockhsm", feature = "keylog"))]
 use subtle::ConstantTimeEq;
 
 use super::kdf;
@@ -21,6 +21,8 @@ use super::{
 };
 use auth_key::AuthKey;
 use commands::CommandType;
+#[cfg(feature = "keylog")]
+use keylog::SessionKeys;
 
 // Size of an AES block
 const AES_BLOCK_SIZE: usize = 16;
@@ -122,7 +124,6 @@ impl Channel {
         let enc_key = derive_key(auth_key.enc_key(), 0b100, &context);
         let mac_key = derive_key(auth_key.mac_key(), 0b110, &context);
         let rmac_key = derive_key(auth_key.mac_key(), 0b111, &context);
-        let mac_chaining_value = [0u8; MAC_SIZE * 2];
 
         Self {
             id,
@@ -132,7 +133,23 @@ impl Channel {
             enc_key,
             mac_key,
             rmac_key,
-            mac_chaining_value,
+            mac_chaining_value: [0u8; MAC_SIZE * 2],
+        }
+    }
+
+    /// Create a new channel with the given ID from previously derived
+    /// session keys (e.g. ones loaded from a key log)
+    #[cfg(feature = "keylog")]
+    pub fn from_session_keys(id: Id, keys: &SessionKeys) -> Self {
+        Self {
+            id,
+            counter: 0,
+            security_level: SecurityLevel::NoSecurityLevel,
+            context: keys.context.clone(),
+            enc_key: keys.enc_key,
+            mac_key: keys.mac_key,
+            rmac_key: keys.rmac_key,
+            mac_chaining_value: [0u8; MAC_SIZE * 2],
         }
     }
 
@@ -142,6 +159,25 @@ impl Channel {
         self.id
     }
 
+    /// Get the number of the next command to be sent over this channel
+    #[inline]
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Export the session keys for this channel, along with their context.
+    ///
+    /// Anyone with these keys can decrypt and forge messages in this session!
+    #[cfg(feature = "keylog")]
+    pub fn session_keys(&self) -> SessionKeys {
+        SessionKeys {
+            context: self.context.clone(),
+            enc_key: self.enc_key,
+            mac_key: self.mac_key,
+            rmac_key: self.rmac_key,
+        }
+    }
+
     /// Calculate the card's cryptogram for this session
     pub fn card_cryptogram(&self) -> Cryptogram {
         let mut result_bytes = [0u8; CRYPTOGRAM_SIZE];
@@ -241,11 +277,15 @@ impl Channel {
     ) -> Result<CommandMessage, SecureChannelError> {
         assert_eq!(self.security_level, SecurityLevel::Authenticated);
 
-        let mut message: Vec<u8> = command.into();
-        let pos = message.len();
+        let mut plaintext: Vec<u8> = command.into();
+        let pos = plaintext.len();
 
-        // Provide space at the end of the vec for the padding
+        // Provide space at the end of the vec for the padding, allocating it
+        // up front so the plaintext isn't left behind by a reallocation
+        let mut message = Vec::with_capacity(pos + AES_BLOCK_SIZE);
+        message.extend_from_slice(&plaintext);
         message.extend_from_slice(&[0u8; AES_BLOCK_SIZE]);
+        plaintext.as_mut_slice().clear();
 
         let cipher = Aes128::new_varkey(&self.enc_key).unwrap();
         let icv = compute_icv(&cipher, self.counter);
@@ -325,8 +365,9 @@ impl Channel {
         Ok(())
     }
 
-    /// Verify a host authentication message (for simulating a connector/card)
-    #[cfg(feature = "mockhsm")]
+    /// Verify a host authentication message (for simulating a connector/card,
+    /// or decoding captured traffic)
+    #[cfg(any(feature = "mockhsm", feature = "keylog"))]
     pub fn verify_authenticate_session(
         &mut self,
         command: &CommandMessage,
@@ -367,7 +408,7 @@ impl Channel {
     }
 
     /// Verify and decrypt a command from the host
-    #[cfg(feature = "mockhsm")]
+    #[cfg(any(feature = "mockhsm", feature = "keylog"))]
     pub fn decrypt_command(
         &mut self,
         encrypted_command: CommandMessage,
@@ -399,7 +440,7 @@ impl Channel {
     }
 
     /// Verify a Command MAC (C-MAC) value, updating the internal session state
-    #[cfg(feature = "mockhsm")]
+    #[cfg(any(feature = "mockhsm", feature = "keylog"))]
     pub fn verify_command_mac(
         &mut self,
         command: &CommandMessage,
@@ -504,8 +545,8 @@ impl Channel {
         });
     }
 
-    /// Terminate the session
-    fn terminate(&mut self) {
+    /// Terminate the session, wiping its keys
+    pub fn terminate(&mut self) {
         self.security_level = SecurityLevel::Terminated;
         self.enc_key.clear();
         self.mac_key.clear();
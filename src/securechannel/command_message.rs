@@ -6,9 +6,8 @@
 //!
 //! <https://developers.yubico.com/YubiHSM2/Commands/>
 
-#[cfg(feature = "mockhsm")]
-use byteorder::ByteOrder;
-use byteorder::{BigEndian, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use clear_on_drop::clear::Clear;
 use rand::{self, RngCore};
 use uuid::Uuid;
 
@@ -18,7 +17,7 @@ use commands::CommandType;
 /// A command sent from the host to the `YubiHSM2`. May or may not be
 /// authenticated using SCP03's chained/evolving MAC protocol.
 #[derive(Debug)]
-pub(crate) struct CommandMessage {
+pub struct CommandMessage {
     /// UUID which uniquely identifies this command
     pub uuid: Uuid,
 
@@ -37,7 +36,7 @@ pub(crate) struct CommandMessage {
 
 impl CommandMessage {
     /// Create a new command message without a MAC
-    pub fn new<T>(command_type: CommandType, command_data: T) -> Result<Self, SecureChannelError>
+    pub(crate) fn new<T>(command_type: CommandType, command_data: T) -> Result<Self, SecureChannelError>
     where
         T: Into<Vec<u8>>,
     {
@@ -61,7 +60,7 @@ impl CommandMessage {
     }
 
     /// Create a new command message with a MAC
-    pub fn new_with_mac<D, M>(
+    pub(crate) fn new_with_mac<D, M>(
         command_type: CommandType,
         session_id: SessionId,
         command_data: D,
@@ -90,8 +89,8 @@ impl CommandMessage {
         })
     }
 
-    /// Parse a command structure from a vector, taking ownership of the vector
-    #[cfg(feature = "mockhsm")]
+    /// Parse a command structure from a vector, taking ownership of the vector.
+    /// Parsed commands are assigned a new random UUID.
     pub fn parse(mut bytes: Vec<u8>) -> Result<Self, SecureChannelError> {
         if bytes.len() < 3 {
             secure_channel_fail!(
@@ -180,7 +179,10 @@ impl Into<Vec<u8>> for CommandMessage {
             result.push(session_id.to_u8());
         }
 
-        result.append(&mut self.data);
+        // Copy rather than append the data so its buffer (which may contain
+        // secrets, e.g. keys being imported) can be cleared
+        result.extend_from_slice(&self.data);
+        self.data.as_mut_slice().clear();
 
         if let Some(mac) = self.mac {
             result.extend_from_slice(mac.as_slice());
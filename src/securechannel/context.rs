@@ -6,6 +6,7 @@ use super::{Challenge, CHALLENGE_SIZE};
 pub const CONTEXT_SIZE: usize = CHALLENGE_SIZE * 2;
 
 /// Derivation context (i.e. concatenated challenges)
+#[derive(Clone)]
 pub struct Context([u8; CONTEXT_SIZE]);
 
 impl Context {
@@ -42,10 +42,14 @@ pub const MAX_MSG_SIZE: usize = 2048;
 
 pub use self::challenge::{Challenge, CHALLENGE_SIZE};
 pub(crate) use self::channel::Channel;
+pub use self::channel::MAX_COMMANDS_PER_SESSION;
 pub use self::channel::Id as SessionId;
-pub(crate) use self::command_message::CommandMessage;
-pub use self::context::{Context, CONTEXT_SIZE};
+pub use self::command_message::CommandMessage;
+pub use self::context::Context;
+#[cfg(feature = "keylog")]
+pub use self::context::CONTEXT_SIZE;
 pub use self::cryptogram::{Cryptogram, CRYPTOGRAM_SIZE};
 pub use self::error::{SecureChannelError, SecureChannelErrorKind};
-pub(crate) use self::mac::{Mac, MAC_SIZE};
-pub(crate) use self::response_message::{ResponseCode, ResponseMessage};
+pub use self::mac::{Mac, MAC_SIZE};
+pub use self::response_message::ResponseCode;
+pub use self::response_message::ResponseMessage;
@@ -12,7 +12,7 @@ use commands::CommandType;
 
 /// Command responses
 #[derive(Debug)]
-pub(crate) struct ResponseMessage {
+pub struct ResponseMessage {
     /// Success (for a given command type) or an error type
     pub code: ResponseCode,
 
@@ -89,8 +89,8 @@ impl ResponseMessage {
     }
 
     /// Create a new response without an associated session
-    #[cfg(feature = "mockhsm")]
-    pub fn new<T>(code: ResponseCode, response_data: T) -> ResponseMessage
+    #[cfg(any(feature = "mockhsm", feature = "keylog"))]
+    pub(crate) fn new<T>(code: ResponseCode, response_data: T) -> ResponseMessage
     where
         T: Into<Vec<u8>>,
     {
@@ -104,7 +104,7 @@ impl ResponseMessage {
 
     /// Create a new response message with a MAC
     #[cfg(feature = "mockhsm")]
-    pub fn new_with_mac<D, M>(
+    pub(crate) fn new_with_mac<D, M>(
         code: ResponseCode,
         session_id: SessionId,
         response_data: D,
@@ -123,28 +123,47 @@ impl ResponseMessage {
     }
 
     /// Create a successful response
-    #[cfg(feature = "mockhsm")]
-    pub fn success<T>(command_type: CommandType, response_data: T) -> ResponseMessage
+    #[cfg(any(feature = "mockhsm", feature = "keylog"))]
+    pub(crate) fn success<T>(command_type: CommandType, response_data: T) -> ResponseMessage
     where
         T: Into<Vec<u8>>,
     {
         Self::new(ResponseCode::Success(command_type), response_data)
     }
 
-    /// Create an error response
+    /// Create an error response with the given device error code, in the
+    /// same form as the `YubiHSM2` itself
     #[cfg(feature = "mockhsm")]
-    pub fn error(message: &str) -> ResponseMessage {
-        ResponseMessage::new(ResponseCode::MemoryError, message.as_bytes())
+    pub(crate) fn error(code: ResponseCode, message: &str) -> ResponseMessage {
+        debug!("yubihsm: MockHSM error: {:?} ({})", code, message);
+
+        let device_error = ResponseCode::DeviceOK.to_u8() - code.to_u8();
+        Self::success(CommandType::Error, vec![device_error])
     }
 
     /// Did an error occur?
     pub fn is_err(&self) -> bool {
         match self.code {
+            ResponseCode::Success(CommandType::Error) => true,
             ResponseCode::Success(_) => false,
             _ => true,
         }
     }
 
+    /// Get the error code for an error response. Errors reported by the
+    /// `YubiHSM2` itself are sent as an `Error` response whose body is the
+    /// device error code.
+    pub fn error_code(&self) -> ResponseCode {
+        match self.code {
+            ResponseCode::Success(CommandType::Error) => self
+                .data
+                .first()
+                .and_then(|&byte| ResponseCode::from_device_error(byte).ok())
+                .unwrap_or(ResponseCode::GenericError),
+            code => code,
+        }
+    }
+
     /// Get the command being responded to
     pub fn command(&self) -> Option<CommandType> {
         match self.code {
@@ -194,35 +213,94 @@ impl Into<Vec<u8>> for ResponseMessage {
 /// Codes associated with `YubiHSM2` responses
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ResponseCode {
+    /// Successful response to the given command type
     Success(CommandType),
+
+    /// Memory error
     MemoryError,
+
+    /// Initialization error
     InitError,
+
+    /// Connection error
     NetError,
+
+    /// Couldn't find the connector
     ConnectorNotFound,
+
+    /// Invalid parameters
     InvalidParams,
+
+    /// Wrong length
     WrongLength,
+
+    /// Buffer too small
     BufferTooSmall,
+
+    /// Cryptogram mismatch (e.g. wrong auth key)
     CryptogramMismatch,
+
+    /// Error authenticating the session
     AuthSessionError,
+
+    /// MAC mismatch
     MACMismatch,
+
+    /// Device success
     DeviceOK,
+
+    /// Invalid command
     DeviceInvalidCommand,
+
+    /// Malformed command or invalid data
     DeviceInvalidData,
+
+    /// Invalid session
     DeviceInvalidSession,
+
+    /// Message encryption or verification failed
     DeviceAuthFail,
+
+    /// All sessions are allocated
     DeviceSessionsFull,
+
+    /// Session creation failed
     DeviceSessionFailed,
+
+    /// Storage failure
     DeviceStorageFailed,
+
+    /// Wrong length
     DeviceWrongLength,
+
+    /// Insufficient permissions for the operation
     DeviceInvalidPermission,
+
+    /// Audit log is full and forced auditing is enabled
     DeviceLogFull,
+
+    /// Object not found
     DeviceObjNotFound,
+
+    /// Invalid object ID
     DeviceIDIllegal,
+
+    /// Invalid OTP
     DeviceInvalidOTP,
+
+    /// Device is in demo mode and must be power cycled
     DeviceDemoMode,
+
+    /// Command execution hasn't finished
     DeviceCmdUnexecuted,
+
+    /// Unknown error
     GenericError,
+
+    /// An object with the given ID already exists
     DeviceObjectExists,
+
+    /// Connector operation failed
     ConnectorError,
 }
 
@@ -272,6 +350,15 @@ impl ResponseCode {
         })
     }
 
+    /// Convert an error code from the body of an `Error` response (e.g.
+    /// `0x0b` for "object not found") into a ResponseCode
+    pub fn from_device_error(byte: u8) -> Result<Self, SecureChannelError> {
+        match ResponseCode::DeviceOK.to_u8().checked_sub(byte) {
+            Some(code) if byte > 0 => ResponseCode::from_u8(code),
+            _ => secure_channel_fail!(ProtocolError, "invalid device error code: {}", byte),
+        }
+    }
+
     /// Convert a ResponseCode back into its original byte form
     pub fn to_u8(self) -> u8 {
         let code: i8 = match self {
@@ -0,0 +1,648 @@
+//! Asynchronous encrypted sessions with the `YubiHSM2`, built on futures
+//!
+//! `AsyncSession` uses the same secure channel and serialization code as
+//! `Session`, and the same `ReconnectPolicy`, automatic rekeying, and
+//! `SessionObserver` hooks, but sends messages through an `AsyncConnector`.
+//! As encrypted commands must be sent in order, each command takes ownership
+//! of the session and returns it along with the response once the command
+//! completes, or along with the error if it fails.
+//!
+//! Delays between reconnect attempts use the tokio timer, so sessions whose
+//! `ReconnectPolicy` makes more than one attempt must be driven by a tokio
+//! runtime. Keepalives and key logging are only supported by `Session`.
+
+// Failed commands return the session along with the error, so it can be reused
+#![allow(unknown_lints, result_large_err)]
+
+use futures::future::{self, Future, Loop};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::timer::Delay;
+
+use super::observer::{
+    notify_command_finished, notify_command_started, notify_reconnect_attempted,
+};
+use super::{
+    check_decrypted_response, check_rekey_threshold, check_response, decode_response,
+    open_channel, timed_out, Credentials, ReconnectPolicy, SessionError, SessionErrorKind,
+    SessionObserver, CONNECTOR_STATUS_OK, DEFAULT_REKEY_THRESHOLD,
+};
+use auth_key::AuthKey;
+use commands::{
+    close_session::CloseSessionCommand,
+    create_session::{self, CreateSessionCommand},
+    Command, CommandType,
+};
+use connector::{AsyncConnector, AsyncHttpConnector, HttpConfig, Status as ConnectorStatus};
+use object::ObjectId;
+use securechannel::{Challenge, Channel, CommandMessage, ResponseMessage, SessionId};
+
+/// Future which resolves to a session and the result of a command. If the
+/// command fails, the session is returned along with the error, so it can
+/// still be used (e.g. after the HSM returns an error) or closed.
+pub type SessionFuture<C, T> = Box<
+    Future<Item = (AsyncSession<C>, T), Error = (AsyncSession<C>, SessionError)> + Send,
+>;
+
+/// Future which resolves to a newly authenticated session
+pub type CreateSessionFuture<C> = Box<Future<Item = AsyncSession<C>, Error = SessionError> + Send>;
+
+/// Future which resolves to a session once it has been reconnected, rekeyed,
+/// or authenticated
+type StepFuture<C> =
+    Box<Future<Item = AsyncSession<C>, Error = (AsyncSession<C>, SessionError)> + Send>;
+
+/// Future which resolves to the next iteration of the reconnect loop, i.e.
+/// the reconnected session or the session and number of the next attempt
+type ReconnectFuture<C> = Box<
+    Future<
+            Item = Loop<AsyncSession<C>, (AsyncSession<C>, u32)>,
+            Error = (AsyncSession<C>, SessionError),
+        > + Send,
+>;
+
+/// Asynchronous encrypted session with the `YubiHSM2`.
+///
+/// Unlike `Session`, an `AsyncSession` is not closed on `Drop`, as doing so
+/// would require blocking. Use `AsyncSession::close` to close it explicitly,
+/// otherwise the `YubiHSM2` will close it after `SESSION_INACTIVITY_TIMEOUT`.
+pub struct AsyncSession<C = AsyncHttpConnector>
+where
+    C: AsyncConnector,
+{
+    /// Encrypted channel to the HSM
+    channel: Channel,
+
+    /// Connector to send messages through
+    connector: C,
+
+    /// Instant when the last command with the YubiHSM2 was sent. Used for
+    /// tracking session inactivity timeouts
+    last_command_timestamp: Instant,
+
+    /// Is the connection presumed to be healthy?
+    active: bool,
+
+    /// Cached `Credentials` for reconnecting lost sessions and rekeying
+    credentials: Option<Credentials>,
+
+    /// Policy for reconnecting lost sessions and retrying failed commands
+    reconnect_policy: ReconnectPolicy,
+
+    /// Channel counter value at which the session is automatically rekeyed
+    rekey_threshold: u32,
+
+    /// Observer notified about commands and reconnects (if any)
+    observer: Option<Arc<SessionObserver>>,
+}
+
+// Special cased for AsyncHttpConnector for the same reasons as `Session::create`
+impl AsyncSession<AsyncHttpConnector> {
+    /// Open a new session to the HSM, authenticating with the given `AuthKey`
+    pub fn create<R: Into<ReconnectPolicy>>(
+        connector_config: HttpConfig,
+        auth_key_id: ObjectId,
+        auth_key: AuthKey,
+        reconnect: R,
+    ) -> CreateSessionFuture<AsyncHttpConnector> {
+        let connector_info = connector_config.to_string();
+        let reconnect_policy = reconnect.into();
+
+        Box::new(
+            AsyncHttpConnector::open(connector_config)
+                .and_then(|connector| connector.status().map(|status| (connector, status)))
+                .from_err()
+                .and_then(move |(connector, status)| -> CreateSessionFuture<_> {
+                    if status.message != CONNECTOR_STATUS_OK {
+                        return Box::new(future::err(session_err!(
+                            CreateFailed,
+                            "bad status response from {}: {}",
+                            connector_info,
+                            status.message
+                        )));
+                    }
+
+                    Self::new(connector, auth_key_id, auth_key, reconnect_policy)
+                }),
+        )
+    }
+
+    /// Open a new session to the HSM, authenticating with a given password.
+    /// See `Session::create_from_password` for notes on key derivation.
+    #[cfg(feature = "passwords")]
+    pub fn create_from_password<R: Into<ReconnectPolicy>>(
+        connector_config: HttpConfig,
+        auth_key_id: ObjectId,
+        password: &[u8],
+        reconnect: R,
+    ) -> CreateSessionFuture<AsyncHttpConnector> {
+        Self::create(
+            connector_config,
+            auth_key_id,
+            AuthKey::derive_from_password(password),
+            reconnect,
+        )
+    }
+}
+
+impl<C: AsyncConnector> AsyncSession<C> {
+    /// Create a new encrypted session using the given connector, YubiHSM2 auth key ID, and
+    /// authentication key. Lost sessions are re-established according to
+    /// the given `ReconnectPolicy` (or `bool`).
+    pub fn new<R: Into<ReconnectPolicy>>(
+        connector: C,
+        auth_key_id: ObjectId,
+        auth_key: AuthKey,
+        reconnect: R,
+    ) -> CreateSessionFuture<C> {
+        debug!("yubihsm: creating new async session");
+
+        let credentials = Credentials {
+            auth_key_id,
+            auth_key,
+        };
+
+        let reconnect_policy = reconnect.into();
+        let channel = Self::create_channel(&connector, &credentials);
+
+        Box::new(channel.and_then(move |channel| {
+            Self {
+                channel,
+                connector,
+                last_command_timestamp: Instant::now(),
+                active: true,
+                credentials: if reconnect_policy.retains_credentials() {
+                    Some(credentials)
+                } else {
+                    None
+                },
+                reconnect_policy,
+                rekey_threshold: DEFAULT_REKEY_THRESHOLD,
+                observer: None,
+            }.authenticate(auth_key_id)
+                .map_err(|(_, e)| e)
+        }))
+    }
+
+    /// Get the current session ID
+    #[inline]
+    pub fn id(&self) -> SessionId {
+        self.channel.id()
+    }
+
+    /// Request current yubihsm-connector status
+    pub fn connector_status(&self) -> Box<Future<Item = ConnectorStatus, Error = SessionError> + Send> {
+        Box::new(self.connector.status().from_err())
+    }
+
+    /// Is the current session active?
+    pub fn is_active(&self) -> bool {
+        self.active && !timed_out(self.id(), self.last_command_timestamp)
+    }
+
+    /// Set an observer to be notified about the commands sent over this
+    /// session and attempts to reconnect it
+    pub fn set_observer(&mut self, observer: Arc<SessionObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Number of commands sent over the current channel, i.e. with the
+    /// current session keys (including the authentication command)
+    pub fn command_count(&self) -> u32 {
+        self.channel.counter()
+    }
+
+    /// Number of commands after which the session is automatically rekeyed
+    pub fn rekey_threshold(&self) -> u32 {
+        self.rekey_threshold
+    }
+
+    /// Set the number of commands after which the session is automatically
+    /// rekeyed, which defaults to `DEFAULT_REKEY_THRESHOLD`. Commands sent
+    /// once the threshold is reached fail unless the session's
+    /// `ReconnectPolicy` retains its credentials.
+    ///
+    /// Panics unless the threshold is at least 2 and less than
+    /// `MAX_COMMANDS_PER_SESSION`.
+    pub fn set_rekey_threshold(&mut self, threshold: u32) {
+        check_rekey_threshold(threshold);
+        self.rekey_threshold = threshold;
+    }
+
+    /// Close this session, releasing its `YubiHSM2` resources.
+    ///
+    /// Sessions which are no longer active (e.g. because they timed out) are
+    /// not sent anything, as the `YubiHSM2` has already released them (or
+    /// will do so once they reach `SESSION_INACTIVITY_TIMEOUT`).
+    pub fn close(self) -> Box<Future<Item = (), Error = SessionError> + Send> {
+        if !self.is_active() {
+            session_debug!(self, "session is inactive, abandoning it");
+            return Box::new(future::ok(()));
+        }
+
+        session_debug!(self, "closing session");
+
+        Box::new(self.close_channel().map(|_| ()).map_err(|(_, e)| e))
+    }
+
+    /// Close the current encrypted channel and open a new one with fresh
+    /// session keys, re-authenticating with the same credentials.
+    ///
+    /// Returns an error unless the session's `ReconnectPolicy` retains its
+    /// credentials.
+    pub fn rekey(self) -> SessionFuture<C, ()> {
+        Box::new(self.rekey_channel().map(|session| (session, ())))
+    }
+
+    /// Encrypt a command and send it to the card, then authenticate and
+    /// decrypt the response.
+    ///
+    /// If the session is lost while sending an idempotent command and the
+    /// `ReconnectPolicy` allows it, the command is retried once after the
+    /// session has been re-established.
+    pub fn send_command<T>(self, command: T) -> SessionFuture<C, T::ResponseType>
+    where
+        T: Command + Send + 'static,
+        T::ResponseType: Send + 'static,
+    {
+        Box::new(
+            self.send_encrypted_message(command.into())
+                .and_then(|(session, response)| match decode_response::<T>(response) {
+                    Ok(response) => Ok((session, response)),
+                    Err(e) => Err((session, e)),
+                }),
+        )
+    }
+
+    /// Send a command with the given type and raw (i.e. serialized) payload
+    /// over the encrypted session, returning the raw decrypted response data
+    pub fn send_raw_command(
+        self,
+        command_type: CommandType,
+        data: &[u8],
+    ) -> SessionFuture<C, Vec<u8>> {
+        let command = match CommandMessage::new(command_type, data) {
+            Ok(cmd) => cmd,
+            Err(e) => return Box::new(future::err((self, e.into()))),
+        };
+
+        Box::new(
+            self.send_encrypted_message(command)
+                .and_then(move |(session, response)| {
+                    match check_decrypted_response(command_type, &response) {
+                        Ok(()) => Ok((session, response.data)),
+                        Err(e) => Err((session, e)),
+                    }
+                }),
+        )
+    }
+
+    /// Encrypt a command message and send it to the card, retrying it once
+    /// if the session was lost, it's idempotent, and the policy allows it
+    fn send_encrypted_message(self, command: CommandMessage) -> SessionFuture<C, ResponseMessage> {
+        let command_type = command.command_type;
+
+        if !self.reconnect_policy.retries(command_type) {
+            return self.try_send_encrypted_message(command);
+        }
+
+        let retry_message = match CommandMessage::new(command_type, command.data.clone()) {
+            Ok(message) => message,
+            Err(e) => return Box::new(future::err((self, e.into()))),
+        };
+
+        Box::new(self.try_send_encrypted_message(command).or_else(
+            move |(session, e)| -> SessionFuture<C, ResponseMessage> {
+                if session.active || !e.kind().is_retryable() {
+                    return Box::new(future::err((session, e)));
+                }
+
+                session_debug!(session, "retrying {:?} after error: {}", command_type, e);
+                session.try_send_encrypted_message(retry_message)
+            },
+        ))
+    }
+
+    /// Make a single attempt to encrypt a command message and send it to the
+    /// card, reconnecting or rekeying the session beforehand if needed
+    fn try_send_encrypted_message(
+        self,
+        command: CommandMessage,
+    ) -> SessionFuture<C, ResponseMessage> {
+        Box::new(
+            self.reconnect_if_inactive()
+                .and_then(|session| session.rekey_if_needed())
+                .and_then(move |session| session.encrypt_and_send(command)),
+        )
+    }
+
+    /// Attempt to re-establish an encrypted connection with the YubiHSM2 if
+    /// the current session has timed out or failed
+    fn reconnect_if_inactive(mut self) -> StepFuture<C> {
+        if self.is_active() {
+            return Box::new(future::ok(self));
+        }
+
+        self.active = false;
+        self.reconnect()
+    }
+
+    /// Attempt to re-establish an encrypted connection with the YubiHSM2,
+    /// making up to `ReconnectPolicy::max_attempts` attempts
+    fn reconnect(self) -> StepFuture<C> {
+        let credentials = match self.credentials {
+            Some(ref credentials) if self.reconnect_policy.is_enabled() => credentials.clone(),
+            _ => {
+                return Box::new(future::err((
+                    self,
+                    session_err!(CreateFailed, "session reconnect is disabled"),
+                )))
+            }
+        };
+
+        Box::new(future::loop_fn(
+            (self, 1),
+            move |(session, attempt)| -> ReconnectFuture<C> {
+                session_debug!(session, "attempting to reconnect (attempt {})", attempt);
+
+                let started_at = Instant::now();
+
+                Box::new(session.establish_channel(&credentials).then(
+                    move |result| -> ReconnectFuture<C> {
+                        let (session, err) = match result {
+                            Ok(session) => {
+                                notify_reconnect_attempted(
+                                    session.observer.as_ref(),
+                                    attempt,
+                                    started_at,
+                                    None,
+                                );
+
+                                return Box::new(future::ok(Loop::Break(session)));
+                            }
+                            Err(failure) => failure,
+                        };
+
+                        notify_reconnect_attempted(
+                            session.observer.as_ref(),
+                            attempt,
+                            started_at,
+                            Some(err.kind()),
+                        );
+
+                        let backoff = match session.reconnect_policy.next_attempt(attempt, &err) {
+                            Some(backoff) => backoff,
+                            None => return Box::new(future::err((session, err))),
+                        };
+
+                        session_debug!(
+                            session,
+                            "reconnect failed ({}), retrying in {}ms",
+                            err,
+                            backoff.as_secs() * 1000 + u64::from(backoff.subsec_millis())
+                        );
+
+                        Box::new(Delay::new(Instant::now() + backoff).then(
+                            move |result| match result {
+                                Ok(()) => Ok(Loop::Continue((session, attempt + 1))),
+                                Err(e) => {
+                                    let err = session_err!(
+                                        CreateFailed,
+                                        "couldn't wait to reconnect ({}): {}",
+                                        err,
+                                        e
+                                    );
+
+                                    Err((session, err))
+                                }
+                            },
+                        ))
+                    },
+                ))
+            },
+        ))
+    }
+
+    /// Rekey the session before the channel reaches `MAX_COMMANDS_PER_SESSION`,
+    /// after which the YubiHSM2 won't accept any more commands over it
+    fn rekey_if_needed(self) -> StepFuture<C> {
+        if self.channel.counter() < self.rekey_threshold {
+            return Box::new(future::ok(self));
+        }
+
+        session_debug!(
+            self,
+            "reached rekey threshold ({} commands)",
+            self.rekey_threshold
+        );
+
+        self.rekey_channel()
+    }
+
+    /// Close the current encrypted channel (if it's active) and open a new one
+    fn rekey_channel(self) -> StepFuture<C> {
+        session_debug!(self, "rekeying session");
+
+        let credentials = match self.credentials {
+            Some(ref credentials) => credentials.clone(),
+            None => {
+                return Box::new(future::err((
+                    self,
+                    session_err!(CreateFailed, "credentials weren't retained for rekeying"),
+                )))
+            }
+        };
+
+        let closed: StepFuture<C> = if self.is_active() {
+            Box::new(self.close_channel().or_else(|(session, e)| {
+                session_debug!(session, "error closing session before rekey: {}", e);
+                Ok(session)
+            }))
+        } else {
+            Box::new(future::ok(self))
+        };
+
+        Box::new(closed.and_then(move |mut session| {
+            session.active = false;
+            session.establish_channel(&credentials)
+        }))
+    }
+
+    /// Create a new encrypted channel with the YubiHSM2
+    fn create_channel(
+        connector: &C,
+        credentials: &Credentials,
+    ) -> Box<Future<Item = Channel, Error = SessionError> + Send> {
+        let host_challenge = Challenge::random();
+
+        let command_message: CommandMessage = CreateSessionCommand {
+            auth_key_id: credentials.auth_key_id,
+            host_challenge,
+        }.into();
+
+        let uuid = command_message.uuid;
+        let response = connector.send_command(uuid, command_message.into());
+        let credentials = credentials.clone();
+
+        Box::new(response.from_err().and_then(move |response_body| {
+            let (session_id, session_response) = create_session::parse_response(response_body)?;
+            open_channel(&credentials, host_challenge, session_id, &session_response)
+        }))
+    }
+
+    /// Open and authenticate a new encrypted channel over the current connection
+    fn establish_channel(self, credentials: &Credentials) -> StepFuture<C> {
+        let auth_key_id = credentials.auth_key_id;
+        let channel = Self::create_channel(&self.connector, credentials);
+
+        Box::new(channel.then(move |result| -> StepFuture<C> {
+            let mut session = self;
+
+            match result {
+                Ok(channel) => {
+                    session.channel = channel;
+                    session.active = true;
+                    session.last_command_timestamp = Instant::now();
+                    session.authenticate(auth_key_id)
+                }
+                Err(e) => Box::new(future::err((session, e))),
+            }
+        }))
+    }
+
+    /// Authenticate the current session with the `YubiHSM2`
+    fn authenticate(mut self, auth_key_id: ObjectId) -> StepFuture<C> {
+        session_debug!(self, "authenticating session with key ID: {}", auth_key_id);
+
+        let command = match self.channel.authenticate_session() {
+            Ok(command) => command,
+            Err(e) => return Box::new(future::err((self, e.into()))),
+        };
+
+        Box::new(
+            self.send_message(command)
+                .and_then(move |(mut session, response)| {
+                    if let Err(e) = session.channel.finish_authenticate_session(&response) {
+                        session_debug!(session, "error authenticating with key ID: {}", auth_key_id);
+                        session.active = false;
+                        return Err((session, e.into()));
+                    }
+
+                    session_debug!(session, "session authenticated successfully");
+                    Ok(session)
+                }),
+        )
+    }
+
+    /// Close the current channel with the YubiHSM2
+    fn close_channel(self) -> StepFuture<C> {
+        Box::new(
+            self.encrypt_and_send(CloseSessionCommand {}.into())
+                .and_then(|(session, response)| {
+                    match decode_response::<CloseSessionCommand>(response) {
+                        Ok(_) => Ok(session),
+                        Err(e) => Err((session, e)),
+                    }
+                }),
+        )
+    }
+
+    /// Encrypt a command message and send it over the current channel as-is,
+    /// without reconnecting or rekeying, then authenticate and decrypt the
+    /// response
+    fn encrypt_and_send(mut self, command: CommandMessage) -> SessionFuture<C, ResponseMessage> {
+        let command_type = command.command_type;
+
+        let encrypted_cmd = match self.channel.encrypt_command(command) {
+            Ok(cmd) => cmd,
+            Err(e) => return Box::new(future::err((self, e.into()))),
+        };
+
+        let uuid = encrypted_cmd.uuid;
+        session_debug!(self, "uuid={} encrypted-cmd={:?}", uuid, command_type);
+
+        let command_info =
+            notify_command_started(self.observer.as_ref(), self.id(), command_type, uuid);
+
+        Box::new(
+            self.send_message(encrypted_cmd)
+                .and_then(move |(mut session, encrypted_response)| {
+                    match session.channel.decrypt_response(encrypted_response) {
+                        Ok(response) => {
+                            session_debug!(
+                                session,
+                                "uuid={} decrypted-resp={:?} length={}",
+                                uuid,
+                                response.code,
+                                response.data.len()
+                            );
+
+                            Ok((session, response))
+                        }
+                        Err(e) => Err((session, e.into())),
+                    }
+                })
+                .then(move |result| {
+                    {
+                        let (session, result) = match result {
+                            Ok((ref session, ref response)) => (session, Ok(response)),
+                            Err((ref session, ref e)) => (session, Err(e)),
+                        };
+
+                        notify_command_finished(session.observer.as_ref(), command_info, result);
+                    }
+
+                    result
+                }),
+        )
+    }
+
+    /// Send a command message to the YubiHSM2 and parse the response
+    fn send_message(self, cmd: CommandMessage) -> SessionFuture<C, ResponseMessage> {
+        let cmd_type = cmd.command_type;
+        let uuid = cmd.uuid;
+
+        session_debug!(self, "uuid={} command={:?}", &uuid, cmd_type);
+
+        let response = self.connector.send_command(uuid, cmd.into());
+
+        Box::new(response.then(move |result| {
+            let mut session = self;
+
+            let response_bytes = match result {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    // Mark connection as unhealthy
+                    session.active = false;
+                    return Err((session, e.into()));
+                }
+            };
+
+            let response = match ResponseMessage::parse(response_bytes) {
+                Ok(response) => response,
+                Err(e) => return Err((session, e.into())),
+            };
+
+            session_debug!(
+                session,
+                "uuid={} response={:?} length={}",
+                &uuid,
+                response.code,
+                response.data.len()
+            );
+
+            session.last_command_timestamp = Instant::now();
+
+            if let Err(e) = check_response(cmd_type, &response) {
+                // Protocol errors leave the channel in an unknown state
+                if e.kind() == SessionErrorKind::ProtocolError {
+                    session.active = false;
+                }
+
+                return Err((session, e));
+            }
+
+            Ok((session, response))
+        }))
+    }
+}
@@ -1,9 +1,9 @@
 //! Errors that occur during sessions
 
-use connector::ConnectorError;
+use connector::{ConnectorError, ConnectorErrorKind};
 use error::Error;
-use securechannel::SecureChannelError;
-use serializers::SerializationError;
+use securechannel::{ResponseCode, SecureChannelError, SecureChannelErrorKind};
+use serializers::{SerializationError, SerializationErrorKind};
 
 /// Session errors
 pub type SessionError = Error<SessionErrorKind>;
@@ -23,15 +23,58 @@ pub enum SessionErrorKind {
     #[fail(display = "protocol error")]
     ProtocolError,
 
-    /// HSM returned an error response
+    /// HSM returned an unexpected response
     #[fail(display = "bad HSM response")]
     ResponseError,
 
+    /// HSM returned an error code
+    #[fail(display = "HSM error: {:?}", _0)]
+    DeviceError(ResponseCode),
+
+    /// Error communicating with yubihsm-connector
+    #[fail(display = "connector error")]
+    ConnectorError(ConnectorErrorKind),
+
+    /// Error in the encrypted channel with the HSM
+    #[fail(display = "secure channel error")]
+    SecureChannelError(SecureChannelErrorKind),
+
+    /// Error serializing a command or deserializing a response
+    #[fail(display = "serialization error")]
+    SerializationError(SerializationErrorKind),
+
     /// Session with the YubiHSM2 timed out
     #[fail(display = "session timeout")]
     TimeoutError,
 }
 
+impl SessionErrorKind {
+    /// Could this error be fixed by re-establishing the session and trying
+    /// again? Authentication failures and errors reported by the HSM itself
+    /// are fatal.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            SessionErrorKind::ConnectorError(kind) => kind.is_retryable(),
+            SessionErrorKind::ProtocolError
+            | SessionErrorKind::SecureChannelError(_)
+            | SessionErrorKind::SerializationError(_)
+            | SessionErrorKind::TimeoutError => true,
+            SessionErrorKind::AuthFailed
+            | SessionErrorKind::CreateFailed
+            | SessionErrorKind::ResponseError
+            | SessionErrorKind::DeviceError(_) => false,
+        }
+    }
+
+    /// Error code returned by the HSM, if this is a `DeviceError`
+    pub fn response_code(self) -> Option<ResponseCode> {
+        match self {
+            SessionErrorKind::DeviceError(code) => Some(code),
+            _ => None,
+        }
+    }
+}
+
 /// Create a new Session error with a formatted message
 macro_rules! session_err {
     ($kind:ident, $msg:expr) => {
@@ -57,18 +100,40 @@ macro_rules! session_fail {
 
 impl From<ConnectorError> for SessionError {
     fn from(err: ConnectorError) -> Self {
-        session_err!(ProtocolError, err.to_string())
+        SessionError::new(
+            SessionErrorKind::ConnectorError(err.kind()),
+            Some(err.to_string()),
+        )
     }
 }
 
 impl From<SecureChannelError> for SessionError {
     fn from(err: SecureChannelError) -> Self {
-        session_err!(ProtocolError, err.to_string())
+        SessionError::new(
+            SessionErrorKind::SecureChannelError(err.kind()),
+            Some(err.to_string()),
+        )
     }
 }
 
 impl From<SerializationError> for SessionError {
     fn from(err: SerializationError) -> Self {
-        session_err!(ProtocolError, err.to_string())
+        SessionError::new(
+            SessionErrorKind::SerializationError(err.kind()),
+            Some(err.to_string()),
+        )
+    }
+}
+
+impl From<ResponseCode> for SessionError {
+    fn from(code: ResponseCode) -> Self {
+        let description = match code {
+            ResponseCode::MemoryError => {
+                Some("general HSM error (e.g. bad command params, missing object)".to_owned())
+            }
+            _ => None,
+        };
+
+        SessionError::new(SessionErrorKind::DeviceError(code), description)
     }
 }
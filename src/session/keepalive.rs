@@ -0,0 +1,161 @@
+//! Background keepalive for sessions
+//!
+//! Sessions with the `YubiHSM2` time out after `SESSION_INACTIVITY_TIMEOUT`,
+//! after which the next command pays for creating and authenticating a new
+//! session. A `Keepalive` runs a background thread which periodically sends
+//! an `echo` over sessions which have been idle for a while, and can
+//! optionally rotate their session keys once they reach a certain age.
+
+use std::sync::{Arc, Condvar, Mutex, TryLockError, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{Session, SessionPool, SESSION_INACTIVITY_TIMEOUT};
+use connector::Connector;
+
+/// Default interval at which idle sessions are sent keepalives (10 seconds)
+pub const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Keepalive options
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeepaliveConfig {
+    /// Sessions which have been idle for at least this long are sent an
+    /// `echo`. Sessions are checked at this interval too, so a session may
+    /// go up to twice this long without a command, which must be less than
+    /// `SESSION_INACTIVITY_TIMEOUT`.
+    pub interval: Duration,
+
+    /// Rekey sessions whose session keys are older than this (if set). Only
+    /// sessions whose `ReconnectPolicy` retains their credentials can be
+    /// rekeyed: others fail to keep alive once they reach this age.
+    pub rekey_interval: Option<Duration>,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_KEEPALIVE_INTERVAL,
+            rekey_interval: None,
+        }
+    }
+}
+
+/// Types containing sessions which can be kept alive by a `Keepalive`
+pub trait KeepaliveTarget: Send + Sync + 'static {
+    /// Keep the contained sessions from timing out. Sessions which are
+    /// presently in use should be skipped.
+    fn keepalive(&self, config: &KeepaliveConfig);
+}
+
+impl<C: Connector + 'static> KeepaliveTarget for Mutex<Session<C>> {
+    fn keepalive(&self, config: &KeepaliveConfig) {
+        let mut session = match self.try_lock() {
+            Ok(session) => session,
+            // The session is in use, so it isn't idle
+            Err(TryLockError::WouldBlock) => return,
+            Err(TryLockError::Poisoned(_)) => return,
+        };
+
+        if let Err(e) = session.keepalive(config) {
+            session_debug!(session, "keepalive failed: {}", e);
+        }
+    }
+}
+
+impl<C: Connector + 'static> KeepaliveTarget for SessionPool<C> {
+    fn keepalive(&self, config: &KeepaliveConfig) {
+        SessionPool::keepalive(self, config)
+    }
+}
+
+/// Background thread which keeps sessions alive. The thread is stopped when
+/// the `Keepalive` is dropped, or when its target is dropped.
+pub struct Keepalive {
+    /// Set to `true` (and signaled) to stop the thread
+    stop: Arc<(Mutex<bool>, Condvar)>,
+
+    /// Keepalive thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Keepalive {
+    /// Spawn a thread which keeps the given target's sessions alive, e.g. an
+    /// `Arc<Mutex<Session>>` or `Arc<SessionPool>`.
+    ///
+    /// Panics if `config.interval` is too long to prevent sessions from
+    /// timing out.
+    pub fn spawn<T: KeepaliveTarget>(target: &Arc<T>, config: KeepaliveConfig) -> Self {
+        assert!(
+            config.interval * 2 < SESSION_INACTIVITY_TIMEOUT,
+            "keepalive interval must be less than half of SESSION_INACTIVITY_TIMEOUT"
+        );
+
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = Arc::clone(&stop);
+        let target = Arc::downgrade(target);
+
+        let thread = thread::spawn(move || run(&target, &config, &thread_stop));
+
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stop the keepalive thread, waiting for it to exit
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Keepalive {
+    fn drop(&mut self) {
+        let (ref stopped, ref signal) = *self.stop;
+        *stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        signal.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Keep the target's sessions alive until it's dropped or we're told to stop
+fn run<T: KeepaliveTarget>(
+    target: &Weak<T>,
+    config: &KeepaliveConfig,
+    stop: &(Mutex<bool>, Condvar),
+) {
+    let (ref stopped, ref signal) = *stop;
+    let mut next_check = Instant::now() + config.interval;
+
+    loop {
+        {
+            let mut stopped = stopped.lock().unwrap_or_else(|e| e.into_inner());
+
+            loop {
+                if *stopped {
+                    return;
+                }
+
+                let now = Instant::now();
+
+                if now >= next_check {
+                    break;
+                }
+
+                stopped = signal
+                    .wait_timeout(stopped, next_check - now)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+        }
+
+        match target.upgrade() {
+            Some(target) => target.keepalive(config),
+            None => return,
+        }
+
+        next_check = Instant::now() + config.interval;
+    }
+}
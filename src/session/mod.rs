@@ -1,3 +1,7 @@
+use std::sync::Arc;
+#[cfg(any(feature = "rustls-signer", feature = "signatory"))]
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
 use std::time::{Duration, Instant};
 use subtle::ConstantTimeEq;
 
@@ -5,12 +9,21 @@ use subtle::ConstantTimeEq;
 mod error;
 
 pub use self::error::{SessionError, SessionErrorKind};
+pub use securechannel::{ResponseCode, SecureChannelErrorKind, MAX_COMMANDS_PER_SESSION};
+pub use serializers::SerializationErrorKind;
 use auth_key::AuthKey;
-use commands::{close_session::CloseSessionCommand, create_session::create_session, Command};
+use commands::{
+    close_session::CloseSessionCommand,
+    create_session::{create_session, CreateSessionResponse},
+    echo::EchoCommand,
+    Command, CommandType,
+};
 use connector::{Connector, HttpConfig, HttpConnector, Status as ConnectorStatus};
+#[cfg(feature = "keylog")]
+use keylog::KeyLogFile;
 use object::ObjectId;
 use securechannel::SessionId;
-use securechannel::{Challenge, Channel, CommandMessage, ResponseCode, ResponseMessage};
+use securechannel::{Challenge, Channel, CommandMessage, ResponseMessage};
 use serializers::deserialize;
 
 /// Sessions with the YubiHSM2 are stateful and expire after 30 seconds. See:
@@ -22,9 +35,16 @@ pub const SESSION_INACTIVITY_TIMEOUT: Duration = Duration::from_secs(30);
 /// timeout. This should (hopefully) ensure we always time out first.
 const TIMEOUT_SKEW_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Default number of commands after which a session is rekeyed. Leaves room
+/// for the `CloseSession` command sent over the old channel.
+pub const DEFAULT_REKEY_THRESHOLD: u32 = MAX_COMMANDS_PER_SESSION - 1;
+
 /// Status message returned from healthy connectors
 const CONNECTOR_STATUS_OK: &str = "OK";
 
+/// Message sent in keepalive echo commands
+const KEEPALIVE_MESSAGE: &[u8] = b"\0";
+
 /// Write consistent `debug!(...) lines for sessions
 macro_rules! session_debug {
     ($session:expr, $msg:expr) => {
@@ -35,6 +55,25 @@ macro_rules! session_debug {
     };
 }
 
+#[cfg(feature = "async")]
+mod async_session;
+mod keepalive;
+mod observer;
+mod pool;
+mod reconnect;
+
+#[cfg(feature = "async")]
+pub use self::async_session::{AsyncSession, CreateSessionFuture, SessionFuture};
+pub use self::keepalive::{
+    Keepalive, KeepaliveConfig, KeepaliveTarget, DEFAULT_KEEPALIVE_INTERVAL,
+};
+pub use self::observer::{CommandInfo, CommandOutcome, ReconnectAttempt, SessionObserver};
+use self::observer::{notify_command_finished, notify_command_started, notify_reconnect_attempted};
+pub use self::pool::{PooledSession, SessionPool, DEFAULT_CHECKOUT_TIMEOUT, MAX_SESSIONS};
+pub use self::reconnect::{
+    ReconnectPolicy, DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_BACKOFF,
+};
+
 /// Encrypted session with the `YubiHSM2`.
 /// A session is needed to perform any commands.
 ///
@@ -42,7 +81,8 @@ macro_rules! session_debug {
 /// be swapped in, which is primarily useful for substituting the `MockHSM`.
 ///
 /// Sessions are automatically closed on `Drop`, releasing `YubiHSM2` session
-/// resources and wiping the ephemeral keys used to encrypt the session.
+/// resources and wiping the ephemeral keys used to encrypt the session. Use
+/// `Session::close` to close them explicitly and find out if it succeeded.
 pub struct Session<C = HttpConnector>
 where
     C: Connector,
@@ -57,15 +97,33 @@ where
     /// tracking session inactivity timeouts
     last_command_timestamp: Instant,
 
+    /// Instant when the current channel (i.e. set of session keys) was opened
+    channel_timestamp: Instant,
+
     /// Is the connection presumed to be healthy?
     active: bool,
 
-    /// Cached `Credentials` for reconnecting lost sessions
+    /// Cached `Credentials` for reconnecting lost sessions and rekeying (if
+    /// the `ReconnectPolicy` retains them)
     credentials: Option<Credentials>,
+
+    /// Policy for reconnecting lost sessions and retrying failed commands
+    reconnect_policy: ReconnectPolicy,
+
+    /// Channel counter value at which the session is automatically rekeyed
+    rekey_threshold: u32,
+
+    /// Observer notified about commands and reconnects (if any)
+    observer: Option<Arc<SessionObserver>>,
+
+    /// Key log the keys for each channel are written to (if any)
+    #[cfg(feature = "keylog")]
+    key_log: Option<Arc<KeyLogFile>>,
 }
 
 /// Credentials used to establish a YubiHSM2 session
-struct Credentials {
+#[derive(Clone)]
+pub(crate) struct Credentials {
     /// Key ID to authenticate with
     auth_key_id: ObjectId,
 
@@ -81,11 +139,11 @@ struct Credentials {
 // So we special case these for HttpConnector to make the API more ergonomic
 impl Session<HttpConnector> {
     /// Open a new session to the HSM, authenticating with the given `AuthKey`
-    pub fn create(
+    pub fn create<R: Into<ReconnectPolicy>>(
         connector_config: HttpConfig,
         auth_key_id: ObjectId,
         auth_key: AuthKey,
-        reconnect: bool,
+        reconnect: R,
     ) -> Result<Self, SessionError> {
         let connector_info = connector_config.to_string();
         let connector = HttpConnector::open(connector_config)?;
@@ -108,11 +166,11 @@ impl Session<HttpConnector> {
     /// (PBKDF2 + static salt), which is not particularly strong, so use
     /// of a long, random password is recommended.
     #[cfg(feature = "passwords")]
-    pub fn create_from_password(
+    pub fn create_from_password<R: Into<ReconnectPolicy>>(
         connector_config: HttpConfig,
         auth_key_id: ObjectId,
         password: &[u8],
-        reconnect: bool,
+        reconnect: R,
     ) -> Result<Self, SessionError> {
         Self::create(
             connector_config,
@@ -125,12 +183,17 @@ impl Session<HttpConnector> {
 
 impl<C: Connector> Session<C> {
     /// Create a new encrypted session using the given connector, YubiHSM2 auth key ID, and
-    /// authentication key
-    pub fn new(
+    /// authentication key. Lost sessions are re-established according to
+    /// the given `ReconnectPolicy` (or `bool`).
+    ///
+    /// The auth key is only kept in memory after authenticating if the
+    /// policy enables reconnecting or `ReconnectPolicy::retain_credentials`,
+    /// which rekeying the session also requires.
+    pub fn new<R: Into<ReconnectPolicy>>(
         connector: C,
         auth_key_id: ObjectId,
         auth_key: AuthKey,
-        reconnect: bool,
+        reconnect: R,
     ) -> Result<Self, SessionError> {
         debug!("yubihsm: creating new session");
 
@@ -140,13 +203,24 @@ impl<C: Connector> Session<C> {
         };
 
         let channel = Self::create_channel(&connector, &credentials)?;
+        let reconnect_policy = reconnect.into();
 
         let mut session = Self {
             channel,
             connector,
             last_command_timestamp: Instant::now(),
+            channel_timestamp: Instant::now(),
             active: true,
-            credentials: if reconnect { Some(credentials) } else { None },
+            credentials: if reconnect_policy.retains_credentials() {
+                Some(credentials)
+            } else {
+                None
+            },
+            reconnect_policy,
+            rekey_threshold: DEFAULT_REKEY_THRESHOLD,
+            observer: None,
+            #[cfg(feature = "keylog")]
+            key_log: None,
         };
 
         session.authenticate(auth_key_id)?;
@@ -159,6 +233,11 @@ impl<C: Connector> Session<C> {
         self.channel.id()
     }
 
+    /// Borrow the connector this session sends messages through
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
     /// Request current yubihsm-connector status
     pub fn connector_status(&mut self) -> Result<ConnectorStatus, SessionError> {
         self.connector.status().map_err(|e| e.into())
@@ -166,71 +245,232 @@ impl<C: Connector> Session<C> {
 
     /// Is the current session active?
     pub fn is_active(&self) -> bool {
-        if !self.active {
-            return false;
+        self.active && !timed_out(self.id(), self.last_command_timestamp)
+    }
+
+    /// Amount of time since the last command was sent to the `YubiHSM2`
+    pub fn idle_time(&self) -> Duration {
+        Instant::now().duration_since(self.last_command_timestamp)
+    }
+
+    /// Amount of time since the current session keys were established
+    pub fn channel_age(&self) -> Duration {
+        Instant::now().duration_since(self.channel_timestamp)
+    }
+
+    /// Set an observer to be notified about the commands sent over this
+    /// session and attempts to reconnect it
+    pub fn set_observer(&mut self, observer: Arc<SessionObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// **DANGER:** log the keys for this session, and each channel it opens
+    /// afterwards (e.g. when reconnecting or rekeying), to the given key log.
+    ///
+    /// Anyone with access to the key log can decrypt and forge messages in
+    /// these sessions! Only use this for debugging. See the `keylog` module.
+    #[cfg(feature = "keylog")]
+    pub fn set_key_log(&mut self, key_log: Arc<KeyLogFile>) {
+        self.key_log = Some(key_log);
+        self.log_session_keys();
+    }
+
+    /// Close this session, releasing its `YubiHSM2` resources and wiping the
+    /// ephemeral keys used to encrypt it.
+    ///
+    /// Returns `Ok(())` if the session was closed cleanly. If an error is
+    /// returned, the session was abandoned instead, and the `YubiHSM2` will
+    /// release it once it reaches `SESSION_INACTIVITY_TIMEOUT`.
+    pub fn close(mut self) -> Result<(), SessionError> {
+        session_debug!(self, "closing session");
+
+        let result = if !self.active {
+            Err(session_err!(
+                ProtocolError,
+                "session is unhealthy and can't be closed cleanly"
+            ))
+        } else if timed_out(self.id(), self.last_command_timestamp) {
+            Err(session_err!(TimeoutError, "session has already timed out"))
+        } else {
+            self.close_channel()
+        };
+
+        if let Err(ref e) = result {
+            session_debug!(self, "abandoning session: {}", e);
         }
 
-        let time_since_last_command = Instant::now().duration_since(self.last_command_timestamp);
+        // Ensure `Drop` doesn't try to close the session again
+        self.active = false;
+        self.channel.terminate();
 
-        // Make sure the session hasn't timed out
-        if time_since_last_command > (SESSION_INACTIVITY_TIMEOUT - TIMEOUT_SKEW_INTERVAL) {
-            session_debug!(
-                self,
-                "session timed out after {} seconds (max {})",
-                time_since_last_command.as_secs(),
-                SESSION_INACTIVITY_TIMEOUT.as_secs()
-            );
+        result
+    }
+
+    /// Abandon this session without closing it with the `YubiHSM2`, wiping
+    /// the ephemeral keys used to encrypt it. The `YubiHSM2` will release it
+    /// once it reaches `SESSION_INACTIVITY_TIMEOUT`.
+    pub fn abandon(mut self) {
+        session_debug!(self, "abandoning session");
+
+        self.active = false;
+        self.channel.terminate();
+    }
+
+    /// Number of commands sent over the current channel, i.e. with the
+    /// current session keys (including the authentication command)
+    pub fn command_count(&self) -> u32 {
+        self.channel.counter()
+    }
+
+    /// Number of commands after which the session is automatically rekeyed
+    pub fn rekey_threshold(&self) -> u32 {
+        self.rekey_threshold
+    }
+
+    /// Set the number of commands after which the session is automatically
+    /// rekeyed, which defaults to `DEFAULT_REKEY_THRESHOLD`. Commands sent
+    /// once the threshold is reached fail unless the session's
+    /// `ReconnectPolicy` retains its credentials.
+    ///
+    /// Panics unless the threshold is at least 2 and less than
+    /// `MAX_COMMANDS_PER_SESSION`.
+    pub fn set_rekey_threshold(&mut self, threshold: u32) {
+        check_rekey_threshold(threshold);
+        self.rekey_threshold = threshold;
+    }
+
+    /// Close the current encrypted channel and open a new one with fresh
+    /// session keys, re-authenticating with the same credentials.
+    ///
+    /// Returns an error unless the session's `ReconnectPolicy` retains its
+    /// credentials.
+    pub fn rekey(&mut self) -> Result<(), SessionError> {
+        session_debug!(self, "rekeying session");
+
+        let credentials = match self.credentials {
+            Some(ref credentials) => credentials.clone(),
+            None => session_fail!(CreateFailed, "credentials weren't retained for rekeying"),
+        };
 
-            return false;
+        if self.is_active() {
+            if let Err(e) = self.close_channel() {
+                session_debug!(self, "error closing session before rekey: {}", e);
+            }
         }
 
-        true
+        self.active = false;
+        self.establish_channel(&credentials)
+    }
+
+    /// Keep this session from timing out: rekey it if its session keys are
+    /// older than `config.rekey_interval` (see `Session::rekey`), or otherwise
+    /// send an `echo` if it's been idle for at least `config.interval`.
+    ///
+    /// This is called periodically by `Keepalive`, but can also be called
+    /// directly, e.g. from an existing event loop.
+    pub fn keepalive(&mut self, config: &KeepaliveConfig) -> Result<(), SessionError> {
+        if let Some(rekey_interval) = config.rekey_interval {
+            if self.channel_age() >= rekey_interval {
+                return self.rekey();
+            }
+        }
+
+        if self.idle_time() >= config.interval {
+            session_debug!(self, "sending keepalive");
+
+            self.send_encrypted_command(EchoCommand {
+                message: KEEPALIVE_MESSAGE.into(),
+            })?;
+        }
+
+        Ok(())
     }
 
     /// Create a new encrypted session with the YubiHSM2
     fn create_channel(connector: &C, credentials: &Credentials) -> Result<Channel, SessionError> {
-        let host_challenge = Challenge::random();
+        let host_challenge = connector
+            .host_challenge()
+            .unwrap_or_else(Challenge::random);
 
         let (session_id, session_response) =
             create_session(connector, credentials.auth_key_id, host_challenge)?;
 
-        let channel = Channel::new(
-            session_id,
-            &credentials.auth_key,
-            host_challenge,
-            session_response.card_challenge,
-        );
-
-        if channel
-            .card_cryptogram()
-            .ct_eq(&session_response.card_cryptogram)
-            .unwrap_u8() != 1
-        {
-            session_fail!(AuthFailed, "card cryptogram mismatch!");
-        }
-
-        Ok(channel)
+        open_channel(credentials, host_challenge, session_id, &session_response)
     }
 
-    /// Attempt to re-establish an encrypted connection with the YubiHSM2
+    /// Attempt to re-establish an encrypted connection with the YubiHSM2,
+    /// making up to `ReconnectPolicy::max_attempts` attempts
     fn reconnect(&mut self) -> Result<(), SessionError> {
-        let auth_key_id = match self.credentials {
-            Some(ref credentials) => {
-                // TODO: display connector debug info?
-                session_debug!(self, "attempting to reconnect");
-
-                self.connector.reconnect()?;
-                self.channel = Self::create_channel(&self.connector, credentials)?;
-                credentials.auth_key_id
-            }
-            None => session_fail!(CreateFailed, "session reconnect is disabled"),
+        let credentials = match self.credentials {
+            Some(ref credentials) if self.reconnect_policy.is_enabled() => credentials.clone(),
+            _ => session_fail!(CreateFailed, "session reconnect is disabled"),
         };
 
+        let mut attempt = 1;
+
+        loop {
+            // TODO: display connector debug info?
+            session_debug!(self, "attempting to reconnect (attempt {})", attempt);
+
+            let started_at = Instant::now();
+            let result = self.try_reconnect(&credentials);
+
+            notify_reconnect_attempted(
+                self.observer.as_ref(),
+                attempt,
+                started_at,
+                result.as_ref().err().map(|e| e.kind()),
+            );
+
+            let err = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            let backoff = match self.reconnect_policy.next_attempt(attempt, &err) {
+                Some(backoff) => backoff,
+                None => return Err(err),
+            };
+
+            session_debug!(
+                self,
+                "reconnect failed ({}), retrying in {}ms",
+                err,
+                backoff.as_secs() * 1000 + u64::from(backoff.subsec_millis())
+            );
+
+            thread::sleep(backoff);
+            attempt += 1;
+        }
+    }
+
+    /// Make a single attempt to re-establish an encrypted connection
+    fn try_reconnect(&mut self, credentials: &Credentials) -> Result<(), SessionError> {
+        // Connector errors which can't be fixed by trying again (e.g. an
+        // invalid URL) aren't retryable, ending the reconnect attempts
+        self.connector.reconnect()?;
+        self.establish_channel(credentials)
+    }
+
+    /// Open and authenticate a new encrypted channel over the current connection
+    fn establish_channel(&mut self, credentials: &Credentials) -> Result<(), SessionError> {
+        self.channel = Self::create_channel(&self.connector, credentials)?;
+        #[cfg(feature = "keylog")]
+        self.log_session_keys();
         self.active = true;
         self.last_command_timestamp = Instant::now();
-        self.authenticate(auth_key_id)?;
+        self.channel_timestamp = self.last_command_timestamp;
+        self.authenticate(credentials.auth_key_id)
+    }
 
-        Ok(())
+    /// Write the keys for the current channel to the key log (if enabled)
+    #[cfg(feature = "keylog")]
+    fn log_session_keys(&self) {
+        if let Some(ref key_log) = self.key_log {
+            if let Err(e) = key_log.write(self.id(), &self.channel.session_keys()) {
+                warn!("yubihsm: session={} error logging keys: {}", self.id().to_u8(), e);
+            }
+        }
     }
 
     /// Authenticate the current session with the `YubiHSM2`
@@ -253,12 +493,6 @@ impl<C: Connector> Session<C> {
 
     /// Send a command message to the YubiHSM2 and parse the response
     fn send_command(&mut self, cmd: CommandMessage) -> Result<ResponseMessage, SessionError> {
-        // Attempt to automatically reconnect if the session is unhealthy
-        if !self.is_active() {
-            self.active = false;
-            self.reconnect()?;
-        }
-
         let cmd_type = cmd.command_type;
         let uuid = cmd.uuid;
 
@@ -285,35 +519,139 @@ impl<C: Connector> Session<C> {
 
         self.last_command_timestamp = Instant::now();
 
-        if response.is_err() {
-            session_fail!(ResponseError, "HSM error: {:?}", response.code);
-        }
-
-        if response.command().unwrap() != cmd_type {
-            self.active = false;
+        if let Err(e) = check_response(cmd_type, &response) {
+            // Protocol errors leave the channel in an unknown state
+            if e.kind() == SessionErrorKind::ProtocolError {
+                self.active = false;
+            }
 
-            session_fail!(
-                ProtocolError,
-                "command type mismatch: expected {:?}, got {:?}",
-                cmd_type,
-                response.command().unwrap()
-            );
+            return Err(e);
         }
 
         Ok(response)
     }
 
     /// Encrypt a command and send it to the card, then authenticate and
-    /// decrypt the response
-    pub(crate) fn send_encrypted_command<T: Command>(
+    /// decrypt the response.
+    ///
+    /// This can be used to send commands defined outside of this crate by
+    /// implementing the `Command` and `Response` traits for them.
+    ///
+    /// If the session is lost while sending an idempotent command and the
+    /// `ReconnectPolicy` allows it, the command is retried once after the
+    /// session has been re-established.
+    pub fn send_encrypted_command<T: Command>(
         &mut self,
         command: T,
     ) -> Result<T::ResponseType, SessionError> {
-        let encrypted_cmd = self.channel.encrypt_command(command.into())?;
+        let response = self.send_encrypted_message(command.into())?;
+        decode_response::<T>(response)
+    }
+
+    /// Send a command with the given type and raw (i.e. serialized) payload
+    /// over the encrypted session, returning the raw decrypted response data.
+    ///
+    /// This is a low-level interface for commands this crate doesn't support
+    /// yet. Errors returned by the HSM are still surfaced as `DeviceError`s.
+    pub fn send_raw_command(
+        &mut self,
+        command_type: CommandType,
+        data: &[u8],
+    ) -> Result<Vec<u8>, SessionError> {
+        let response = self.send_encrypted_message(CommandMessage::new(command_type, data)?)?;
+        check_decrypted_response(command_type, &response)?;
+        Ok(response.data)
+    }
+
+    /// Encrypt a command message and send it to the card, retrying it once
+    /// if the session was lost, it's idempotent, and the policy allows it
+    fn send_encrypted_message(
+        &mut self,
+        command: CommandMessage,
+    ) -> Result<ResponseMessage, SessionError> {
+        let command_type = command.command_type;
+
+        if !self.reconnect_policy.retries(command_type) {
+            return self.try_send_encrypted_message(command);
+        }
+
+        let retry_message = CommandMessage::new(command_type, command.data.clone())?;
+
+        match self.try_send_encrypted_message(command) {
+            Err(ref e) if !self.active && e.kind().is_retryable() => {
+                session_debug!(self, "retrying {:?} after error: {}", command_type, e);
+            }
+            result => return result,
+        }
+
+        self.try_send_encrypted_message(retry_message)
+    }
+
+    /// Make a single attempt to encrypt a command message and send it to the
+    /// card, reconnecting or rekeying the session beforehand if needed
+    fn try_send_encrypted_message(
+        &mut self,
+        command: CommandMessage,
+    ) -> Result<ResponseMessage, SessionError> {
+        // Attempt to automatically reconnect if the session is unhealthy.
+        // This must happen before the command is encrypted, as reconnecting
+        // replaces the channel's session keys.
+        if !self.is_active() {
+            self.active = false;
+            self.reconnect()?;
+        }
+
+        // Rekey before the channel reaches `MAX_COMMANDS_PER_SESSION`, after
+        // which the YubiHSM2 won't accept any more commands over it
+        if self.channel.counter() >= self.rekey_threshold {
+            session_debug!(
+                self,
+                "reached rekey threshold ({} commands)",
+                self.rekey_threshold
+            );
+
+            self.rekey()?;
+        }
+
+        self.encrypt_and_send(command)
+    }
+
+    /// Close the current channel with the YubiHSM2
+    fn close_channel(&mut self) -> Result<(), SessionError> {
+        let response = self.encrypt_and_send(CloseSessionCommand {}.into())?;
+        decode_response::<CloseSessionCommand>(response)?;
+        Ok(())
+    }
+
+    /// Encrypt a command message and send it over the current channel as-is,
+    /// without reconnecting or rekeying, then authenticate and decrypt the
+    /// response
+    fn encrypt_and_send(
+        &mut self,
+        command: CommandMessage,
+    ) -> Result<ResponseMessage, SessionError> {
+        let command_type = command.command_type;
+        let encrypted_cmd = self.channel.encrypt_command(command)?;
         let uuid = encrypted_cmd.uuid;
 
-        session_debug!(self, "uuid={} encrypted-cmd={:?}", uuid, T::COMMAND_TYPE);
+        session_debug!(self, "uuid={} encrypted-cmd={:?}", uuid, command_type);
+
+        let command_info =
+            notify_command_started(self.observer.as_ref(), self.id(), command_type, uuid);
+
+        let result = self.send_and_decrypt(encrypted_cmd);
+        notify_command_finished(self.observer.as_ref(), command_info, result.as_ref());
+
+        result
+    }
 
+    /// Send an encrypted command message, then authenticate and decrypt the
+    /// response
+    fn send_and_decrypt(
+        &mut self,
+        encrypted_cmd: CommandMessage,
+    ) -> Result<ResponseMessage, SessionError> {
+        let uuid = encrypted_cmd.uuid;
         let encrypted_response = self.send_command(encrypted_cmd)?;
         let response = self.channel.decrypt_response(encrypted_response)?;
 
@@ -325,34 +663,136 @@ impl<C: Connector> Session<C> {
             response.data.len()
         );
 
-        if response.is_err() {
-            // TODO: factor this into ResponseMessage or ResponseCode?
-            let description = match response.code {
-                ResponseCode::MemoryError => {
-                    "general HSM error (e.g. bad command params, missing object)".to_owned()
-                }
-                other => format!("{:?}", other),
-            };
+        Ok(response)
+    }
+}
 
-            session_fail!(ResponseError, description);
-        }
+/// Ensure a rekey threshold leaves room for at least one command per channel
+/// (besides authentication), and for closing the channel before rekeying
+pub(crate) fn check_rekey_threshold(threshold: u32) {
+    assert!(
+        threshold >= 2 && threshold < MAX_COMMANDS_PER_SESSION,
+        "rekey threshold must be between 2 and {}",
+        MAX_COMMANDS_PER_SESSION - 1
+    );
+}
 
-        if response.command().unwrap() != T::COMMAND_TYPE {
-            session_fail!(
-                ResponseError,
-                "command type mismatch: expected {:?}, got {:?}",
-                T::COMMAND_TYPE,
-                response.command().unwrap()
-            );
-        }
+/// Lock a session shared between threads (e.g. by signers). If another thread
+/// panicked while using the session, it may have been left in an inconsistent
+/// state (e.g. in the middle of a command), so this returns an error instead.
+#[cfg(any(feature = "rustls-signer", feature = "signatory"))]
+pub(crate) fn lock<C: Connector>(
+    session: &Mutex<Session<C>>,
+) -> Result<MutexGuard<Session<C>>, SessionError> {
+    session.lock().map_err(|_| {
+        session_err!(
+            ProtocolError,
+            "session lock poisoned (another thread panicked while using it)"
+        )
+    })
+}
+
+/// Has a session which last sent a command at the given time timed out?
+pub(crate) fn timed_out(session_id: SessionId, last_command_timestamp: Instant) -> bool {
+    let time_since_last_command = Instant::now().duration_since(last_command_timestamp);
+
+    // Make sure the session hasn't timed out
+    if time_since_last_command > (SESSION_INACTIVITY_TIMEOUT - TIMEOUT_SKEW_INTERVAL) {
+        debug!(
+            "yubihsm: session={} session timed out after {} seconds (max {})",
+            session_id.to_u8(),
+            time_since_last_command.as_secs(),
+            SESSION_INACTIVITY_TIMEOUT.as_secs()
+        );
 
-        deserialize(response.data.as_ref()).map_err(|e| e.into())
+        return true;
     }
+
+    false
+}
+
+/// Open an encrypted channel from the YubiHSM2's response to a `CreateSession`
+/// command, ensuring the card cryptogram is correct
+pub(crate) fn open_channel(
+    credentials: &Credentials,
+    host_challenge: Challenge,
+    session_id: SessionId,
+    session_response: &CreateSessionResponse,
+) -> Result<Channel, SessionError> {
+    let channel = Channel::new(
+        session_id,
+        &credentials.auth_key,
+        host_challenge,
+        session_response.card_challenge,
+    );
+
+    if channel
+        .card_cryptogram()
+        .ct_eq(&session_response.card_cryptogram)
+        .unwrap_u8() != 1
+    {
+        session_fail!(AuthFailed, "card cryptogram mismatch!");
+    }
+
+    Ok(channel)
+}
+
+/// Ensure a response sent over the wire is successful and matches the
+/// command which was sent
+pub(crate) fn check_response(
+    cmd_type: CommandType,
+    response: &ResponseMessage,
+) -> Result<(), SessionError> {
+    if response.is_err() {
+        return Err(response.error_code().into());
+    }
+
+    if response.command().unwrap() != cmd_type {
+        session_fail!(
+            ProtocolError,
+            "command type mismatch: expected {:?}, got {:?}",
+            cmd_type,
+            response.command().unwrap()
+        );
+    }
+
+    Ok(())
+}
+
+/// Check a decrypted response to an encrypted command and deserialize it
+pub(crate) fn decode_response<T: Command>(
+    response: ResponseMessage,
+) -> Result<T::ResponseType, SessionError> {
+    check_decrypted_response(T::COMMAND_TYPE, &response)?;
+    deserialize(response.data.as_ref()).map_err(|e| e.into())
+}
+
+/// Ensure a decrypted response is successful and matches the command which
+/// was sent
+pub(crate) fn check_decrypted_response(
+    cmd_type: CommandType,
+    response: &ResponseMessage,
+) -> Result<(), SessionError> {
+    if response.is_err() {
+        return Err(response.error_code().into());
+    }
+
+    if response.command().unwrap() != cmd_type {
+        session_fail!(
+            ResponseError,
+            "command type mismatch: expected {:?}, got {:?}",
+            cmd_type,
+            response.command().unwrap()
+        );
+    }
+
+    Ok(())
 }
 
 /// Close session automatically on drop
 impl<C: Connector> Drop for Session<C> {
-    /// Make a best effort to close the session
+    /// Make a best effort to close the session, unless it was already closed
+    /// with `Session::close` (or abandoned)
     ///
     /// NOTE: this runs the potential of panicking in a drop handler, which
     /// results in the following when it occurs (Aieee!):
@@ -360,17 +800,18 @@ impl<C: Connector> Drop for Session<C> {
     /// "thread panicked while panicking. aborting"
     ///
     /// Because of this, it's very important `send_encrypted_command` and
-    /// everything it calls be panic-free.
+    /// everything it calls be panic-free. Use `Session::close` to avoid this
+    /// and find out whether the session was closed cleanly.
     fn drop(&mut self) {
-        // Don't do anything if the session is presumed unhealthy
+        // Don't do anything if the session is closed or presumed unhealthy
         if !self.is_active() {
             return;
         }
 
         session_debug!(self, "closing dropped session");
 
-        if let Err(e) = self.send_encrypted_command(CloseSessionCommand {}) {
-            session_debug!(self, "error closing dropped session: {}", e);
+        if let Err(e) = self.close_channel() {
+            session_debug!(self, "error closing dropped session (abandoning it): {}", e);
         }
     }
 }
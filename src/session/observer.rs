@@ -0,0 +1,142 @@
+//! Hooks for observing sessions, e.g. to collect metrics or trace commands
+//!
+//! A `SessionObserver` is notified around every encrypted command sent over
+//! a `Session` or `AsyncSession` (including the connector round trip), and after every attempt
+//! to reconnect a lost session. Observers only receive metadata about
+//! commands: payloads are never passed to them, so they can't leak keys or
+//! other sensitive data into logs or metrics backends.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::{SessionError, SessionErrorKind};
+use commands::CommandType;
+use securechannel::{ResponseMessage, SessionId};
+
+/// Observes commands sent over sessions and attempts to reconnect them.
+///
+/// Observers are called synchronously from the thread using the session, so
+/// they should return quickly (e.g. by recording metrics or sending events
+/// to another thread).
+pub trait SessionObserver: Send + Sync {
+    /// Called before an encrypted command is sent
+    fn command_started(&self, _command: &CommandInfo) {}
+
+    /// Called after an encrypted command has completed or failed
+    fn command_finished(&self, _command: &CommandInfo, _outcome: &CommandOutcome) {}
+
+    /// Called after each attempt to reconnect a lost session
+    fn reconnect_attempted(&self, _attempt: &ReconnectAttempt) {}
+}
+
+/// Information about an encrypted command
+#[derive(Clone, Debug)]
+pub struct CommandInfo {
+    /// Session the command was sent over
+    pub session_id: SessionId,
+
+    /// Type of the (encrypted) command
+    pub command_type: CommandType,
+
+    /// UUID of the request sent to yubihsm-connector
+    pub uuid: Uuid,
+
+    /// Instant when the command was sent
+    pub started_at: Instant,
+}
+
+/// Result of an encrypted command
+#[derive(Clone, Debug)]
+pub struct CommandOutcome {
+    /// Amount of time the command took, including the connector round trip
+    pub duration: Duration,
+
+    /// The kind of error which occurred, if the command failed. Errors
+    /// returned by the HSM are `SessionErrorKind::DeviceError`s which
+    /// include the `ResponseCode`.
+    pub error: Option<SessionErrorKind>,
+}
+
+impl CommandOutcome {
+    /// Did the command succeed?
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Information about an attempt to reconnect a lost session
+#[derive(Clone, Debug)]
+pub struct ReconnectAttempt {
+    /// Number of this attempt (starting at 1)
+    pub attempt: u32,
+
+    /// Amount of time this attempt took
+    pub duration: Duration,
+
+    /// The kind of error which occurred, if the attempt failed
+    pub error: Option<SessionErrorKind>,
+}
+
+/// Notify the observer (if any) that an encrypted command is about to be sent,
+/// returning the information passed to it
+pub(crate) fn notify_command_started(
+    observer: Option<&Arc<SessionObserver>>,
+    session_id: SessionId,
+    command_type: CommandType,
+    uuid: Uuid,
+) -> Option<CommandInfo> {
+    observer.map(|observer| {
+        let info = CommandInfo {
+            session_id,
+            command_type,
+            uuid,
+            started_at: Instant::now(),
+        };
+
+        observer.command_started(&info);
+        info
+    })
+}
+
+/// Notify the observer (if any) that an encrypted command has completed or
+/// failed with the given result
+pub(crate) fn notify_command_finished(
+    observer: Option<&Arc<SessionObserver>>,
+    info: Option<CommandInfo>,
+    result: Result<&ResponseMessage, &SessionError>,
+) {
+    if let (Some(observer), Some(info)) = (observer, info) {
+        let error = match result {
+            Ok(response) if response.is_err() => {
+                Some(SessionErrorKind::DeviceError(response.error_code()))
+            }
+            Ok(_) => None,
+            Err(e) => Some(e.kind()),
+        };
+
+        let outcome = CommandOutcome {
+            duration: Instant::now().duration_since(info.started_at),
+            error,
+        };
+
+        observer.command_finished(&info, &outcome);
+    }
+}
+
+/// Notify the observer (if any) about an attempt to reconnect a lost session
+/// which began at the given time
+pub(crate) fn notify_reconnect_attempted(
+    observer: Option<&Arc<SessionObserver>>,
+    attempt: u32,
+    started_at: Instant,
+    error: Option<SessionErrorKind>,
+) {
+    if let Some(observer) = observer {
+        observer.reconnect_attempted(&ReconnectAttempt {
+            attempt,
+            duration: Instant::now().duration_since(started_at),
+            error,
+        });
+    }
+}
@@ -0,0 +1,369 @@
+//! Thread-safe pools of authenticated sessions
+//!
+//! `Session` requires `&mut self` to send commands, so sharing a single
+//! session between threads serializes every operation. A `SessionPool`
+//! instead maintains up to `MAX_SESSIONS` authenticated sessions (the number
+//! of concurrent sessions supported by the `YubiHSM2`) and hands them out to
+//! callers on demand.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::{KeepaliveConfig, ReconnectPolicy, Session, SessionError, SessionObserver};
+use auth_key::AuthKey;
+use connector::{Connector, ConnectorError, HttpConfig, HttpConnector};
+#[cfg(feature = "keylog")]
+use keylog::KeyLogFile;
+use object::ObjectId;
+
+/// Maximum number of concurrent sessions supported by the `YubiHSM2`
+pub const MAX_SESSIONS: usize = 16;
+
+/// Default amount of time to wait for a session to become available
+pub const DEFAULT_CHECKOUT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Function which opens new connections to the `YubiHSM2`
+type ConnectorFactory<C> = Box<Fn() -> Result<C, ConnectorError> + Send + Sync>;
+
+/// Pool of authenticated sessions which can be shared between threads
+/// (e.g. by wrapping it in an `Arc`).
+///
+/// Sessions which have timed out or were marked unhealthy are discarded and
+/// transparently replaced with new ones. All idle sessions are closed when
+/// the pool is closed or dropped.
+pub struct SessionPool<C = HttpConnector>
+where
+    C: Connector,
+{
+    /// Opens a new connector for each session in the pool
+    open_connector: ConnectorFactory<C>,
+
+    /// Key ID to authenticate with
+    auth_key_id: ObjectId,
+
+    /// Auth key to authenticate with
+    auth_key: AuthKey,
+
+    /// Maximum number of sessions this pool will open
+    max_sessions: usize,
+
+    /// Observer set on each session opened by the pool (if any)
+    observer: Option<Arc<SessionObserver>>,
+
+    /// Key log set on each session opened by the pool (if any)
+    #[cfg(feature = "keylog")]
+    key_log: Option<Arc<KeyLogFile>>,
+
+    /// Mutable state of the pool
+    state: Mutex<PoolState<C>>,
+
+    /// Signaled whenever a session is returned or a slot is freed
+    available: Condvar,
+}
+
+/// Mutable state of a `SessionPool`
+struct PoolState<C: Connector> {
+    /// Authenticated sessions which aren't presently checked out
+    idle: Vec<Session<C>>,
+
+    /// Number of sessions opened by the pool (idle, checked out, or being created)
+    open: usize,
+
+    /// Has the pool been closed?
+    closed: bool,
+}
+
+// Special cased for HttpConnector for the same reasons as `Session::create`
+impl SessionPool<HttpConnector> {
+    /// Create a pool of sessions to the given yubihsm-connector, each of which
+    /// uses its own connection and authenticates with the given `AuthKey`
+    pub fn create(
+        connector_config: HttpConfig,
+        auth_key_id: ObjectId,
+        auth_key: AuthKey,
+        max_sessions: usize,
+    ) -> Self {
+        Self::new(
+            move || HttpConnector::open(connector_config.clone()),
+            auth_key_id,
+            auth_key,
+            max_sessions,
+        )
+    }
+
+    /// Create a pool of sessions authenticating with a given password.
+    /// See `Session::create_from_password` for notes on key derivation.
+    #[cfg(feature = "passwords")]
+    pub fn create_from_password(
+        connector_config: HttpConfig,
+        auth_key_id: ObjectId,
+        password: &[u8],
+        max_sessions: usize,
+    ) -> Self {
+        Self::create(
+            connector_config,
+            auth_key_id,
+            AuthKey::derive_from_password(password),
+            max_sessions,
+        )
+    }
+}
+
+impl<C: Connector> SessionPool<C> {
+    /// Create a new session pool which opens connections using the given
+    /// function. Sessions are opened lazily as they are requested.
+    ///
+    /// Panics if `max_sessions` is zero or exceeds `MAX_SESSIONS`.
+    pub fn new<F>(
+        open_connector: F,
+        auth_key_id: ObjectId,
+        auth_key: AuthKey,
+        max_sessions: usize,
+    ) -> Self
+    where
+        F: Fn() -> Result<C, ConnectorError> + Send + Sync + 'static,
+    {
+        assert!(
+            max_sessions > 0 && max_sessions <= MAX_SESSIONS,
+            "max_sessions must be between 1 and {} (got {})",
+            MAX_SESSIONS,
+            max_sessions
+        );
+
+        Self {
+            open_connector: Box::new(open_connector),
+            auth_key_id,
+            auth_key,
+            max_sessions,
+            observer: None,
+            #[cfg(feature = "keylog")]
+            key_log: None,
+            state: Mutex::new(PoolState {
+                idle: vec![],
+                open: 0,
+                closed: false,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Set an observer on each session subsequently opened by this pool
+    pub fn set_observer(&mut self, observer: Arc<SessionObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// **DANGER:** log the session keys of each session subsequently opened
+    /// by this pool to the given key log. See `Session::set_key_log`.
+    #[cfg(feature = "keylog")]
+    pub fn set_key_log(&mut self, key_log: Arc<KeyLogFile>) {
+        self.key_log = Some(key_log);
+    }
+
+    /// Maximum number of sessions this pool will open concurrently
+    pub fn max_sessions(&self) -> usize {
+        self.max_sessions
+    }
+
+    /// Number of sessions presently opened by this pool
+    pub fn open_sessions(&self) -> usize {
+        self.lock_state().open
+    }
+
+    /// Number of sessions which are idle (i.e. not checked out)
+    pub fn idle_sessions(&self) -> usize {
+        self.lock_state().idle.len()
+    }
+
+    /// Check out a session from the pool, waiting up to
+    /// `DEFAULT_CHECKOUT_TIMEOUT` for one to become available
+    pub fn get(&self) -> Result<PooledSession<C>, SessionError> {
+        self.get_timeout(DEFAULT_CHECKOUT_TIMEOUT)
+    }
+
+    /// Check out a session from the pool, waiting up to the given timeout for
+    /// one to become available. The session is returned to the pool when the
+    /// `PooledSession` is dropped.
+    pub fn get_timeout(&self, timeout: Duration) -> Result<PooledSession<C>, SessionError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock_state();
+
+        loop {
+            if state.closed {
+                session_fail!(CreateFailed, "session pool is closed");
+            }
+
+            while let Some(session) = state.idle.pop() {
+                if session.is_active() {
+                    return Ok(PooledSession {
+                        pool: self,
+                        session: Some(session),
+                    });
+                }
+
+                session_debug!(session, "discarding inactive pooled session");
+                state.open -= 1;
+            }
+
+            if state.open < self.max_sessions {
+                // Reserve a slot, then authenticate without holding the lock
+                state.open += 1;
+                drop(state);
+                return self.open_session();
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                session_fail!(
+                    TimeoutError,
+                    "timed out waiting for a pooled session (max {})",
+                    self.max_sessions
+                );
+            }
+
+            state = self
+                .available
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Close all idle sessions and prevent new ones from being checked out.
+    /// Sessions which are presently checked out are closed when returned.
+    pub fn close(&self) {
+        let idle = {
+            let mut state = self.lock_state();
+            state.closed = true;
+            state.open -= state.idle.len();
+            state.idle.drain(..).collect::<Vec<_>>()
+        };
+
+        debug!("yubihsm: closing session pool ({} idle)", idle.len());
+
+        // Dropping the sessions closes them
+        drop(idle);
+        self.available.notify_all();
+    }
+
+    /// Keep idle sessions from timing out (see `Session::keepalive`).
+    /// Sessions which fail are discarded.
+    pub fn keepalive(&self, config: &KeepaliveConfig) {
+        // Check the idle sessions out while sending keepalives, so they
+        // aren't handed out while in use and the pool isn't locked
+        let idle = self.lock_state().idle.drain(..).collect::<Vec<_>>();
+
+        for mut session in idle {
+            if let Err(e) = session.keepalive(config) {
+                session_debug!(session, "pooled session keepalive failed: {}", e);
+            }
+
+            self.checkin(session);
+        }
+    }
+
+    /// Open a new session in a slot which has already been reserved
+    fn open_session(&self) -> Result<PooledSession<C>, SessionError> {
+        let result = (self.open_connector)()
+            .map_err(|e| e.into())
+            .and_then(|connector| {
+                // The pool keeps the auth key around anyway, so its sessions
+                // retain it too, allowing them to be rekeyed
+                let policy = ReconnectPolicy {
+                    retain_credentials: true,
+                    ..ReconnectPolicy::disabled()
+                };
+
+                Session::new(connector, self.auth_key_id, self.auth_key.clone(), policy)
+            })
+            .map(|mut session| {
+                if let Some(ref observer) = self.observer {
+                    session.set_observer(Arc::clone(observer));
+                }
+
+                #[cfg(feature = "keylog")]
+                {
+                    if let Some(ref key_log) = self.key_log {
+                        session.set_key_log(Arc::clone(key_log));
+                    }
+                }
+
+                session
+            });
+
+        match result {
+            Ok(session) => Ok(PooledSession {
+                pool: self,
+                session: Some(session),
+            }),
+            Err(e) => {
+                self.lock_state().open -= 1;
+                self.available.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    /// Return a session to the pool, discarding it if it's unhealthy
+    fn checkin(&self, session: Session<C>) {
+        let mut state = self.lock_state();
+
+        if state.closed || !session.is_active() {
+            state.open -= 1;
+            drop(state);
+
+            // Dropping the session closes it (if it's still active)
+            drop(session);
+        } else {
+            state.idle.push(session);
+            drop(state);
+        }
+
+        self.available.notify_one();
+    }
+
+    /// Lock the pool's state, ignoring poisoning as the state is always
+    /// left consistent
+    fn lock_state(&self) -> MutexGuard<PoolState<C>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Close all idle sessions when the pool is dropped
+impl<C: Connector> Drop for SessionPool<C> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Session checked out from a `SessionPool`, which is returned to the pool on `Drop`
+pub struct PooledSession<'a, C: Connector + 'a> {
+    /// Pool this session belongs to
+    pool: &'a SessionPool<C>,
+
+    /// Session (always `Some` until dropped)
+    session: Option<Session<C>>,
+}
+
+impl<'a, C: Connector> Deref for PooledSession<'a, C> {
+    type Target = Session<C>;
+
+    fn deref(&self) -> &Session<C> {
+        self.session.as_ref().unwrap()
+    }
+}
+
+impl<'a, C: Connector> DerefMut for PooledSession<'a, C> {
+    fn deref_mut(&mut self) -> &mut Session<C> {
+        self.session.as_mut().unwrap()
+    }
+}
+
+impl<'a, C: Connector> Drop for PooledSession<'a, C> {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            self.pool.checkin(session);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Policies for reconnecting sessions and retrying commands
+
+use rand::{self, Rng};
+use std::time::Duration;
+
+use super::SessionError;
+use commands::CommandType;
+
+/// Default maximum number of reconnect attempts
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Default delay between the first and second reconnect attempts (100ms)
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Default upper bound on the delay between reconnect attempts (5 seconds)
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Policy for re-establishing sessions which have timed out or failed.
+///
+/// The first reconnect attempt is made immediately. Subsequent attempts are
+/// delayed by an exponentially increasing backoff, during which the thread
+/// using the session is put to sleep. Errors which can't be fixed by trying
+/// again (e.g. authentication failures) end the attempts early.
+///
+/// A `bool` can be converted into a policy: `true` makes a single attempt to
+/// reconnect without retrying commands (see `ReconnectPolicy::single_attempt`)
+/// and `false` disables reconnecting.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Maximum number of attempts to reconnect. Zero disables reconnecting.
+    pub max_attempts: u32,
+
+    /// Delay before the second attempt, which doubles for each attempt after it
+    pub initial_backoff: Duration,
+
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+
+    /// Randomize each delay between half of and the full computed backoff, so
+    /// clients which failed at the same time don't reconnect in lockstep
+    pub jitter: bool,
+
+    /// After reconnecting, transparently retry the command which failed
+    /// (once) if it's idempotent (see `CommandType::is_idempotent`).
+    /// Disabled by default, so a failed command is always reported.
+    pub retry_idempotent: bool,
+
+    /// Keep the auth key in memory for the lifetime of the session so it can
+    /// be rekeyed (explicitly, by a `Keepalive`, or on reaching its rekey
+    /// threshold) even though reconnecting is disabled. Sessions which can
+    /// reconnect always retain it, as reconnecting needs it too.
+    pub retain_credentials: bool,
+}
+
+impl ReconnectPolicy {
+    /// Policy which never reconnects
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Policy which makes one attempt to reconnect (i.e. without any backoff)
+    /// and doesn't retry commands
+    pub fn single_attempt() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Is reconnecting enabled?
+    pub fn is_enabled(&self) -> bool {
+        self.max_attempts > 0
+    }
+
+    /// Should sessions keep their credentials (i.e. the auth key) around?
+    pub(crate) fn retains_credentials(&self) -> bool {
+        self.is_enabled() || self.retain_credentials
+    }
+
+    /// Delay to wait after the given (1-based) failed attempt before the next
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let mut backoff = self.initial_backoff;
+
+        for _ in 1..attempt {
+            if backoff >= self.max_backoff {
+                break;
+            }
+
+            backoff *= 2;
+        }
+
+        if backoff > self.max_backoff {
+            backoff = self.max_backoff;
+        }
+
+        if !self.jitter {
+            return backoff;
+        }
+
+        let millis = backoff.as_secs() * 1000 + u64::from(backoff.subsec_millis());
+        Duration::from_millis(rand::thread_rng().gen_range(millis / 2, millis + 1))
+    }
+
+    /// Delay to wait before the next attempt after the given (1-based)
+    /// attempt failed with the given error, or `None` to give up
+    pub(crate) fn next_attempt(&self, attempt: u32, error: &SessionError) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.kind().is_retryable() {
+            return None;
+        }
+
+        Some(self.backoff(attempt))
+    }
+
+    /// Should the given command be retried if the session is lost while
+    /// sending it?
+    pub(crate) fn retries(&self, command_type: CommandType) -> bool {
+        self.retry_idempotent && command_type.is_idempotent()
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            jitter: true,
+            retry_idempotent: false,
+            retain_credentials: false,
+        }
+    }
+}
+
+impl From<bool> for ReconnectPolicy {
+    fn from(reconnect: bool) -> Self {
+        if reconnect {
+            Self::single_attempt()
+        } else {
+            Self::disabled()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: false,
+            ..ReconnectPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn from_bool() {
+        let policy = ReconnectPolicy::from(true);
+        assert_eq!(policy.max_attempts, 1);
+        assert!(!policy.retry_idempotent);
+        assert!(!policy.retries(CommandType::Echo));
+
+        let error = session_err!(TimeoutError, "timed out");
+        assert_eq!(policy.next_attempt(1, &error), None);
+
+        assert!(!ReconnectPolicy::from(false).is_enabled());
+    }
+
+    #[test]
+    fn backoff_jitter() {
+        let policy = ReconnectPolicy::default();
+
+        for _ in 0..100 {
+            let backoff = policy.backoff(2);
+            assert!(backoff >= Duration::from_millis(100));
+            assert!(backoff <= Duration::from_millis(200));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! ECDSA signer for keys stored in the `YubiHSM2`
+
+use ecdsa::elliptic_curve::generic_array::ArrayLength;
+use ecdsa::elliptic_curve::PrimeCurve;
+use ecdsa::{Signature, SignatureSize};
+use k256::Secp256k1;
+use p256::NistP256;
+use signature::{self, Signature as SignatureTrait, Signer};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use super::checked_public_key;
+use commands::get_pubkey::PublicKey;
+use commands::sign_ecdsa::{sign_ecdsa_sha256, ECDSASignature};
+use connector::HttpConnector;
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use mockhsm::MockConnector;
+use session::lock;
+use {AsymmetricAlgorithm, Connector, ObjectId, Session, SessionError};
+
+/// Elliptic curves supported by `ECDSASigner`
+pub trait ECDSACurve: PrimeCurve {
+    /// Algorithm of `YubiHSM2` keys on this curve
+    const ALGORITHM: AsymmetricAlgorithm;
+
+    /// Normalize signatures so `s` is at most half the curve's order
+    const LOW_S: bool;
+}
+
+impl ECDSACurve for NistP256 {
+    const ALGORITHM: AsymmetricAlgorithm = AsymmetricAlgorithm::EC_P256;
+    const LOW_S: bool = false;
+}
+
+/// secp256k1 signatures are normalized to low-S form, as required by e.g.
+/// Bitcoin's consensus rules
+impl ECDSACurve for Secp256k1 {
+    const ALGORITHM: AsymmetricAlgorithm = AsymmetricAlgorithm::EC_K256;
+    const LOW_S: bool = true;
+}
+
+/// ECDSA signer backed by a key stored in the `YubiHSM2`, which signs the
+/// SHA-256 hash of messages
+pub struct ECDSASigner<E, C = HttpConnector>
+where
+    E: ECDSACurve,
+    C: Connector,
+{
+    /// Session shared with other signers (or the rest of the application)
+    session: Arc<Mutex<Session<C>>>,
+
+    /// ID of the signing key
+    key_id: ObjectId,
+
+    /// Public key of the signing key, fetched when the signer was created
+    public_key: PublicKey,
+
+    /// Curve of the signing key
+    curve: PhantomData<E>,
+}
+
+impl<E: ECDSACurve, C: Connector> ECDSASigner<E, C> {
+    /// Create a signer for the given key ID, ensuring (with `get_pubkey`)
+    /// that it's a key on the signer's curve
+    pub fn create(session: Arc<Mutex<Session<C>>>, key_id: ObjectId) -> Result<Self, SessionError> {
+        let public_key = checked_public_key(&session, key_id, E::ALGORITHM)?;
+
+        Ok(Self {
+            session,
+            key_id,
+            public_key,
+            curve: PhantomData,
+        })
+    }
+
+    /// Get the ID of the signing key
+    pub fn key_id(&self) -> ObjectId {
+        self.key_id
+    }
+
+    /// Get the public key of the signing key
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+}
+
+#[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+impl<E, C> Signer<Signature<E>> for ECDSASigner<E, C>
+where
+    E: ECDSACurve,
+    C: Connector,
+    SignatureSize<E>: ArrayLength<u8>,
+{
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature<E>, signature::Error> {
+        let mut session = lock(&self.session).map_err(signature::Error::from_source)?;
+        let signature = sign_ecdsa_sha256(&mut session, self.key_id, msg)
+            .map_err(signature::Error::from_source)?;
+
+        to_signature(&signature)
+    }
+}
+
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+impl<E> Signer<Signature<E>> for ECDSASigner<E, MockConnector>
+where
+    E: ECDSACurve,
+    SignatureSize<E>: ArrayLength<u8>,
+{
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature<E>, signature::Error> {
+        let mut session = lock(&self.session).map_err(signature::Error::from_source)?;
+        let signature = sign_ecdsa_sha256(&mut session, self.key_id, msg)
+            .map_err(signature::Error::from_source)?;
+
+        to_signature(&signature)
+    }
+}
+
+/// Convert an ASN.1 DER signature returned by the `YubiHSM2` to a typed
+/// (fixed-width) signature
+fn to_signature<E>(signature: &ECDSASignature) -> Result<Signature<E>, signature::Error>
+where
+    E: ECDSACurve,
+    SignatureSize<E>: ArrayLength<u8>,
+{
+    let mut components = signature
+        .components(E::ALGORITHM)
+        .map_err(signature::Error::from_source)?;
+
+    if E::LOW_S {
+        components.normalize_s();
+    }
+
+    Signature::from_bytes(&components.to_fixed())
+}
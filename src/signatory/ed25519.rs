@@ -0,0 +1,61 @@
+//! Ed25519 signer for keys stored in the `YubiHSM2`
+
+use ed25519::Signature;
+use signature::{self, Signer};
+use std::sync::{Arc, Mutex};
+
+use super::checked_public_key;
+use commands::get_pubkey::PublicKey;
+use commands::sign_eddsa::sign_ed25519;
+use connector::HttpConnector;
+use session::lock;
+use {AsymmetricAlgorithm, Connector, ObjectId, Session, SessionError};
+
+/// Ed25519 signer backed by a key stored in the `YubiHSM2`
+pub struct Ed25519Signer<C = HttpConnector>
+where
+    C: Connector,
+{
+    /// Session shared with other signers (or the rest of the application)
+    session: Arc<Mutex<Session<C>>>,
+
+    /// ID of the signing key
+    key_id: ObjectId,
+
+    /// Public key of the signing key, fetched when the signer was created
+    public_key: PublicKey,
+}
+
+impl<C: Connector> Ed25519Signer<C> {
+    /// Create a signer for the given key ID, ensuring (with `get_pubkey`)
+    /// that it's an Ed25519 key
+    pub fn create(session: Arc<Mutex<Session<C>>>, key_id: ObjectId) -> Result<Self, SessionError> {
+        let public_key = checked_public_key(&session, key_id, AsymmetricAlgorithm::EC_ED25519)?;
+
+        Ok(Self {
+            session,
+            key_id,
+            public_key,
+        })
+    }
+
+    /// Get the ID of the signing key
+    pub fn key_id(&self) -> ObjectId {
+        self.key_id
+    }
+
+    /// Get the public key of the signing key
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+}
+
+impl<C: Connector> Signer<Signature> for Ed25519Signer<C> {
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+        let mut session = lock(&self.session).map_err(signature::Error::from_source)?;
+        let signature = sign_ed25519(&mut session, self.key_id, msg)
+            .map_err(signature::Error::from_source)?;
+
+        Signature::from_bytes(signature.as_ref())
+    }
+}
@@ -0,0 +1,54 @@
+//! Instead of writing an adapter which holds a `Session` and key ID for each
+//! signing algorithm, wrap a shared session in an `Ed25519Signer` or an
+//! `ECDSASigner` and use them anywhere a `signature::Signer` is expected:
+//!
+//! - `Ed25519Signer` produces `ed25519::Signature`s
+//! - `ECDSASigner<NistP256>` and `ECDSASigner<Secp256k1>` produce
+//!   `ecdsa::Signature`s (i.e. fixed-width `r || s`) of the SHA-256 hash of
+//!   the message
+//!
+//! Signers lock the session for the duration of each signing operation, and
+//! fail if another thread panicked while holding the lock.
+//!
+//! This module (and its cargo feature) is named after the [Signatory] project,
+//! whose signer traits now live in the `signature` crate. It can't be called
+//! `signature`, as cargo already defines a feature of that name for the
+//! optional `signature` dependency.
+//!
+//! [Signatory]: https://github.com/iqlusioninc/signatory
+
+use std::sync::Mutex;
+
+use commands::get_pubkey::{get_pubkey, PublicKey};
+use session::lock;
+use {AsymmetricAlgorithm, Connector, ObjectId, Session, SessionError, SessionErrorKind};
+
+mod ecdsa;
+mod ed25519;
+
+pub use self::ecdsa::{ECDSACurve, ECDSASigner};
+pub use self::ed25519::Ed25519Signer;
+pub use k256::Secp256k1;
+pub use p256::NistP256;
+
+/// Get the public key for the given key ID, ensuring it exists and has the
+/// expected algorithm
+fn checked_public_key<C: Connector>(
+    session: &Mutex<Session<C>>,
+    key_id: ObjectId,
+    algorithm: AsymmetricAlgorithm,
+) -> Result<PublicKey, SessionError> {
+    let public_key = get_pubkey(&mut *lock(session)?, key_id)?;
+
+    if public_key.algorithm != algorithm {
+        return Err(SessionError::new(
+            SessionErrorKind::ProtocolError,
+            Some(format!(
+                "expected {:?} key for key ID {} (got {:?})",
+                algorithm, key_id, public_key.algorithm
+            )),
+        ));
+    }
+
+    Ok(public_key)
+}
@@ -0,0 +1,240 @@
+//! Decoder for the messages exchanged with yubihsm-connector, e.g. for
+//! pretty-printing hex dumps of captured traffic.
+//!
+//! `decode_command` and `decode_response` parse the framing of a message
+//! (command type or response code, length, session ID, and MAC). Commands
+//! and responses which are sent in plaintext (i.e. outside of an encrypted
+//! session) and are supported by this decoder are also deserialized into
+//! their typed request or response structs.
+//!
+//! The payloads of `SessionMessage` commands and responses are encrypted.
+//! To decrypt them, see the `keylog` module.
+
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+pub use commands::{
+    create_session::{CreateSessionCommand, CreateSessionResponse},
+    device_info::{DeviceInfoCommand, DeviceInfoResponse},
+    echo::{EchoCommand, EchoResponse},
+};
+pub use securechannel::{
+    CommandMessage, Cryptogram, Mac, ResponseMessage, SecureChannelError, SecureChannelErrorKind,
+};
+
+use commands::CommandType;
+use connector::encode_hex;
+use securechannel::SessionId;
+use serializers::deserialize;
+
+/// Decode a command message sent to yubihsm-connector
+pub fn decode_command(bytes: &[u8]) -> Result<DecodedCommand, SecureChannelError> {
+    let message = CommandMessage::parse(bytes.into())?;
+
+    let payload = match message.command_type {
+        CommandType::CreateSession => Some(CommandPayload::CreateSession(deserialize_payload(
+            &message.data,
+        )?)),
+        CommandType::DeviceInfo => Some(CommandPayload::DeviceInfo(deserialize_payload(
+            &message.data,
+        )?)),
+        CommandType::Echo => Some(CommandPayload::Echo(deserialize_payload(&message.data)?)),
+        _ => None,
+    };
+
+    Ok(DecodedCommand { message, payload })
+}
+
+/// Decode a response message received from yubihsm-connector
+pub fn decode_response(bytes: &[u8]) -> Result<DecodedResponse, SecureChannelError> {
+    let message = ResponseMessage::parse(bytes.into())?;
+
+    let payload = if message.is_err() {
+        None
+    } else {
+        match message.command() {
+            Some(CommandType::CreateSession) => Some(ResponsePayload::CreateSession(
+                deserialize_payload(&message.data)?,
+            )),
+            Some(CommandType::DeviceInfo) => Some(ResponsePayload::DeviceInfo(
+                deserialize_payload(&message.data)?,
+            )),
+            Some(CommandType::Echo) => Some(ResponsePayload::Echo(deserialize_payload(
+                &message.data,
+            )?)),
+            _ => None,
+        }
+    };
+
+    Ok(DecodedResponse { message, payload })
+}
+
+/// Deserialize the payload of a plaintext message
+fn deserialize_payload<T>(data: &[u8]) -> Result<T, SecureChannelError>
+where
+    T: DeserializeOwned,
+{
+    deserialize(data).map_err(|e| {
+        SecureChannelError::new(
+            SecureChannelErrorKind::ProtocolError,
+            Some(format!("malformed payload: {}", e)),
+        )
+    })
+}
+
+/// A decoded command message
+#[derive(Debug)]
+pub struct DecodedCommand {
+    /// The parsed command message
+    pub message: CommandMessage,
+
+    /// The typed command, for plaintext commands supported by the decoder
+    pub payload: Option<CommandPayload>,
+}
+
+/// Plaintext commands supported by the decoder
+#[derive(Debug)]
+pub enum CommandPayload {
+    /// Create a new encrypted session
+    CreateSession(CreateSessionCommand),
+
+    /// Get information about the device
+    DeviceInfo(DeviceInfoCommand),
+
+    /// Echo a message
+    Echo(EchoCommand),
+}
+
+impl fmt::Display for DecodedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "command: {:?}", self.message.command_type)?;
+        write_framing(f, self.message.len(), self.message.session_id, &self.message.mac)?;
+
+        match self.payload {
+            Some(ref payload) => write!(f, "\n  payload: {:?}", payload),
+            None => write!(f, "\n  data: {}", encode_hex(&self.message.data)),
+        }
+    }
+}
+
+/// A decoded response message
+#[derive(Debug)]
+pub struct DecodedResponse {
+    /// The parsed response message
+    pub message: ResponseMessage,
+
+    /// The typed response, for plaintext responses supported by the decoder
+    pub payload: Option<ResponsePayload>,
+}
+
+/// Plaintext responses supported by the decoder
+#[derive(Debug)]
+pub enum ResponsePayload {
+    /// Response to creating a new encrypted session
+    CreateSession(CreateSessionResponse),
+
+    /// Information about the device
+    DeviceInfo(DeviceInfoResponse),
+
+    /// The echoed message
+    Echo(EchoResponse),
+}
+
+impl fmt::Display for DecodedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.message.is_err() {
+            write!(f, "response: error {:?}", self.message.error_code())?;
+        } else {
+            write!(f, "response: {:?}", self.message.code)?;
+        }
+
+        write_framing(f, self.message.len(), self.message.session_id, &self.message.mac)?;
+
+        match self.payload {
+            Some(ref payload) => write!(f, "\n  payload: {:?}", payload),
+            None => write!(f, "\n  data: {}", encode_hex(&self.message.data)),
+        }
+    }
+}
+
+/// Write the length, session ID, and MAC of a message
+fn write_framing(
+    f: &mut fmt::Formatter,
+    length: usize,
+    session_id: Option<SessionId>,
+    mac: &Option<Mac>,
+) -> fmt::Result {
+    write!(f, "\n  length: {}", length)?;
+
+    if let Some(id) = session_id {
+        write!(f, "\n  session: {}", id.to_u8())?;
+    }
+
+    if let Some(ref mac) = *mac {
+        write!(f, "\n  mac: {}", encode_hex(mac.as_slice()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commands::CommandType;
+    use securechannel::ResponseCode;
+
+    #[test]
+    fn decode_create_session_test() {
+        let command = decode_command(&[
+            0x03, 0x00, 0x0a, 0x00, 0x01, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        ]).unwrap();
+
+        assert_eq!(command.message.command_type, CommandType::CreateSession);
+
+        match command.payload {
+            Some(CommandPayload::CreateSession(ref payload)) => {
+                assert_eq!(payload.auth_key_id, 1);
+                assert_eq!(payload.host_challenge.as_slice(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+            }
+            ref other => panic!("unexpected payload: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_echo_response_test() {
+        let response = decode_response(&[0x81, 0x00, 0x05, b'h', b'e', b'l', b'l', b'o']).unwrap();
+
+        assert_eq!(
+            response.message.code,
+            ResponseCode::Success(CommandType::Echo)
+        );
+
+        match response.payload {
+            Some(ResponsePayload::Echo(ref payload)) => assert_eq!(payload.0, b"hello"),
+            ref other => panic!("unexpected payload: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_session_message_test() {
+        let command = decode_command(&[
+            0x05, 0x00, 0x0d, 0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+            0x77, 0x88,
+        ]).unwrap();
+
+        assert_eq!(command.message.command_type, CommandType::SessionMessage);
+        assert_eq!(command.message.session_id.unwrap().to_u8(), 2);
+        assert_eq!(command.message.data, [0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(
+            command.message.mac.as_ref().unwrap().as_slice(),
+            [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88]
+        );
+        assert!(command.payload.is_none());
+    }
+
+    #[test]
+    fn decode_truncated_message_test() {
+        let err = decode_response(&[0x81, 0x00, 0x05, b'h']).err().unwrap();
+        assert_eq!(err.kind(), SecureChannelErrorKind::ProtocolError);
+    }
+}
@@ -0,0 +1,760 @@
+//! Parsing and verification of the attestation certificates returned by
+//! `attest_asymmetric`
+
+use byteorder::{BigEndian, ByteOrder};
+use ring::signature;
+use std::time::SystemTime;
+use untrusted;
+
+use super::parser::ParsedCertificate;
+use super::{Certificate, X509Error};
+use commands::attest_asymmetric::AttestationCertificate;
+use commands::get_pubkey::{get_pubkey, PublicKey};
+use encoding::der::{self, Decoder};
+use encoding::oid;
+use {
+    AsymmetricAlgorithm, Capability, Connector, Domain, ObjectId, ObjectLabel, ObjectOrigin,
+    Session,
+};
+
+/// Attestation certificate for a key stored in the `YubiHSM2`, parsed into
+/// the attested public key and the properties of the key the `YubiHSM2`
+/// records in its extensions
+///
+/// <https://developers.yubico.com/YubiHSM2/Concepts/Attestation.html>
+///
+/// Parsing doesn't check the certificate is authentic: use `verify` to check
+/// its signature chain, and `check_public_key` to check the attested key is
+/// the one the `YubiHSM2` returns for the attested object ID.
+#[derive(Clone, Debug)]
+pub struct Attestation {
+    /// The attestation certificate itself
+    certificate: Certificate,
+
+    /// Public key of the attested key
+    pub public_key: PublicKey,
+
+    /// Major version of the `YubiHSM2` firmware
+    pub major_version: u8,
+
+    /// Minor version of the `YubiHSM2` firmware
+    pub minor_version: u8,
+
+    /// Build version of the `YubiHSM2` firmware
+    pub build_version: u8,
+
+    /// Serial number of the `YubiHSM2`
+    pub serial_number: u32,
+
+    /// How the attested key was created
+    pub origin: ObjectOrigin,
+
+    /// Domains of the attested key
+    pub domains: Domain,
+
+    /// Capabilities of the attested key
+    pub capabilities: Capability,
+
+    /// Object ID of the attested key
+    pub object_id: ObjectId,
+
+    /// Label of the attested key
+    pub label: ObjectLabel,
+}
+
+impl Attestation {
+    /// Parse an attestation certificate
+    pub fn parse(attestation_certificate: &AttestationCertificate) -> Result<Self, X509Error> {
+        let certificate = ParsedCertificate::parse(attestation_certificate.as_slice())?;
+        let public_key = PublicKey::from_spki_der(certificate.subject_public_key_info)?;
+
+        let mut firmware_version = extension(&certificate, oid::YUBIHSM_FIRMWARE_VERSION)?;
+        let version = firmware_version.decode(der::OCTET_STRING)?;
+        firmware_version.finish()?;
+
+        if version.len() != 3 {
+            x509_fail!(ParseError, "malformed firmware version: {:?}", version);
+        }
+
+        let mut serial_number = extension(&certificate, oid::YUBIHSM_SERIAL_NUMBER)?;
+        let serial = integer(&mut serial_number, 4)?;
+        serial_number.finish()?;
+
+        let mut origin = extension(&certificate, oid::YUBIHSM_ORIGIN)?;
+        let origin_byte = bit_string(&mut origin, 1)?;
+        origin.finish()?;
+
+        let mut domains = extension(&certificate, oid::YUBIHSM_DOMAINS)?;
+        let domain_bits = bit_string(&mut domains, 2)?;
+        domains.finish()?;
+
+        let mut capabilities = extension(&certificate, oid::YUBIHSM_CAPABILITIES)?;
+        let capability_bits = bit_string(&mut capabilities, 8)?;
+        capabilities.finish()?;
+
+        let mut object_id = extension(&certificate, oid::YUBIHSM_OBJECT_ID)?;
+        let id = integer(&mut object_id, 2)?;
+        object_id.finish()?;
+
+        let mut label = extension(&certificate, oid::YUBIHSM_LABEL)?;
+        let label_bytes = label.decode(der::UTF8_STRING)?;
+        label.finish()?;
+
+        Ok(Self {
+            certificate: Certificate::from_der(attestation_certificate.as_slice())?,
+            public_key,
+            major_version: version[0],
+            minor_version: version[1],
+            build_version: version[2],
+            serial_number: serial as u32,
+            origin: ObjectOrigin::from_u8(origin_byte as u8)
+                .map_err(|e| x509_err!(ParseError, "{}", e))?,
+            domains: Domain::from_bits_truncate(domain_bits as u16),
+            capabilities: Capability::from_bits_truncate(capability_bits),
+            object_id: id as ObjectId,
+            label: ObjectLabel::new(label_bytes).map_err(|e| x509_err!(ParseError, "{}", e))?,
+        })
+    }
+
+    /// Borrow the attestation certificate
+    pub fn certificate(&self) -> &Certificate {
+        &self.certificate
+    }
+
+    /// Was the attested key generated within the `YubiHSM2` (and never
+    /// exported), and is it non-exportable (i.e. does it lack the
+    /// `EXPORT_UNDER_WRAP` capability)?
+    pub fn is_generated_non_exportable(&self) -> bool {
+        self.origin == ObjectOrigin::Generated
+            && !self.capabilities.contains(Capability::EXPORT_UNDER_WRAP)
+    }
+
+    /// Verify the signature chain of the attestation certificate: it must be
+    /// signed by the first certificate in the chain, which must be signed by
+    /// the next, and so on. The last certificate in the chain is trusted as
+    /// given, so it should be a root CA certificate (e.g. Yubico's, when
+    /// verifying an attestation by the device attestation key) or the
+    /// certificate of an attestation key.
+    ///
+    /// Each issuer name must exactly match the subject name of the next
+    /// certificate, and every certificate in the chain must be a CA
+    /// certificate (with basicConstraints `cA` set and keyUsage including
+    /// keyCertSign). All of the certificates must be valid now, and none of
+    /// them may have critical extensions other than basicConstraints and
+    /// keyUsage, as no others are processed.
+    pub fn verify(&self, chain: &[Certificate]) -> Result<(), X509Error> {
+        self.verify_at(chain, SystemTime::now())
+    }
+
+    /// Verify the signature chain of the attestation certificate as `verify`
+    /// does, but checking the certificates are valid at the given time
+    pub fn verify_at(&self, chain: &[Certificate], time: SystemTime) -> Result<(), X509Error> {
+        if chain.is_empty() {
+            x509_fail!(VerificationFailed, "empty certificate chain");
+        }
+
+        let mut subject = ParsedCertificate::parse(self.certificate.as_der())?;
+        check_validity(&subject, time)?;
+        check_critical_extensions(&subject)?;
+
+        for (ca_certificates_below, issuer) in chain.iter().enumerate() {
+            let issuer = ParsedCertificate::parse(issuer.as_der())?;
+            check_validity(&issuer, time)?;
+            check_critical_extensions(&issuer)?;
+            check_issuer(&issuer, ca_certificates_below)?;
+            verify_signature(&subject, &issuer)?;
+            subject = issuer;
+        }
+
+        Ok(())
+    }
+
+    /// Check the attested public key matches the public key the `YubiHSM2`
+    /// returns (with `get_pubkey`) for the attested object ID
+    pub fn check_public_key<C: Connector>(
+        &self,
+        session: &mut Session<C>,
+    ) -> Result<(), X509Error> {
+        let public_key = get_pubkey(session, self.object_id)?;
+
+        if public_key.algorithm != self.public_key.algorithm
+            || public_key.bytes != self.public_key.bytes
+        {
+            x509_fail!(
+                VerificationFailed,
+                "attested public key doesn't match the public key of object 0x{:04x}",
+                self.object_id
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Find an attestation extension, returning a decoder for its value
+fn extension<'a>(
+    certificate: &ParsedCertificate<'a>,
+    id: &[u32],
+) -> Result<Decoder<'a>, X509Error> {
+    match certificate.extension(id) {
+        Some(value) => Ok(Decoder::new(value)),
+        None => x509_fail!(ParseError, "missing attestation extension: {:?}", id),
+    }
+}
+
+/// Decode an INTEGER of at most the given size in bytes
+fn integer(decoder: &mut Decoder, size: usize) -> Result<u64, X509Error> {
+    let bytes = decoder.integer()?;
+
+    if bytes.len() > size {
+        x509_fail!(ParseError, "integer too large ({} bytes)", bytes.len());
+    }
+
+    Ok(BigEndian::read_uint(bytes, bytes.len()))
+}
+
+/// Decode a BIT STRING of at most the given size in bytes as a big endian
+/// integer of that size. Trailing zero bits may be omitted from the encoding,
+/// as with the named bit lists OpenSSL emits.
+fn bit_string(decoder: &mut Decoder, size: usize) -> Result<u64, X509Error> {
+    let contents = decoder.decode(der::BIT_STRING)?;
+
+    let bytes = match contents.split_first() {
+        Some((&unused_bits, bytes)) if unused_bits < 8 => bytes,
+        _ => x509_fail!(ParseError, "malformed bit string"),
+    };
+
+    if bytes.len() > size {
+        x509_fail!(ParseError, "bit string too long ({} bytes)", bytes.len());
+    }
+
+    let mut padded = vec![0u8; size];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    Ok(BigEndian::read_uint(&padded, size))
+}
+
+/// Check a certificate is valid (i.e. within its validity period) at the
+/// given time
+fn check_validity(certificate: &ParsedCertificate, time: SystemTime) -> Result<(), X509Error> {
+    if time < certificate.not_before {
+        x509_fail!(VerificationFailed, "certificate isn't valid yet");
+    }
+
+    if time > certificate.not_after {
+        x509_fail!(VerificationFailed, "certificate has expired");
+    }
+
+    Ok(())
+}
+
+/// Check a certificate has no critical extensions other than the ones we
+/// process (basicConstraints and keyUsage), which RFC 5280 (section 4.2)
+/// requires us to reject the certificate for
+fn check_critical_extensions(certificate: &ParsedCertificate) -> Result<(), X509Error> {
+    for extension in &certificate.extensions {
+        if extension.critical
+            && !der::object_identifier_eq(extension.id, oid::BASIC_CONSTRAINTS)
+            && !der::object_identifier_eq(extension.id, oid::KEY_USAGE)
+        {
+            x509_fail!(VerificationFailed, "certificate has an unsupported critical extension");
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a certificate can issue other certificates: its basicConstraints
+/// must mark it as a CA (allowing the given number of CA certificates below
+/// it in the chain), and its keyUsage must include keyCertSign
+fn check_issuer(issuer: &ParsedCertificate, ca_certificates_below: usize) -> Result<(), X509Error> {
+    let basic_constraints = match issuer.extension(oid::BASIC_CONSTRAINTS) {
+        Some(value) => value,
+        None => x509_fail!(VerificationFailed, "issuer has no basicConstraints"),
+    };
+
+    // BasicConstraints ::= SEQUENCE { cA BOOLEAN DEFAULT FALSE,
+    //                                 pathLenConstraint INTEGER OPTIONAL }
+    let mut decoder = Decoder::new(basic_constraints);
+    let mut basic_constraints = decoder.sequence()?;
+    decoder.finish()?;
+
+    if basic_constraints.optional(der::BOOLEAN)? != Some(&[0xff][..]) {
+        x509_fail!(VerificationFailed, "issuer isn't a CA");
+    }
+
+    if !basic_constraints.is_empty() {
+        let path_len = integer(&mut basic_constraints, 8)?;
+
+        if ca_certificates_below as u64 > path_len {
+            x509_fail!(VerificationFailed, "issuer's path length constraint exceeded");
+        }
+    }
+
+    basic_constraints.finish()?;
+
+    let key_usage = match issuer.extension(oid::KEY_USAGE) {
+        Some(value) => value,
+        None => x509_fail!(VerificationFailed, "issuer has no keyUsage"),
+    };
+
+    let mut decoder = Decoder::new(key_usage);
+    let key_usage = bit_string(&mut decoder, 2)?;
+    decoder.finish()?;
+
+    // keyCertSign is bit 5 of the named bit list
+    if key_usage & 0x0400 == 0 {
+        x509_fail!(VerificationFailed, "issuer's keyUsage doesn't include keyCertSign");
+    }
+
+    Ok(())
+}
+
+/// Verify the signature of a certificate with its issuer's public key
+fn verify_signature(
+    subject: &ParsedCertificate,
+    issuer: &ParsedCertificate,
+) -> Result<(), X509Error> {
+    if subject.issuer != issuer.subject {
+        x509_fail!(
+            VerificationFailed,
+            "certificate issuer doesn't match the next certificate's subject"
+        );
+    }
+
+    let public_key = PublicKey::from_spki_der(issuer.subject_public_key_info)?;
+    let signature_algorithm = subject.signature_algorithm;
+    let signed_with = |id| der::object_identifier_eq(signature_algorithm, id);
+
+    let algorithm: &signature::VerificationAlgorithm = match public_key.algorithm {
+        AsymmetricAlgorithm::EC_P256 if signed_with(oid::ECDSA_WITH_SHA256) => {
+            &signature::ECDSA_P256_SHA256_ASN1
+        }
+        AsymmetricAlgorithm::EC_P256 if signed_with(oid::ECDSA_WITH_SHA384) => {
+            &signature::ECDSA_P256_SHA384_ASN1
+        }
+        AsymmetricAlgorithm::EC_P384 if signed_with(oid::ECDSA_WITH_SHA256) => {
+            &signature::ECDSA_P384_SHA256_ASN1
+        }
+        AsymmetricAlgorithm::EC_P384 if signed_with(oid::ECDSA_WITH_SHA384) => {
+            &signature::ECDSA_P384_SHA384_ASN1
+        }
+        AsymmetricAlgorithm::EC_ED25519 if signed_with(oid::ED25519) => &signature::ED25519,
+        AsymmetricAlgorithm::RSA2048
+        | AsymmetricAlgorithm::RSA3072
+        | AsymmetricAlgorithm::RSA4096 => {
+            if signed_with(oid::SHA256_WITH_RSA_ENCRYPTION) {
+                &signature::RSA_PKCS1_2048_8192_SHA256
+            } else if signed_with(oid::SHA384_WITH_RSA_ENCRYPTION) {
+                &signature::RSA_PKCS1_2048_8192_SHA384
+            } else if signed_with(oid::SHA512_WITH_RSA_ENCRYPTION) {
+                &signature::RSA_PKCS1_2048_8192_SHA512
+            } else {
+                x509_fail!(UnsupportedAlgorithm, "unsupported RSA signature algorithm");
+            }
+        }
+        AsymmetricAlgorithm::EC_P521 => x509_fail!(
+            UnverifiableSignature,
+            "can't verify signatures by NIST P-521 keys"
+        ),
+        other => x509_fail!(
+            UnsupportedAlgorithm,
+            "unsupported signature algorithm for {:?} keys",
+            other
+        ),
+    };
+
+    let key = match public_key.algorithm {
+        AsymmetricAlgorithm::EC_ED25519 => public_key.bytes.clone(),
+        AsymmetricAlgorithm::RSA2048
+        | AsymmetricAlgorithm::RSA3072
+        | AsymmetricAlgorithm::RSA4096 => public_key.to_pkcs1_der()?,
+        _ => public_key.to_sec1_uncompressed()?,
+    };
+
+    signature::verify(
+        algorithm,
+        untrusted::Input::from(&key),
+        untrusted::Input::from(subject.tbs_certificate),
+        untrusted::Input::from(subject.signature),
+    ).map_err(|_| x509_err!(VerificationFailed, "invalid certificate signature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connector::decode_hex;
+    use std::time::{Duration, UNIX_EPOCH};
+    use x509::X509ErrorKind;
+
+    /// Attestation certificate for a P-256 key with ID 0x0064, signed by
+    /// `DEVICE_CERTIFICATE`
+    const ATTESTATION_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIICJjCCAc2gAwIBAgIBAzAKBggqhkjOPQQDAjAjMSEwHwYDVQQDDBhUZXN0IFl1
+YmlIU00gQXR0ZXN0YXRpb24wIBcNMjYxMDE5MTAxMDI4WhgPMjEyNjA5MjUxMDEw
+MjhaMCgxJjAkBgNVBAMMHVl1YmlIU00gQXR0ZXN0YXRpb24gaWQ6MHgwMDY0MFkw
+EwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEX7PnmHICYEka7rYjNx9hw/Y6hlQDNgnO
+jIKy5dit6vYJBxA9LjvH2QJFG0yuZrce001APRBh9HOqKO5iZnmCSaOB6jCB5zAT
+BgorBgEEAYLECgQBBAUEAwIBAzAUBgorBgEEAYLECgQCBAYCBAC8YU4wEgYKKwYB
+BAGCxAoEAwQEAwIAATATBgorBgEEAYLECgQEBAUDAwAAATAZBgorBgEEAYLECgQF
+BAsDCQAAAAAAAAAAgDARBgorBgEEAYLECgQGBAMCAWQwIwYKKwYBBAGCxAoECQQV
+DBN5dWJpaHNtLnJzIHRlc3Qga2V5MB0GA1UdDgQWBBR0rbZBIRvjbX1BTdJ+gY5L
+ZOVKHzAfBgNVHSMEGDAWgBQDVb8PjTY5aLqkVcXY2zGPVGzY6DAKBggqhkjOPQQD
+AgNHADBEAiB/MCtddvdFk2jzXiP66/7+BExWTXX/Z/rI/BVcHh6IpwIgKHxNJSUG
+gnIC6LaJAV1Vxlv1j5XF2gZpqq7cTqJeAaA=
+-----END CERTIFICATE-----
+";
+
+    /// P-256 device attestation certificate, signed by `ROOT_CERTIFICATE`
+    const DEVICE_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIICWzCCAUOgAwIBAgIBAjANBgkqhkiG9w0BAQsFADAjMSEwHwYDVQQDDBhUZXN0
+IEF0dGVzdGF0aW9uIFJvb3QgQ0EwIBcNMjYxMDE5MTAwOTA2WhgPMjEyNjA5MjUx
+MDA5MDZaMCMxITAfBgNVBAMMGFRlc3QgWXViaUhTTSBBdHRlc3RhdGlvbjBZMBMG
+ByqGSM49AgEGCCqGSM49AwEHA0IABBYC44gDS+iNYiNtR5jBnAU+WFKaqy+FZq7F
+khrqwieWfC+yhcWQucj7pVaKQ8EzD/gViWqoaOBd2xANagpvFfajYzBhMA8GA1Ud
+EwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgIEMB0GA1UdDgQWBBQDVb8PjTY5aLqk
+VcXY2zGPVGzY6DAfBgNVHSMEGDAWgBRZY5hGjoSUsF6kWt/XGJG+lt4MSTANBgkq
+hkiG9w0BAQsFAAOCAQEAMpppUzqPvlNPdHOCK7w4YdnXiQO//bE+LEXt7iNUejaK
+QQYiTv2g48/ByhNWODu27qodK5mpHX7L2TdOYpE+MW851xDCxkNqpLmBVnD4IRfN
+16t/Tpw+QWQz6LURj3QbvbCi66Xkz8hGYY+g+Nx5HewdGAxYiOCGJASAff81Hl4c
+MgvLYg+7nNeQQc4bwATad8j7QUnodecNtoq90uL97EVHY3oGG/+TJnp0zNBwHFG4
+DRHei5yU/6kupX2Vvp0HRdmuBQzcefgOUFi9e18IgYti36TaGBwjoU3qPieLLBHC
+VZ1LdjpqOANEbucz3U9l2rsBCUOM3mPIVWZdf82Hww==
+-----END CERTIFICATE-----
+";
+
+    /// Self-signed RSA-2048 root certificate
+    const ROOT_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIDBTCCAe2gAwIBAgIBATANBgkqhkiG9w0BAQsFADAjMSEwHwYDVQQDDBhUZXN0
+IEF0dGVzdGF0aW9uIFJvb3QgQ0EwIBcNMjYxMDE5MTAwOTA2WhgPMjEyNjA5MjUx
+MDA5MDZaMCMxITAfBgNVBAMMGFRlc3QgQXR0ZXN0YXRpb24gUm9vdCBDQTCCASIw
+DQYJKoZIhvcNAQEBBQADggEPADCCAQoCggEBALjFRNZvc/AcX1HfwtKA1JdddGN2
+dQw2EYxAOYI4AtuHQzXBnEYiOZ5wvYkyI7aF14MlPGWoBK4cuSnv0X7mU6MR4ka9
+8XplxHIwzmi+DOUgzwCFA9eCZtI1DqNj6iVj4SeK6W+/uWulnJK2fqVt6566EnHS
+JZziMWR0LcQZpL4XRmHd28VVaerP2H499PijlCPr0q2vMRpwp2GhKXD85xZ1+txO
+EexmGTJLVNiehrfC36p1jGzrBT5tWnjMZJAtoQoXHJhezZSZ8UX2CiVZ2BHCGT0d
+JbgvGOAtaHFgrUHVu6YNvqoZcBeQ2YoT3WLXxBArz5DicnH/lNIGGcDpp5UCAwEA
+AaNCMEAwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCAgQwHQYDVR0OBBYE
+FFljmEaOhJSwXqRa39cYkb6W3gxJMA0GCSqGSIb3DQEBCwUAA4IBAQAtFpCJT4Tj
+b/zG/oplbKiluLsY57nF7fnWQnQbyVpW74b5ovRFTeMx/b6pdFpbresECMBNH2SM
+3vXa2i0OC2m7jHuQQaopzk18rAmDgxEUhqiRj51MBKaGpfDWb1aElev/vANzvAId
+UNJyopkRaCtDqA6vdxsapSr7JWpvaGAzuiHYtjyebY6hxZgo/Ya2cbYwlTFMVO8H
+VRvH3rnaXHwR/z1cuRIoEctGKvj3X+sfe0nb50zd3mZD6cgq3zzxldD2mq5SlO7o
+XAjsjN2FLGuNpkvgeH22g6tx33HJ8sDmpzCM1oexxzxQBdOQbO5ztvZF38K89lbF
+lOG45zzAB3ad
+-----END CERTIFICATE-----
+";
+
+    /// End-entity certificate signed by `ROOT_CERTIFICATE`
+    const ROGUE_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIICVTCCAT2gAwIBAgIBBDANBgkqhkiG9w0BAQsFADAjMSEwHwYDVQQDDBhUZXN0
+IEF0dGVzdGF0aW9uIFJvb3QgQ0EwIBcNMjYxMDE5MTEwOTQ0WhgPMjEyNjA5MjUx
+MTA5NDRaMCAxHjAcBgNVBAMMFVRlc3QgUm9ndWUgRW5kIEVudGl0eTBZMBMGByqG
+SM49AgEGCCqGSM49AwEHA0IABDbmrymkRGCIdH3zvPaa5TjGNNcuAEng/N8Z0S/F
+oa6Q0LTVg7asDnpwYBzZTS3Osj67+3CnJPcw7vSgtdTjzUmjYDBeMAwGA1UdEwEB
+/wQCMAAwDgYDVR0PAQH/BAQDAgeAMB0GA1UdDgQWBBSE/OGZSzM+woIuyfYGT5W3
+fritdTAfBgNVHSMEGDAWgBRZY5hGjoSUsF6kWt/XGJG+lt4MSTANBgkqhkiG9w0B
+AQsFAAOCAQEAV+UsHJndg5wulsRx7isewgkwYG//2lAfJ2rvgFGHLqLicVmseR9E
+1ciQ3NqNW643BxGn9sTdOaybrrEyfwfnkk4zUTg2DOH0KJCDpAbrWwxIRHke+Wa9
+leDXYoXwDgtIVqdeu/4ndbyG61RBNaMezpYkmCOWmgpmw+FFWOHh2q/KXpNU8aad
+yX+T768VubWX9CXm0XW+nzHZUo13PHz/P8zPXX5CNB37ABrNSy0iM9122GlTi1YV
+PqmqmCz8xZObh4pcLjrbhrZAO8+AaC3GexQByIKb+cDHeZA1QrR0qpfONGcTyCzS
+fWWD/TRHtFyP4WnaS2U3FaHx0ckKZ08qVA==
+-----END CERTIFICATE-----
+";
+
+    /// Attestation certificate for the same key as `ATTESTATION_CERTIFICATE`,
+    /// signed by the end-entity `ROGUE_CERTIFICATE`
+    const FORGED_ATTESTATION_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIICJDCCAcqgAwIBAgIBBTAKBggqhkjOPQQDAjAgMR4wHAYDVQQDDBVUZXN0IFJv
+Z3VlIEVuZCBFbnRpdHkwIBcNMjYxMDE5MTEwOTQ0WhgPMjEyNjA5MjUxMTA5NDRa
+MCgxJjAkBgNVBAMMHVl1YmlIU00gQXR0ZXN0YXRpb24gaWQ6MHgwMDY0MFkwEwYH
+KoZIzj0CAQYIKoZIzj0DAQcDQgAEX7PnmHICYEka7rYjNx9hw/Y6hlQDNgnOjIKy
+5dit6vYJBxA9LjvH2QJFG0yuZrce001APRBh9HOqKO5iZnmCSaOB6jCB5zATBgor
+BgEEAYLECgQBBAUEAwIBAzAUBgorBgEEAYLECgQCBAYCBAC8YU4wEgYKKwYBBAGC
+xAoEAwQEAwIAATATBgorBgEEAYLECgQEBAUDAwAAATAZBgorBgEEAYLECgQFBAsD
+CQAAAAAAAAAAgDARBgorBgEEAYLECgQGBAMCAWQwIwYKKwYBBAGCxAoECQQVDBN5
+dWJpaHNtLnJzIHRlc3Qga2V5MB0GA1UdDgQWBBR0rbZBIRvjbX1BTdJ+gY5LZOVK
+HzAfBgNVHSMEGDAWgBSE/OGZSzM+woIuyfYGT5W3fritdTAKBggqhkjOPQQDAgNI
+ADBFAiB/KUdkRImed7RMRE5Wi1UmhLKGq2gZHcWqrfJfI1bZTgIhAPnkedkNfhRK
+plbSD7KrLM+8s4zqRLyvFrq4JfRe/x8z
+-----END CERTIFICATE-----
+";
+
+    /// Self-signed CA certificate whose keyUsage lacks keyCertSign
+    const NO_CERT_SIGN_CA_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBfzCCASWgAwIBAgIBBjAKBggqhkjOPQQDAjAmMSQwIgYDVQQDDBtUZXN0IENB
+IFdpdGhvdXQga2V5Q2VydFNpZ24wIBcNMjYxMDE5MTEwOTQ0WhgPMjEyNjA5MjUx
+MTA5NDRaMCYxJDAiBgNVBAMMG1Rlc3QgQ0EgV2l0aG91dCBrZXlDZXJ0U2lnbjBZ
+MBMGByqGSM49AgEGCCqGSM49AwEHA0IABOdrWUTEAg+C6YEUS/1wHJf4KP2hBjzL
+86o1IjUUF7bQ5IfovQEVomjAvtGYoemYFy1ZNl3+jD9yEArNoXh42E+jQjBAMA8G
+A1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgeAMB0GA1UdDgQWBBSo5tKM9VRb
+96Vq8T3KeqDyfNzsrTAKBggqhkjOPQQDAgNIADBFAiEA8g3cqvoZH9gwDuYkQUMT
+Bw0GRGatVqNurvinhone5yYCIDDl+LAFyJX96bvu2Xn8D4bmzYi8A/Uxv4mhofRY
+7x9G
+-----END CERTIFICATE-----
+";
+
+    /// Self-signed NIST P-521 CA certificate
+    const P521_CA_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIB6zCCAUygAwIBAgIBBzAKBggqhkjOPQQDBDAYMRYwFAYDVQQDDA1UZXN0IFAt
+NTIxIENBMCAXDTI2MTAxOTExMDk0NFoYDzIxMjYwOTI1MTEwOTQ0WjAYMRYwFAYD
+VQQDDA1UZXN0IFAtNTIxIENBMIGbMBAGByqGSM49AgEGBSuBBAAjA4GGAAQBFvc5
+a5cU8H8tcjgGggJ2Ws1Js0iI2zuB3HLRMe8wc34sj/TMD4NiPlqYGa0cB+gaZVIi
+FTsQ04IMRQDlJLo2EesBWIpvYbn/CLbbwPGKxh4f4oBbO8NWEsBKMMYG0osMeR8K
+lvkmevVB/UqkhEToIHboTvL3jsFsb48B5ATXr+/TBf6jQjBAMA8GA1UdEwEB/wQF
+MAMBAf8wDgYDVR0PAQH/BAQDAgIEMB0GA1UdDgQWBBQAwt3ktn+j0DbFun6JH6in
+jRGPcDAKBggqhkjOPQQDBAOBjAAwgYgCQgD3c07PyEnT3NfTUOVwRt5bK3tNPZ+Z
+iRwzp8LJFjlA0a5Xa7rzoIvqN9Xe+7R2i22Gwj9v6CQnDCFqEO1DU1kQNgJCAT/b
+vvP1edPZUMjVLKwzJrHn9ICJQpo/wTSncm9Y8LEVwNH1MqLHsWWvIII8H97jZX68
+FpfRHeU8bzVUmoHwJjiy
+-----END CERTIFICATE-----
+";
+
+    /// Self-signed CA certificate for the key which signed
+    /// `CONSTRAINED_CA_ATTESTATION_CERTIFICATE`
+    const UNCONSTRAINED_CA_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBbzCCARWgAwIBAgIBCDAKBggqhkjOPQQDAjAeMRwwGgYDVQQDDBNUZXN0IENv
+bnN0cmFpbmVkIENBMCAXDTI2MTAxOTExNTMzNloYDzIxMjYwOTI1MTE1MzM2WjAe
+MRwwGgYDVQQDDBNUZXN0IENvbnN0cmFpbmVkIENBMFkwEwYHKoZIzj0CAQYIKoZI
+zj0DAQcDQgAEW3rQTAf+bvhkMyM3DJqeWyGJscyUvJ48keag8MRqExumlLaPC8o/
+HfE0UWhdH8PNd2kj8QMtLzTWSj/9awr4BKNCMEAwDwYDVR0TAQH/BAUwAwEB/zAO
+BgNVHQ8BAf8EBAMCAgQwHQYDVR0OBBYEFOpBTkaUcCmLrzLkJBjkXdJ7Jdd0MAoG
+CCqGSM49BAMCA0gAMEUCIBxE5kgIojcB/DdCC3AFr3z/lIClo/zOoiIk4eRDE+Vp
+AiEAh2gJDqCZI7P3cuAHTyMBPnqQAWwXbSW3l6HWWQK3l+w=
+-----END CERTIFICATE-----
+";
+
+    /// Self-signed CA certificate for the same key and subject as
+    /// `UNCONSTRAINED_CA_CERTIFICATE`, with a critical nameConstraints extension
+    const NAME_CONSTRAINED_CA_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBjzCCATSgAwIBAgIBCDAKBggqhkjOPQQDAjAeMRwwGgYDVQQDDBNUZXN0IENv
+bnN0cmFpbmVkIENBMCAXDTI2MTAxOTExNTMzNloYDzIxMjYwOTI1MTE1MzM2WjAe
+MRwwGgYDVQQDDBNUZXN0IENvbnN0cmFpbmVkIENBMFkwEwYHKoZIzj0CAQYIKoZI
+zj0DAQcDQgAEW3rQTAf+bvhkMyM3DJqeWyGJscyUvJ48keag8MRqExumlLaPC8o/
+HfE0UWhdH8PNd2kj8QMtLzTWSj/9awr4BKNhMF8wDwYDVR0TAQH/BAUwAwEB/zAO
+BgNVHQ8BAf8EBAMCAgQwHQYDVR0OBBYEFOpBTkaUcCmLrzLkJBjkXdJ7Jdd0MB0G
+A1UdHgEB/wQTMBGgDzANggtleGFtcGxlLmNvbTAKBggqhkjOPQQDAgNJADBGAiEA
+ynedExztkZzWvyFdJAktThXf2/YOsev1h9LwVlEzAiUCIQDoMFu7dFOqzaTu3IsJ
+kvlUDWc1V7hm9gUCqHjWWuP/1w==
+-----END CERTIFICATE-----
+";
+
+    /// Attestation certificate (with the same attributes as
+    /// `ATTESTATION_CERTIFICATE`) signed by the key of the CA certificates above
+    const CONSTRAINED_CA_ATTESTATION_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIICITCCAcigAwIBAgIBCTAKBggqhkjOPQQDAjAeMRwwGgYDVQQDDBNUZXN0IENv
+bnN0cmFpbmVkIENBMCAXDTI2MTAxOTExNTMzNloYDzIxMjYwOTI1MTE1MzM2WjAo
+MSYwJAYDVQQDDB1ZdWJpSFNNIEF0dGVzdGF0aW9uIGlkOjB4MDA2NDBZMBMGByqG
+SM49AgEGCCqGSM49AwEHA0IABJSAqaHb1y5pGshVA+50bvnyR/oi9u9Nvu1KzOLY
++6ZOMb0gy77LFFFNGvOuf8wJWNlTmwRQ0ky2aFKmVJSpAdWjgeowgecwEwYKKwYB
+BAGCxAoEAQQFBAMCAQMwFAYKKwYBBAGCxAoEAgQGAgQAvGFOMBIGCisGAQQBgsQK
+BAMEBAMCAAEwEwYKKwYBBAGCxAoEBAQFAwMAAAEwGQYKKwYBBAGCxAoEBQQLAwkA
+AAAAAAAAAIAwEQYKKwYBBAGCxAoEBgQDAgFkMCMGCisGAQQBgsQKBAkEFQwTeXVi
+aWhzbS5ycyB0ZXN0IGtleTAdBgNVHQ4EFgQUali1jCXo0mN+pK+GalaucR9QOwkw
+HwYDVR0jBBgwFoAU6kFORpRwKYuvMuQkGORd0nsl13QwCgYIKoZIzj0EAwIDRwAw
+RAIgdWKUOn0Bo6Hla97wl/Rdbc0EozrDhmDoxVjKYNSBQ/gCIAtYZCrTMto1Ha98
+Ow+mR3Pk9PyqVY6d5/MXnyfnjgS/
+-----END CERTIFICATE-----
+";
+
+    /// Public key of the attested key
+    const ATTESTED_KEY: &str = concat!(
+        "5fb3e798720260491aeeb623371f61c3f63a8654033609ce8c82b2e5d8adeaf6",
+        "0907103d2e3bc7d902451b4cae66b71ed34d403d1061f473aa28ee6266798249",
+    );
+
+    /// 2027-01-01 00:00:00 UTC, when all of the test certificates are valid
+    fn verification_time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_798_761_600)
+    }
+
+    fn attestation() -> Attestation {
+        parse_attestation(ATTESTATION_CERTIFICATE)
+    }
+
+    fn parse_attestation(pem: &str) -> Attestation {
+        let certificate = Certificate::from_pem(pem).unwrap();
+        Attestation::parse(&AttestationCertificate(certificate.into_der())).unwrap()
+    }
+
+    fn certificate(pem: &str) -> Certificate {
+        Certificate::from_pem(pem).unwrap()
+    }
+
+    #[test]
+    fn parse_test() {
+        let attestation = attestation();
+
+        assert_eq!(attestation.public_key.algorithm, AsymmetricAlgorithm::EC_P256);
+        assert_eq!(
+            attestation.public_key.bytes,
+            decode_hex(ATTESTED_KEY).unwrap()
+        );
+        assert_eq!(
+            (
+                attestation.major_version,
+                attestation.minor_version,
+                attestation.build_version
+            ),
+            (2, 1, 3)
+        );
+        assert_eq!(attestation.serial_number, 12_345_678);
+        assert_eq!(attestation.origin, ObjectOrigin::Generated);
+        assert_eq!(attestation.domains, Domain::DOM1);
+        assert_eq!(attestation.capabilities, Capability::ASYMMETRIC_SIGN_ECDSA);
+        assert_eq!(attestation.object_id, 0x0064);
+        assert_eq!(attestation.label.to_string().unwrap(), "yubihsm.rs test key");
+        assert!(attestation.is_generated_non_exportable());
+    }
+
+    #[test]
+    fn verify_test() {
+        let attestation = attestation();
+        let device = certificate(DEVICE_CERTIFICATE);
+        let root = certificate(ROOT_CERTIFICATE);
+
+        for chain in &[vec![], vec![root.clone()], vec![root.clone(), device.clone()]] {
+            assert_eq!(
+                attestation
+                    .verify_at(chain, verification_time())
+                    .err()
+                    .unwrap()
+                    .kind(),
+                X509ErrorKind::VerificationFailed
+            );
+        }
+
+        attestation
+            .verify_at(&[device.clone(), root], verification_time())
+            .unwrap();
+        attestation.verify_at(&[device], verification_time()).unwrap();
+    }
+
+    #[test]
+    fn verify_validity_test() {
+        let attestation = attestation();
+        let chain = [certificate(DEVICE_CERTIFICATE), certificate(ROOT_CERTIFICATE)];
+
+        // 2026-01-01 00:00:00 UTC and 2127-01-01 00:00:00 UTC
+        for &seconds in &[1_767_225_600, 4_954_435_200] {
+            let time = UNIX_EPOCH + Duration::from_secs(seconds);
+
+            assert_eq!(
+                attestation.verify_at(&chain, time).err().unwrap().kind(),
+                X509ErrorKind::VerificationFailed
+            );
+        }
+    }
+
+    #[test]
+    fn verify_non_ca_issuer_test() {
+        let forged_attestation = parse_attestation(FORGED_ATTESTATION_CERTIFICATE);
+        let rogue = certificate(ROGUE_CERTIFICATE);
+        let root = certificate(ROOT_CERTIFICATE);
+
+        // The forged attestation's signature is valid, but its issuer isn't a CA
+        let subject = ParsedCertificate::parse(forged_attestation.certificate().as_der()).unwrap();
+        let issuer = ParsedCertificate::parse(rogue.as_der()).unwrap();
+        verify_signature(&subject, &issuer).unwrap();
+
+        assert_eq!(
+            forged_attestation
+                .verify_at(&[rogue, root], verification_time())
+                .err()
+                .unwrap()
+                .kind(),
+            X509ErrorKind::VerificationFailed
+        );
+    }
+
+    #[test]
+    fn verify_critical_extension_test() {
+        let attestation = parse_attestation(CONSTRAINED_CA_ATTESTATION_CERTIFICATE);
+
+        attestation
+            .verify_at(
+                &[certificate(UNCONSTRAINED_CA_CERTIFICATE)],
+                verification_time(),
+            )
+            .unwrap();
+
+        // nameConstraints aren't processed, so the certificate must be rejected
+        let constrained = certificate(NAME_CONSTRAINED_CA_CERTIFICATE);
+        let issuer = ParsedCertificate::parse(constrained.as_der()).unwrap();
+        assert_eq!(issuer.extensions.iter().filter(|e| e.critical).count(), 3);
+        verify_signature(&issuer, &issuer).unwrap();
+
+        assert_eq!(
+            attestation
+                .verify_at(&[constrained], verification_time())
+                .err()
+                .unwrap()
+                .kind(),
+            X509ErrorKind::VerificationFailed
+        );
+    }
+
+    #[test]
+    fn check_issuer_test() {
+        for pem in &[DEVICE_CERTIFICATE, ROOT_CERTIFICATE] {
+            let issuer = certificate(pem);
+            check_issuer(&ParsedCertificate::parse(issuer.as_der()).unwrap(), 1).unwrap();
+        }
+
+        for pem in &[
+            ATTESTATION_CERTIFICATE,
+            ROGUE_CERTIFICATE,
+            NO_CERT_SIGN_CA_CERTIFICATE,
+        ] {
+            let issuer = certificate(pem);
+            assert!(check_issuer(&ParsedCertificate::parse(issuer.as_der()).unwrap(), 0).is_err());
+        }
+    }
+
+    #[test]
+    fn verify_p521_issuer_test() {
+        let issuer = certificate(P521_CA_CERTIFICATE);
+        let issuer = ParsedCertificate::parse(issuer.as_der()).unwrap();
+
+        assert_eq!(
+            verify_signature(&issuer, &issuer).err().unwrap().kind(),
+            X509ErrorKind::UnverifiableSignature
+        );
+    }
+
+    #[test]
+    fn parse_trailing_algorithm_parameters_test() {
+        // sha256WithRSAEncryption with NULL parameters
+        let algorithm_id: &[u8] = &[
+            0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05,
+            0x00,
+        ];
+
+        let mut der = certificate(DEVICE_CERTIFICATE).into_der();
+        ParsedCertificate::parse(&der).unwrap();
+
+        // Replace the NULL parameters in both AlgorithmIdentifiers with an
+        // (empty) OCTET STRING
+        let mut replaced = 0;
+
+        for i in 0..der.len() - algorithm_id.len() {
+            if &der[i..i + algorithm_id.len()] == algorithm_id {
+                der[i + algorithm_id.len() - 2] = der::OCTET_STRING;
+                replaced += 1;
+            }
+        }
+
+        assert_eq!(replaced, 2);
+        assert!(ParsedCertificate::parse(&der).is_err());
+    }
+
+    #[test]
+    fn bit_string_test() {
+        // Trailing zero bytes omitted from the encoding
+        let mut decoder = Decoder::new(&[0x03, 0x02, 0x00, 0x01]);
+        assert_eq!(bit_string(&mut decoder, 2).unwrap(), 0x0100);
+
+        let mut decoder = Decoder::new(&[0x03, 0x04, 0x00, 0x00, 0x01, 0x00]);
+        assert!(bit_string(&mut decoder, 2).is_err());
+    }
+}
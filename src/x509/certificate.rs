@@ -0,0 +1,306 @@
+//! X.509 certificates signed by keys stored in the `YubiHSM2`
+
+use rand::{OsRng, RngCore};
+#[cfg(feature = "attestation")]
+use serde::de::{self, Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "attestation")]
+use super::parser::ParsedCertificate;
+use super::signature::{self, SignatureAlgorithm};
+use super::{extension, signed, subject_alt_name, time, Name, X509Error};
+use commands::get_pubkey::{get_pubkey, PublicKey};
+use commands::put_opaque::put_opaque;
+use encoding::{der, oid, pem};
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use mockhsm::MockConnector;
+use {
+    Capability, Connector, Domain, ObjectId, ObjectLabel, OpaqueAlgorithm, Session, SessionError,
+};
+
+/// Default validity period of certificates (one year)
+pub const DEFAULT_VALIDITY: Duration = Duration::from_secs(365 * 86_400);
+
+/// Size of randomly generated serial numbers
+const SERIAL_NUMBER_SIZE: usize = 16;
+
+/// Size of subject and authority key identifiers
+const KEY_IDENTIFIER_SIZE: usize = 20;
+
+/// keyUsage bits for CA certificates: keyCertSign and cRLSign
+const CA_KEY_USAGE: u8 = 0x06;
+
+/// keyUsage bits for other certificates: digitalSignature
+const END_ENTITY_KEY_USAGE: u8 = 0x80;
+
+/// Builder for X.509 v3 certificates
+#[derive(Clone, Debug)]
+pub struct CertificateBuilder {
+    /// Serial number (big endian)
+    pub serial_number: Vec<u8>,
+
+    /// Issuer name (if `None`, the subject name, i.e. a self-signed certificate)
+    pub issuer: Option<Name>,
+
+    /// Subject name
+    pub subject: Name,
+
+    /// Public key of the subject (if `None`, the signing key's public key,
+    /// i.e. a self-signed certificate)
+    pub subject_public_key: Option<PublicKey>,
+
+    /// Beginning of the validity period
+    pub not_before: SystemTime,
+
+    /// End of the validity period
+    pub not_after: SystemTime,
+
+    /// Is the subject a CA? CA certificates may sign certificates and CRLs,
+    /// while other certificates may sign e.g. TLS handshakes.
+    pub ca: bool,
+
+    /// DNS names of the subject (subject alternative names)
+    pub dns_names: Vec<String>,
+}
+
+impl CertificateBuilder {
+    /// Create a builder for a certificate with the given subject, a random
+    /// serial number, and a validity period of `DEFAULT_VALIDITY` from now
+    pub fn new(subject: Name) -> Result<Self, X509Error> {
+        let mut serial_number = vec![0u8; SERIAL_NUMBER_SIZE];
+
+        OsRng::new()
+            .and_then(|mut rng| rng.try_fill_bytes(&mut serial_number))
+            .map_err(|e| x509_err!(RngError, "couldn't generate serial number: {}", e))?;
+
+        // Serial numbers must be positive
+        serial_number[0] &= 0x7f;
+
+        let not_before = SystemTime::now();
+
+        Ok(Self {
+            serial_number,
+            issuer: None,
+            subject,
+            subject_public_key: None,
+            not_before,
+            not_after: not_before + DEFAULT_VALIDITY,
+            ca: false,
+            dns_names: vec![],
+        })
+    }
+
+    /// Sign the certificate with the given key ID (the issuer's key)
+    #[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+    pub fn sign<C: Connector>(
+        &self,
+        session: &mut Session<C>,
+        key_id: ObjectId,
+    ) -> Result<Certificate, X509Error> {
+        let signing_key = get_pubkey(session, key_id)?;
+        let algorithm = SignatureAlgorithm::for_key(signing_key.algorithm)?;
+        let tbs_certificate = self.tbs_certificate(&signing_key, algorithm)?;
+        let signature = signature::sign(session, key_id, algorithm, &tbs_certificate)?;
+
+        Ok(Certificate(signed(&tbs_certificate, algorithm, &signature)))
+    }
+
+    /// Sign the certificate with the given key ID (the issuer's key)
+    // NOTE: this version is enabled when we compile with MockHSM support
+    #[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+    pub fn sign(
+        &self,
+        session: &mut Session<MockConnector>,
+        key_id: ObjectId,
+    ) -> Result<Certificate, X509Error> {
+        let signing_key = get_pubkey(session, key_id)?;
+        let algorithm = SignatureAlgorithm::for_key(signing_key.algorithm)?;
+        let tbs_certificate = self.tbs_certificate(&signing_key, algorithm)?;
+        let signature = signature::sign(session, key_id, algorithm, &tbs_certificate)?;
+
+        Ok(Certificate(signed(&tbs_certificate, algorithm, &signature)))
+    }
+
+    /// Encode the DER `TBSCertificate` (i.e. the certificate without its
+    /// signature)
+    fn tbs_certificate(
+        &self,
+        signing_key: &PublicKey,
+        algorithm: SignatureAlgorithm,
+    ) -> Result<Vec<u8>, X509Error> {
+        if self.not_after < self.not_before {
+            x509_fail!(InvalidField, "certificate expires before it becomes valid");
+        }
+
+        let subject = self.subject.to_der()?;
+        let issuer = match self.issuer {
+            Some(ref issuer) => issuer.to_der()?,
+            None => subject.clone(),
+        };
+
+        let subject_public_key = self
+            .subject_public_key
+            .as_ref()
+            .unwrap_or(signing_key)
+            .to_spki_der()?;
+
+        let subject_key_id = key_identifier(&subject_public_key)?;
+        let authority_key_id = key_identifier(&signing_key.to_spki_der()?)?;
+
+        let key_usage = if self.ca {
+            CA_KEY_USAGE
+        } else {
+            END_ENTITY_KEY_USAGE
+        };
+
+        // BasicConstraints ::= SEQUENCE { cA BOOLEAN DEFAULT FALSE, ... }
+        let basic_constraints = if self.ca {
+            der::sequence(&[&der::boolean(true)])
+        } else {
+            der::sequence(&[])
+        };
+
+        // KeyUsage is a named BIT STRING, so trailing zero bits are unused
+        let key_usage = der::encode(
+            der::BIT_STRING,
+            &[key_usage.trailing_zeros() as u8, key_usage],
+        );
+
+        // AuthorityKeyIdentifier ::= SEQUENCE { keyIdentifier [0] IMPLICIT ..., ... }
+        let authority_key_id =
+            der::sequence(&[&der::encode(der::CONTEXT_SPECIFIC, &authority_key_id)]);
+
+        let mut extensions = vec![
+            extension(oid::BASIC_CONSTRAINTS, true, &basic_constraints),
+            extension(oid::KEY_USAGE, true, &key_usage),
+            extension(
+                oid::SUBJECT_KEY_IDENTIFIER,
+                false,
+                &der::encode(der::OCTET_STRING, &subject_key_id),
+            ),
+            extension(oid::AUTHORITY_KEY_IDENTIFIER, false, &authority_key_id),
+        ];
+
+        if !self.dns_names.is_empty() {
+            extensions.push(subject_alt_name(&self.dns_names)?);
+        }
+
+        let extensions: Vec<&[u8]> = extensions.iter().map(Vec::as_slice).collect();
+
+        Ok(der::sequence(&[
+            // version [0] EXPLICIT: v3
+            &der::encode(der::CONTEXT_SPECIFIC | der::CONSTRUCTED, &der::integer(&[2])),
+            &der::integer(&self.serial_number),
+            &algorithm.to_der(),
+            &issuer,
+            &der::sequence(&[
+                &time::encode(self.not_before)?,
+                &time::encode(self.not_after)?,
+            ]),
+            &subject,
+            &subject_public_key,
+            // extensions [3] EXPLICIT
+            &der::encode(
+                der::CONTEXT_SPECIFIC | der::CONSTRUCTED | 3,
+                &der::sequence(&extensions),
+            ),
+        ]))
+    }
+}
+
+/// Compute the key identifier for a DER-encoded `SubjectPublicKeyInfo`: the
+/// leftmost 160 bits of the SHA-256 hash of its `subjectPublicKey` bits
+/// (RFC 7093 method 1)
+fn key_identifier(spki: &[u8]) -> Result<Vec<u8>, X509Error> {
+    let mut spki = der::Decoder::new(spki).sequence()?;
+    spki.sequence()?;
+    let subject_public_key = spki.bit_string()?;
+    spki.finish()?;
+
+    Ok(Sha256::digest(subject_public_key)[..KEY_IDENTIFIER_SIZE].to_vec())
+}
+
+/// DER-encoded X.509 certificate
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Certificate(Vec<u8>);
+
+impl Certificate {
+    /// Parse a DER-encoded certificate
+    #[cfg(feature = "attestation")]
+    pub fn from_der(bytes: &[u8]) -> Result<Self, X509Error> {
+        ParsedCertificate::parse(bytes)?;
+        Ok(Certificate(bytes.to_vec()))
+    }
+
+    /// Parse a PEM-encoded certificate ("CERTIFICATE")
+    #[cfg(feature = "attestation")]
+    pub fn from_pem(pem: &str) -> Result<Self, X509Error> {
+        let (label, bytes) = pem::decode(pem)?;
+
+        if label != "CERTIFICATE" {
+            x509_fail!(ParseError, "unexpected PEM label: {}", label);
+        }
+
+        Self::from_der(&bytes)
+    }
+
+    /// Decode the subject public key of this certificate
+    #[cfg(feature = "attestation")]
+    pub fn public_key(&self) -> Result<PublicKey, X509Error> {
+        let certificate = ParsedCertificate::parse(&self.0)?;
+        Ok(PublicKey::from_spki_der(certificate.subject_public_key_info)?)
+    }
+
+    /// Borrow the DER encoding of this certificate
+    pub fn as_der(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Unwrap the DER encoding of this certificate
+    pub fn into_der(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Encode this certificate as PEM ("CERTIFICATE")
+    pub fn to_pem(&self) -> String {
+        pem::encode("CERTIFICATE", &self.0)
+    }
+
+    /// Store this certificate in the `YubiHSM2` as an `OPAQUE_X509_CERT`
+    /// object (with `put_opaque`)
+    pub fn put<C: Connector>(
+        &self,
+        session: &mut Session<C>,
+        object_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+    ) -> Result<ObjectId, SessionError> {
+        put_opaque(
+            session,
+            object_id,
+            label,
+            domains,
+            capabilities,
+            OpaqueAlgorithm::OPAQUE_X509_CERT,
+            self.0.as_slice(),
+        )
+    }
+}
+
+impl AsRef<[u8]> for Certificate {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Certificates are deserialized from their DER encoding, which is checked
+/// as with `Certificate::from_der`
+#[cfg(feature = "attestation")]
+impl<'de> Deserialize<'de> for Certificate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let der = Vec::<u8>::deserialize(deserializer)?;
+        Certificate::from_der(&der).map_err(|e| de::Error::custom(format!("{}", e)))
+    }
+}
@@ -0,0 +1,94 @@
+//! Error types for X.509 certificates and certificate requests
+
+use encoding::{EncodingError, EncodingErrorKind};
+use error::Error;
+use session::{SessionError, SessionErrorKind};
+
+/// X.509 errors
+pub type X509Error = Error<X509ErrorKind>;
+
+/// X.509 error kinds
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+pub enum X509ErrorKind {
+    /// The signing key's algorithm can't be used to sign certificates
+    #[fail(display = "unsupported algorithm")]
+    UnsupportedAlgorithm,
+
+    /// A field can't be encoded (e.g. a validity time before 1970)
+    #[fail(display = "invalid field")]
+    InvalidField,
+
+    /// A certificate is malformed (e.g. it's missing an expected extension)
+    #[fail(display = "parse error")]
+    ParseError,
+
+    /// A certificate's signature or public key couldn't be verified
+    #[fail(display = "verification failed")]
+    VerificationFailed,
+
+    /// A signature can't be verified because its algorithm is unsupported
+    /// (e.g. ECDSA with NIST P-521, which *ring* doesn't implement)
+    #[fail(display = "unverifiable signature")]
+    UnverifiableSignature,
+
+    /// Provisioning a key failed, and so did deleting the key afterward, so
+    /// it was left in the `YubiHSM2`
+    #[fail(display = "rollback failed")]
+    RollbackFailed,
+
+    /// The operating system's random number generator failed
+    #[fail(display = "RNG error")]
+    RngError,
+
+    /// Error encoding or decoding a public key or certificate
+    #[fail(display = "encoding error")]
+    EncodingError(EncodingErrorKind),
+
+    /// Error getting a public key or computing a signature with the `YubiHSM2`
+    #[fail(display = "session error")]
+    SessionError(SessionErrorKind),
+}
+
+/// Create a new X.509 error with a formatted message
+macro_rules! x509_err {
+    ($kind:ident, $msg:expr) => {
+        ::x509::X509Error::new(
+            ::x509::X509ErrorKind::$kind,
+            Some($msg.to_owned())
+        )
+    };
+    ($kind:ident, $fmt:expr, $($arg:tt)+) => {
+        ::x509::X509Error::new(
+            ::x509::X509ErrorKind::$kind,
+            Some(format!($fmt, $($arg)+))
+        )
+    };
+}
+
+/// Create and return an X.509 error with a formatted message
+macro_rules! x509_fail {
+    ($kind:ident, $msg:expr) => {
+        return Err(x509_err!($kind, $msg).into());
+    };
+    ($kind:ident, $fmt:expr, $($arg:tt)+) => {
+        return Err(x509_err!($kind, $fmt, $($arg)+).into());
+    };
+}
+
+impl From<EncodingError> for X509Error {
+    fn from(err: EncodingError) -> Self {
+        X509Error::new(
+            X509ErrorKind::EncodingError(err.kind()),
+            Some(err.to_string()),
+        )
+    }
+}
+
+impl From<SessionError> for X509Error {
+    fn from(err: SessionError) -> Self {
+        X509Error::new(
+            X509ErrorKind::SessionError(err.kind()),
+            Some(err.to_string()),
+        )
+    }
+}
@@ -0,0 +1,87 @@
+//! Build X.509 certificates (RFC 5280) and certificate signing requests
+//! (PKCS#10, RFC 2986) around the public key of a key stored in the
+//! `YubiHSM2`, signed by a key stored in the `YubiHSM2`, e.g. for an internal
+//! CA or device certificates:
+//!
+//! - `CertificateBuilder` signs a `Certificate`, which is self-signed unless
+//!   an issuer name and subject public key are given
+//! - `CertificateRequestBuilder` signs a `CertificateRequest` with the key
+//!   it's requesting a certificate for
+//!
+//! Both are available as DER or PEM, and certificates can be stored back into
+//! the `YubiHSM2` as `OPAQUE_X509_CERT` objects with `Certificate::put`.
+//!
+//! With the `attestation` cargo feature, `Attestation` parses the attestation
+//! certificates returned by `attest_asymmetric` and verifies their signature
+//! chains, and `KeyProvisioner` generates keys along with verified
+//! attestations.
+//!
+//! Signatures use ECDSA (with SHA-256, SHA-384, or SHA-512 depending on the
+//! size of the key's curve), Ed25519, or RSASSA-PKCS1-v1_5 with SHA-256
+//! (requires the `rsa` cargo feature).
+
+#[macro_use]
+mod error;
+
+#[cfg(feature = "attestation")]
+mod attestation;
+mod certificate;
+mod name;
+#[cfg(feature = "attestation")]
+mod parser;
+#[cfg(feature = "attestation")]
+mod provision;
+mod request;
+mod signature;
+mod time;
+
+#[cfg(feature = "attestation")]
+pub use self::attestation::Attestation;
+pub use self::certificate::{Certificate, CertificateBuilder, DEFAULT_VALIDITY};
+pub use self::error::{X509Error, X509ErrorKind};
+pub use self::name::Name;
+#[cfg(feature = "attestation")]
+pub use self::provision::{KeyProvisioner, ProvisionedKey};
+pub use self::request::{CertificateRequest, CertificateRequestBuilder};
+
+use self::signature::SignatureAlgorithm;
+use encoding::{der, oid};
+
+/// Encode an `Extension` (RFC 5280) with the given DER-encoded value
+pub(crate) fn extension(id: &[u32], critical: bool, value: &[u8]) -> Vec<u8> {
+    if critical {
+        der::sequence(&[
+            &der::object_identifier(id),
+            &der::boolean(true),
+            &der::encode(der::OCTET_STRING, value),
+        ])
+    } else {
+        der::sequence(&[
+            &der::object_identifier(id),
+            &der::encode(der::OCTET_STRING, value),
+        ])
+    }
+}
+
+/// Encode a subject alternative name extension for the given DNS names
+fn subject_alt_name(dns_names: &[String]) -> Result<Vec<u8>, X509Error> {
+    // GeneralName ::= CHOICE { ..., dNSName [2] IA5String, ... }
+    let mut names = vec![];
+
+    for name in dns_names {
+        if name.is_empty() || !name.is_ascii() {
+            x509_fail!(InvalidField, "DNS names must be non-empty IA5Strings: {:?}", name);
+        }
+
+        names.push(der::encode(der::CONTEXT_SPECIFIC | 2, name.as_bytes()));
+    }
+
+    let names: Vec<&[u8]> = names.iter().map(Vec::as_slice).collect();
+    Ok(extension(oid::SUBJECT_ALT_NAME, false, &der::sequence(&names)))
+}
+
+/// Encode a signed certificate or request: the signed structure, followed by
+/// the signature algorithm and signature
+fn signed(data: &[u8], algorithm: SignatureAlgorithm, signature: &[u8]) -> Vec<u8> {
+    der::sequence(&[data, &algorithm.to_der(), &der::bit_string(signature)])
+}
@@ -0,0 +1,110 @@
+//! Distinguished names of certificate subjects and issuers
+
+use super::X509Error;
+use encoding::{der, oid};
+
+/// X.501 distinguished name. Attributes are encoded in the order of this
+/// struct's fields (e.g. `C=US, O=Example, CN=example.com`), omitting those
+/// which are `None`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Name {
+    /// Country name (C): a two-letter ISO 3166 country code
+    pub country: Option<String>,
+
+    /// State or province name (ST)
+    pub state_or_province: Option<String>,
+
+    /// Locality name (L)
+    pub locality: Option<String>,
+
+    /// Organization name (O)
+    pub organization: Option<String>,
+
+    /// Organizational unit name (OU)
+    pub organizational_unit: Option<String>,
+
+    /// Common name (CN)
+    pub common_name: Option<String>,
+}
+
+impl Name {
+    /// Create a name with only a common name (CN)
+    pub fn from_common_name(common_name: &str) -> Self {
+        Self {
+            common_name: Some(common_name.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Encode this name as a DER `Name` (RFC 5280), with one attribute per
+    /// relative distinguished name
+    pub(super) fn to_der(&self) -> Result<Vec<u8>, X509Error> {
+        let mut rdns = vec![];
+
+        if let Some(ref country) = self.country {
+            if country.len() != 2 || !country.bytes().all(|b| b.is_ascii_alphabetic()) {
+                x509_fail!(InvalidField, "invalid country code: {:?}", country);
+            }
+
+            rdns.push(rdn(
+                oid::COUNTRY_NAME,
+                &der::encode(der::PRINTABLE_STRING, country.as_bytes()),
+            ));
+        }
+
+        let attributes = [
+            (oid::STATE_OR_PROVINCE_NAME, &self.state_or_province),
+            (oid::LOCALITY_NAME, &self.locality),
+            (oid::ORGANIZATION_NAME, &self.organization),
+            (oid::ORGANIZATIONAL_UNIT_NAME, &self.organizational_unit),
+            (oid::COMMON_NAME, &self.common_name),
+        ];
+
+        for &(attribute, value) in &attributes {
+            if let Some(ref value) = *value {
+                rdns.push(rdn(
+                    attribute,
+                    &der::encode(der::UTF8_STRING, value.as_bytes()),
+                ));
+            }
+        }
+
+        let rdns: Vec<&[u8]> = rdns.iter().map(Vec::as_slice).collect();
+        Ok(der::sequence(&rdns))
+    }
+}
+
+/// Encode a relative distinguished name with a single attribute
+fn rdn(attribute: &[u32], value: &[u8]) -> Vec<u8> {
+    der::set_of(&[&der::sequence(&[&der::object_identifier(attribute), value])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Name;
+
+    #[test]
+    fn name_to_der() {
+        let name = Name {
+            country: Some("US".to_owned()),
+            common_name: Some("test".to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            name.to_der().unwrap(),
+            vec![
+                0x30, 0x1c, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55,
+                0x53, 0x31, 0x0d, 0x30, 0x0b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x04, 0x74, 0x65,
+                0x73, 0x74,
+            ]
+        );
+
+        let invalid = Name {
+            country: Some("USA".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(invalid.to_der().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Parser for the parts of X.509 certificates needed to inspect and verify
+//! them (e.g. attestation certificates)
+
+use std::time::SystemTime;
+
+use super::{time, X509Error};
+use encoding::der::{self, Decoder};
+
+/// Borrowed view of the fields of a DER-encoded certificate
+pub(super) struct ParsedCertificate<'a> {
+    /// Entire encoding of the `TBSCertificate` (i.e. the signed data)
+    pub tbs_certificate: &'a [u8],
+
+    /// Entire encoding of the issuer `Name`
+    pub issuer: &'a [u8],
+
+    /// Start of the validity period
+    pub not_before: SystemTime,
+
+    /// End of the validity period
+    pub not_after: SystemTime,
+
+    /// Entire encoding of the subject `Name`
+    pub subject: &'a [u8],
+
+    /// Entire encoding of the `SubjectPublicKeyInfo`
+    pub subject_public_key_info: &'a [u8],
+
+    /// Extensions (empty for v1 certificates)
+    pub extensions: Vec<Extension<'a>>,
+
+    /// Encoded OID of the signature algorithm
+    pub signature_algorithm: &'a [u8],
+
+    /// Signature over the `TBSCertificate`
+    pub signature: &'a [u8],
+}
+
+/// Borrowed view of a certificate extension
+pub(super) struct Extension<'a> {
+    /// Encoded OID of the extension
+    pub id: &'a [u8],
+
+    /// Must certificate users which don't process the extension reject the
+    /// certificate?
+    pub critical: bool,
+
+    /// DER-encoded value of the extension
+    pub value: &'a [u8],
+}
+
+impl<'a> ParsedCertificate<'a> {
+    /// Parse a DER-encoded `Certificate` (RFC 5280)
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, X509Error> {
+        let mut decoder = Decoder::new(bytes);
+        let mut certificate = decoder.sequence()?;
+        decoder.finish()?;
+
+        let tbs_certificate = certificate.encoded(der::SEQUENCE)?;
+        let signature_algorithm_id = certificate.encoded(der::SEQUENCE)?;
+        let signature = certificate.bit_string()?;
+        certificate.finish()?;
+
+        let mut algorithm_id = Decoder::new(signature_algorithm_id).sequence()?;
+        let signature_algorithm = algorithm_id.object_identifier()?;
+        // parameters: NULL for RSA, absent for ECDSA and Ed25519
+        algorithm_id.optional(der::NULL)?;
+        algorithm_id.finish()?;
+
+        let mut decoder = Decoder::new(tbs_certificate);
+        let mut tbs = decoder.sequence()?;
+
+        // version [0] EXPLICIT, defaulting to v1
+        tbs.optional(der::CONTEXT_SPECIFIC | der::CONSTRUCTED)?;
+        tbs.decode(der::INTEGER)?;
+
+        if tbs.encoded(der::SEQUENCE)? != signature_algorithm_id {
+            x509_fail!(ParseError, "certificate signature algorithms don't match");
+        }
+
+        let issuer = tbs.encoded(der::SEQUENCE)?;
+
+        let mut validity = tbs.sequence()?;
+        let not_before = time::decode(&mut validity)?;
+        let not_after = time::decode(&mut validity)?;
+        validity.finish()?;
+
+        let subject = tbs.encoded(der::SEQUENCE)?;
+        let subject_public_key_info = tbs.encoded(der::SEQUENCE)?;
+
+        // issuerUniqueID [1] and subjectUniqueID [2] (both IMPLICIT)
+        tbs.optional(der::CONTEXT_SPECIFIC | 1)?;
+        tbs.optional(der::CONTEXT_SPECIFIC | 2)?;
+
+        let mut extensions = vec![];
+
+        // extensions [3] EXPLICIT
+        if let Some(contents) = tbs.optional(der::CONTEXT_SPECIFIC | der::CONSTRUCTED | 3)? {
+            let mut decoder = Decoder::new(contents);
+            let mut sequence = decoder.sequence()?;
+            decoder.finish()?;
+
+            while !sequence.is_empty() {
+                let mut extension = sequence.sequence()?;
+                let id = extension.object_identifier()?;
+                // critical BOOLEAN DEFAULT FALSE
+                let critical = match extension.optional(der::BOOLEAN)? {
+                    None => false,
+                    Some(value) if value == &[0x00][..] => false,
+                    Some(value) if value == &[0xff][..] => true,
+                    Some(_) => x509_fail!(ParseError, "malformed extension critical flag"),
+                };
+
+                let value = extension.decode(der::OCTET_STRING)?;
+                extension.finish()?;
+
+                extensions.push(Extension {
+                    id,
+                    critical,
+                    value,
+                });
+            }
+        }
+
+        tbs.finish()?;
+
+        Ok(ParsedCertificate {
+            tbs_certificate,
+            issuer,
+            not_before,
+            not_after,
+            subject,
+            subject_public_key_info,
+            extensions,
+            signature_algorithm,
+            signature,
+        })
+    }
+
+    /// Find the value of the extension with the given OID
+    pub fn extension(&self, id: &[u32]) -> Option<&'a [u8]> {
+        self.extensions
+            .iter()
+            .find(|extension| der::object_identifier_eq(extension.id, id))
+            .map(|extension| extension.value)
+    }
+}
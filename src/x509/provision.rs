@@ -0,0 +1,189 @@
+//! Generate asymmetric keys in the `YubiHSM2` along with a verified
+//! attestation that they were generated there
+
+use super::{Attestation, Certificate, X509Error};
+use commands::attest_asymmetric::attest_asymmetric;
+use commands::delete_object::delete_object;
+use commands::generate_asymmetric_key::generate_asymmetric_key;
+use commands::get_object_info::get_object_info;
+use commands::get_opaque::get_opaque;
+use commands::get_pubkey::PublicKey;
+use {
+    AsymmetricAlgorithm, Capability, Connector, Domain, ObjectId, ObjectInfo, ObjectLabel,
+    ObjectOrigin, ObjectType, Session,
+};
+
+/// Object ID of the certificate of the device attestation key
+const DEVICE_ATTESTATION_CERTIFICATE_ID: ObjectId = 0;
+
+/// Provisions an asymmetric key: generates it, attests it, verifies the
+/// attestation, and optionally stores the attestation certificate in the
+/// `YubiHSM2`. If any step fails, the key is deleted again.
+#[derive(Clone, Debug)]
+pub struct KeyProvisioner {
+    /// ID of the key to generate
+    pub key_id: ObjectId,
+
+    /// Label of the key
+    pub label: ObjectLabel,
+
+    /// Domains of the key
+    pub domains: Domain,
+
+    /// Capabilities of the key
+    pub capabilities: Capability,
+
+    /// Algorithm of the key
+    pub algorithm: AsymmetricAlgorithm,
+
+    /// Key to sign the attestation with (if `None`, the device attestation
+    /// key). Its certificate is read from the opaque object with the same ID
+    /// (0 for the device attestation key).
+    pub attestation_key_id: Option<ObjectId>,
+
+    /// Trusted certificates to verify the attestation key's certificate
+    /// against: its issuer first, ending with a root CA certificate (e.g.
+    /// Yubico's). A self-signed attestation key certificate may be given on
+    /// its own.
+    pub issuer_chain: Vec<Certificate>,
+
+    /// ID to store the attestation certificate under as an `OPAQUE_X509_CERT`
+    /// object (with the key's label and domains), if any
+    pub certificate_id: Option<ObjectId>,
+}
+
+impl KeyProvisioner {
+    /// Create a provisioner for a key with the given parameters (as with
+    /// `generate_asymmetric_key`), attested by the device attestation key.
+    /// `issuer_chain` must be set before provisioning.
+    pub fn new(
+        key_id: ObjectId,
+        label: ObjectLabel,
+        domains: Domain,
+        capabilities: Capability,
+        algorithm: AsymmetricAlgorithm,
+    ) -> Self {
+        Self {
+            key_id,
+            label,
+            domains,
+            capabilities,
+            algorithm,
+            attestation_key_id: None,
+            issuer_chain: vec![],
+            certificate_id: None,
+        }
+    }
+
+    /// Generate, attest, and verify the key. If anything fails after the key
+    /// is generated, it's deleted before returning the error. If deleting it
+    /// fails too, the error is a `RollbackFailed` error, and the key is left
+    /// in the `YubiHSM2`.
+    pub fn provision<C: Connector>(
+        &self,
+        session: &mut Session<C>,
+    ) -> Result<ProvisionedKey, X509Error> {
+        if self.issuer_chain.is_empty() {
+            x509_fail!(
+                VerificationFailed,
+                "no trusted certificates to verify the attestation with"
+            );
+        }
+
+        generate_asymmetric_key(
+            session,
+            self.key_id,
+            self.label.clone(),
+            self.domains,
+            self.capabilities,
+            self.algorithm,
+        )?;
+
+        self.attest(session).or_else(|err| {
+            if let Err(e) = delete_object(session, self.key_id, ObjectType::AsymmetricKey) {
+                x509_fail!(
+                    RollbackFailed,
+                    "provisioning key 0x{:04x} failed ({}), and it couldn't be deleted: {}",
+                    self.key_id,
+                    err,
+                    e
+                );
+            }
+
+            Err(err)
+        })
+    }
+
+    /// Attest the newly generated key and verify the attestation
+    fn attest<C: Connector>(&self, session: &mut Session<C>) -> Result<ProvisionedKey, X509Error> {
+        let attestation_certificate =
+            attest_asymmetric(session, self.key_id, self.attestation_key_id)?;
+        let attestation = Attestation::parse(&attestation_certificate)?;
+
+        let attestation_key_certificate_id = self
+            .attestation_key_id
+            .unwrap_or(DEVICE_ATTESTATION_CERTIFICATE_ID);
+
+        let attestation_key_certificate =
+            Certificate::from_der(&get_opaque(session, attestation_key_certificate_id)?)?;
+
+        let mut attestation_chain = vec![attestation.certificate().clone()];
+
+        if self.issuer_chain[0] != attestation_key_certificate {
+            attestation_chain.push(attestation_key_certificate);
+        }
+
+        attestation_chain.extend_from_slice(&self.issuer_chain);
+        attestation.verify(&attestation_chain[1..])?;
+        attestation.check_public_key(session)?;
+
+        if attestation.object_id != self.key_id
+            || attestation.origin != ObjectOrigin::Generated
+            || attestation.domains != self.domains
+            || attestation.capabilities != self.capabilities
+            || attestation.label != self.label
+        {
+            x509_fail!(
+                VerificationFailed,
+                "attestation for key 0x{:04x} doesn't match the requested key",
+                self.key_id
+            );
+        }
+
+        let object_info = get_object_info(session, self.key_id, ObjectType::AsymmetricKey)?;
+
+        if let Some(certificate_id) = self.certificate_id {
+            attestation.certificate().put(
+                session,
+                certificate_id,
+                self.label.clone(),
+                self.domains,
+                Capability::empty(),
+            )?;
+        }
+
+        Ok(ProvisionedKey {
+            object_info,
+            public_key: attestation.public_key,
+            attestation_chain,
+            certificate_id: self.certificate_id,
+        })
+    }
+}
+
+/// Key provisioned by a `KeyProvisioner`, e.g. for recording in an inventory
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvisionedKey {
+    /// Information about the key
+    pub object_info: ObjectInfo,
+
+    /// Public key
+    pub public_key: PublicKey,
+
+    /// Verified attestation certificate chain: the attestation certificate,
+    /// followed by its issuers up to the trusted root
+    pub attestation_chain: Vec<Certificate>,
+
+    /// ID of the stored attestation certificate, if any
+    pub certificate_id: Option<ObjectId>,
+}
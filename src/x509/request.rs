@@ -0,0 +1,112 @@
+//! PKCS#10 certificate signing requests for keys stored in the `YubiHSM2`
+
+use super::signature::{self, SignatureAlgorithm};
+use super::{signed, subject_alt_name, Name, X509Error};
+use commands::get_pubkey::{get_pubkey, PublicKey};
+use encoding::{der, oid, pem};
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use mockhsm::MockConnector;
+#[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+use Connector;
+use {ObjectId, Session};
+
+/// Builder for PKCS#10 certificate signing requests
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CertificateRequestBuilder {
+    /// Subject name
+    pub subject: Name,
+
+    /// DNS names of the subject (requested as subject alternative names)
+    pub dns_names: Vec<String>,
+}
+
+impl CertificateRequestBuilder {
+    /// Create a builder for a request with the given subject
+    pub fn new(subject: Name) -> Self {
+        Self {
+            subject,
+            dns_names: vec![],
+        }
+    }
+
+    /// Sign a request for a certificate for the given key ID (with that key)
+    #[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+    pub fn sign<C: Connector>(
+        &self,
+        session: &mut Session<C>,
+        key_id: ObjectId,
+    ) -> Result<CertificateRequest, X509Error> {
+        let public_key = get_pubkey(session, key_id)?;
+        let algorithm = SignatureAlgorithm::for_key(public_key.algorithm)?;
+        let request_info = self.certification_request_info(&public_key)?;
+        let signature = signature::sign(session, key_id, algorithm, &request_info)?;
+
+        Ok(CertificateRequest(signed(&request_info, algorithm, &signature)))
+    }
+
+    /// Sign a request for a certificate for the given key ID (with that key)
+    // NOTE: this version is enabled when we compile with MockHSM support
+    #[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+    pub fn sign(
+        &self,
+        session: &mut Session<MockConnector>,
+        key_id: ObjectId,
+    ) -> Result<CertificateRequest, X509Error> {
+        let public_key = get_pubkey(session, key_id)?;
+        let algorithm = SignatureAlgorithm::for_key(public_key.algorithm)?;
+        let request_info = self.certification_request_info(&public_key)?;
+        let signature = signature::sign(session, key_id, algorithm, &request_info)?;
+
+        Ok(CertificateRequest(signed(&request_info, algorithm, &signature)))
+    }
+
+    /// Encode the DER `CertificationRequestInfo` (i.e. the request without
+    /// its signature)
+    fn certification_request_info(&self, public_key: &PublicKey) -> Result<Vec<u8>, X509Error> {
+        let attributes = if self.dns_names.is_empty() {
+            vec![]
+        } else {
+            // Attribute ::= SEQUENCE { type, values SET OF Extensions }
+            der::sequence(&[
+                &der::object_identifier(oid::EXTENSION_REQUEST),
+                &der::set_of(&[&der::sequence(&[&subject_alt_name(&self.dns_names)?])]),
+            ])
+        };
+
+        Ok(der::sequence(&[
+            // version: v1
+            &der::integer(&[0]),
+            &self.subject.to_der()?,
+            &public_key.to_spki_der()?,
+            // attributes [0] IMPLICIT SET OF Attribute
+            &der::encode(der::CONTEXT_SPECIFIC | der::CONSTRUCTED, &attributes),
+        ]))
+    }
+}
+
+/// DER-encoded PKCS#10 certificate signing request
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CertificateRequest(Vec<u8>);
+
+impl CertificateRequest {
+    /// Borrow the DER encoding of this request
+    pub fn as_der(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Unwrap the DER encoding of this request
+    pub fn into_der(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Encode this request as PEM ("CERTIFICATE REQUEST")
+    pub fn to_pem(&self) -> String {
+        pem::encode("CERTIFICATE REQUEST", &self.0)
+    }
+}
+
+impl AsRef<[u8]> for CertificateRequest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
@@ -0,0 +1,126 @@
+//! Signature algorithms for certificates and certificate requests
+
+use super::X509Error;
+use commands::sign_ecdsa::sign_ecdsa;
+use commands::sign_eddsa::sign_ed25519;
+#[cfg(feature = "rsa")]
+use commands::sign_rsa_pkcs1v15::sign_rsa_pkcs1v15_sha256;
+use encoding::{der, oid};
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+use mockhsm::MockConnector;
+#[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+use Connector;
+use {AsymmetricAlgorithm, ECDSAAlgorithm, ObjectId, Session, SessionError};
+
+/// Algorithm used to sign a certificate or request, which depends on the
+/// algorithm of the signing key
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) enum SignatureAlgorithm {
+    /// ECDSA with the hash function matching the key's curve size
+    ECDSA(ECDSAAlgorithm),
+
+    /// Ed25519 (PureEdDSA, RFC 8410)
+    Ed25519,
+
+    /// RSASSA-PKCS1-v1_5 with SHA-256
+    #[cfg(feature = "rsa")]
+    RSA,
+}
+
+impl SignatureAlgorithm {
+    /// Get the signature algorithm for a signing key with the given algorithm
+    pub fn for_key(algorithm: AsymmetricAlgorithm) -> Result<Self, X509Error> {
+        Ok(match algorithm {
+            AsymmetricAlgorithm::EC_P256
+            | AsymmetricAlgorithm::EC_K256
+            | AsymmetricAlgorithm::EC_BP256 => {
+                SignatureAlgorithm::ECDSA(ECDSAAlgorithm::EC_ECDSA_SHA256)
+            }
+            AsymmetricAlgorithm::EC_P384 | AsymmetricAlgorithm::EC_BP384 => {
+                SignatureAlgorithm::ECDSA(ECDSAAlgorithm::EC_ECDSA_SHA384)
+            }
+            AsymmetricAlgorithm::EC_P521 | AsymmetricAlgorithm::EC_BP512 => {
+                SignatureAlgorithm::ECDSA(ECDSAAlgorithm::EC_ECDSA_SHA512)
+            }
+            AsymmetricAlgorithm::EC_ED25519 => SignatureAlgorithm::Ed25519,
+            #[cfg(feature = "rsa")]
+            AsymmetricAlgorithm::RSA2048
+            | AsymmetricAlgorithm::RSA3072
+            | AsymmetricAlgorithm::RSA4096 => SignatureAlgorithm::RSA,
+            #[allow(unreachable_patterns)]
+            other => x509_fail!(
+                UnsupportedAlgorithm,
+                "can't sign certificates with {:?} keys",
+                other
+            ),
+        })
+    }
+
+    /// Encode this algorithm as a DER `AlgorithmIdentifier` (RFC 5280)
+    pub fn to_der(self) -> Vec<u8> {
+        match self {
+            SignatureAlgorithm::ECDSA(algorithm) => {
+                let oid = match algorithm {
+                    ECDSAAlgorithm::EC_ECDSA_SHA256 => oid::ECDSA_WITH_SHA256,
+                    ECDSAAlgorithm::EC_ECDSA_SHA384 => oid::ECDSA_WITH_SHA384,
+                    ECDSAAlgorithm::EC_ECDSA_SHA512 => oid::ECDSA_WITH_SHA512,
+                };
+
+                der::sequence(&[&der::object_identifier(oid)])
+            }
+            SignatureAlgorithm::Ed25519 => der::sequence(&[&der::object_identifier(oid::ED25519)]),
+            #[cfg(feature = "rsa")]
+            SignatureAlgorithm::RSA => der::sequence(&[
+                &der::object_identifier(oid::SHA256_WITH_RSA_ENCRYPTION),
+                &der::null(),
+            ]),
+        }
+    }
+}
+
+/// Sign a DER-encoded `TBSCertificate` or `CertificationRequestInfo` with the
+/// given key ID
+#[cfg(any(feature = "doc", not(feature = "mockhsm")))]
+pub(super) fn sign<C: Connector>(
+    session: &mut Session<C>,
+    key_id: ObjectId,
+    algorithm: SignatureAlgorithm,
+    data: &[u8],
+) -> Result<Vec<u8>, SessionError> {
+    match algorithm {
+        SignatureAlgorithm::ECDSA(algorithm) => {
+            sign_ecdsa(session, key_id, algorithm, data).map(|signature| signature.into_vec())
+        }
+        SignatureAlgorithm::Ed25519 => {
+            sign_ed25519(session, key_id, data).map(|signature| signature.as_ref().to_vec())
+        }
+        #[cfg(feature = "rsa")]
+        SignatureAlgorithm::RSA => {
+            sign_rsa_pkcs1v15_sha256(session, key_id, data).map(|signature| signature.into_vec())
+        }
+    }
+}
+
+/// Sign a DER-encoded `TBSCertificate` or `CertificationRequestInfo` with the
+/// given key ID
+// NOTE: this version is enabled when we compile with MockHSM support
+#[cfg(all(feature = "mockhsm", not(feature = "doc")))]
+pub(super) fn sign(
+    session: &mut Session<MockConnector>,
+    key_id: ObjectId,
+    algorithm: SignatureAlgorithm,
+    data: &[u8],
+) -> Result<Vec<u8>, SessionError> {
+    match algorithm {
+        SignatureAlgorithm::ECDSA(algorithm) => {
+            sign_ecdsa(session, key_id, algorithm, data).map(|signature| signature.into_vec())
+        }
+        SignatureAlgorithm::Ed25519 => {
+            sign_ed25519(session, key_id, data).map(|signature| signature.as_ref().to_vec())
+        }
+        #[cfg(feature = "rsa")]
+        SignatureAlgorithm::RSA => {
+            sign_rsa_pkcs1v15_sha256(session, key_id, data).map(|signature| signature.into_vec())
+        }
+    }
+}
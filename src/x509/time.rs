@@ -0,0 +1,210 @@
+//! Encoding and decoding of certificate validity times
+
+#[cfg(feature = "attestation")]
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::X509Error;
+use encoding::der;
+#[cfg(feature = "attestation")]
+use encoding::der::Decoder;
+
+/// Number of seconds in a day
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Encode a time as a UTCTime (for years through 2049) or a GeneralizedTime
+/// (for later years), as required by RFC 5280
+pub(super) fn encode(time: SystemTime) -> Result<Vec<u8>, X509Error> {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| x509_err!(InvalidField, "times before 1970 are unsupported"))?
+        .as_secs();
+
+    let (year, month, day) = civil_from_days(seconds / SECONDS_PER_DAY);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+
+    let time_of_day = format!(
+        "{:02}{:02}{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    );
+
+    if year < 2050 {
+        let date = format!("{:02}{:02}{:02}", year % 100, month, day);
+        Ok(der::encode(der::UTC_TIME, (date + &time_of_day).as_bytes()))
+    } else if year < 10_000 {
+        let date = format!("{:04}{:02}{:02}", year, month, day);
+        Ok(der::encode(der::GENERALIZED_TIME, (date + &time_of_day).as_bytes()))
+    } else {
+        x509_fail!(InvalidField, "times after the year 9999 are unsupported");
+    }
+}
+
+/// Decode a UTCTime or GeneralizedTime, in the `YYMMDDHHMMSSZ` and
+/// `YYYYMMDDHHMMSSZ` forms required by RFC 5280
+#[cfg(feature = "attestation")]
+pub(super) fn decode(decoder: &mut Decoder) -> Result<SystemTime, X509Error> {
+    let (year, time) = if let Some(time) = decoder.optional(der::UTC_TIME)? {
+        if time.len() != 13 {
+            x509_fail!(ParseError, "malformed UTCTime");
+        }
+
+        // Two-digit years from 50 through 99 are in the 20th century
+        let year = digits(&time[..2])?;
+        (if year < 50 { 2000 + year } else { 1900 + year }, &time[2..])
+    } else {
+        let time = decoder.decode(der::GENERALIZED_TIME)?;
+
+        if time.len() != 15 {
+            x509_fail!(ParseError, "malformed GeneralizedTime");
+        }
+
+        (digits(&time[..4])?, &time[4..])
+    };
+
+    let month = digits(&time[0..2])?;
+    let day = digits(&time[2..4])?;
+    let hour = digits(&time[4..6])?;
+    let minute = digits(&time[6..8])?;
+    let second = digits(&time[8..10])?;
+
+    if time[10] != b'Z'
+        || month < 1
+        || month > 12
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        x509_fail!(ParseError, "invalid time: {}", String::from_utf8_lossy(time));
+    }
+
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY as i64
+        + hour * 3600
+        + minute * 60
+        + second;
+
+    if seconds >= 0 {
+        Ok(UNIX_EPOCH + Duration::from_secs(seconds as u64))
+    } else {
+        Ok(UNIX_EPOCH - Duration::from_secs(-seconds as u64))
+    }
+}
+
+/// Parse a string of ASCII decimal digits
+#[cfg(feature = "attestation")]
+fn digits(bytes: &[u8]) -> Result<i64, X509Error> {
+    bytes.iter().try_fold(0, |value, &byte| {
+        if byte.is_ascii_digit() {
+            Ok(value * 10 + i64::from(byte - b'0'))
+        } else {
+            Err(x509_err!(ParseError, "malformed time"))
+        }
+    })
+}
+
+/// Number of days in the given month of the given year
+#[cfg(feature = "attestation")]
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Convert a (year, month, day) date in the proleptic Gregorian calendar into
+/// a number of days since 1970-01-01 (i.e. the inverse of `civil_from_days`).
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+#[cfg(feature = "attestation")]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Shift the epoch to 0000-03-01, so leap days fall at the end of a year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Convert a number of days since 1970-01-01 into a (year, month, day) date
+/// in the proleptic Gregorian calendar.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Shift the epoch to 0000-03-01, so leap days fall at the end of a year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::encode;
+
+    #[test]
+    fn encode_utc_time() {
+        assert_eq!(encode(UNIX_EPOCH).unwrap(), b"\x17\x0d700101000000Z".to_vec());
+
+        // 2000-02-29 12:34:56 UTC
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(encode(leap_day).unwrap(), b"\x17\x0d000229123456Z".to_vec());
+    }
+
+    #[test]
+    fn encode_generalized_time() {
+        // 2050-01-01 00:00:00 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(2_524_608_000);
+        assert_eq!(encode(time).unwrap(), b"\x18\x0f20500101000000Z".to_vec());
+    }
+
+    #[cfg(feature = "attestation")]
+    #[test]
+    fn decode_test() {
+        use super::decode;
+        use encoding::der::Decoder;
+
+        for &seconds in &[0, 951_827_696, 2_524_608_000, 4_102_444_799] {
+            let time = UNIX_EPOCH + Duration::from_secs(seconds);
+            let encoded = encode(time).unwrap();
+            assert_eq!(decode(&mut Decoder::new(&encoded)).unwrap(), time);
+        }
+
+        // 1950-01-01 00:00:00 UTC
+        assert_eq!(
+            decode(&mut Decoder::new(b"\x17\x0d500101000000Z")).unwrap(),
+            UNIX_EPOCH - Duration::from_secs(631_152_000)
+        );
+
+        for invalid in &[
+            &b"\x17\x0b7001010000Z"[..],
+            b"\x17\x0d700101000000+",
+            b"\x17\x0d701301000000Z",
+            b"\x17\x0d010229000000Z",
+            b"\x18\x0f2000022912345aZ",
+        ] {
+            assert!(decode(&mut Decoder::new(invalid)).is_err());
+        }
+    }
+}
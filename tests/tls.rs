@@ -0,0 +1,268 @@
+//! Tests for connecting to yubihsm-connector over HTTPS, using a local TLS
+//! server which stands in for the connector.
+//!
+//! The certificates in `tests/support/tls` were generated with OpenSSL:
+//! `ca.pem` (`ca.der`) is a self-signed CA which issued the server
+//! certificate in `server.der` (for `localhost`, with its PKCS#8 key in
+//! `server-key.der`) and the client certificate in `client.der`, whose
+//! PKCS#12 archive `client.p12` uses the password `yubihsm`.
+
+#![cfg(feature = "tls")]
+
+extern crate rustls;
+extern crate yubihsm;
+
+use rustls::server::{AllowAnyAuthenticatedClient, WantsServerCert};
+use rustls::{
+    Certificate, ConfigBuilder, PrivateKey, RootCertStore, ServerConfig, ServerConnection,
+    StreamOwned,
+};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+use yubihsm::{Connector, HttpConfig, HttpConnector, TlsConfig};
+
+/// Password for the PKCS#12 archive in `tests/support/tls`
+const PKCS12_PASSWORD: &str = "yubihsm";
+
+/// SHA-256 fingerprint of the test server's certificate
+const SERVER_CERT_FINGERPRINT: &str =
+    "18:C2:70:9C:B9:87:B1:2B:B7:52:AB:83:DB:06:96:F2:24:BE:4C:62:0F:07:73:AB:05:5D:EA:90:FC:7A:96:94";
+
+/// Response to `GET /connector/status`
+const STATUS_RESPONSE: &str = "HTTP/1.1 200 OK\r\n\
+                               Content-Length: 43\r\n\r\n\
+                               status=OK\nserial=*\nversion=1.0.2\npid=12345\n";
+
+/// Path to a file in `tests/support/tls`
+fn support_file(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "support", "tls", name]
+        .iter()
+        .collect()
+}
+
+/// Read a file in `tests/support/tls`
+fn read_support_file(name: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    File::open(support_file(name))
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    bytes
+}
+
+/// Spawn a TLS server which responds to every request with `STATUS_RESPONSE`,
+/// returning the port it's listening on
+fn spawn_tls_server() -> u16 {
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth();
+
+    spawn_server(config, None)
+}
+
+/// Spawn a TLS server which requires a client certificate issued by the test
+/// CA, returning the port it's listening on along with a channel receiving
+/// the client certificate presented on each connection
+fn spawn_client_auth_tls_server() -> (u16, Receiver<Vec<u8>>) {
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(&Certificate(read_support_file("ca.der")))
+        .unwrap();
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed());
+
+    let (sender, receiver) = mpsc::channel();
+    (spawn_server(config, Some(sender)), receiver)
+}
+
+/// Spawn a server with the given configuration (less its certificate),
+/// reporting client certificates to `client_certs` if given
+fn spawn_server(
+    config: ConfigBuilder<ServerConfig, WantsServerCert>,
+    client_certs: Option<Sender<Vec<u8>>>,
+) -> u16 {
+    let config = Arc::new(
+        config
+            .with_single_cert(
+                vec![Certificate(read_support_file("server.der"))],
+                PrivateKey(read_support_file("server-key.der")),
+            )
+            .unwrap(),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+        for socket in listener.incoming() {
+            let config = Arc::clone(&config);
+            let client_certs = client_certs.clone();
+
+            thread::spawn(move || {
+                let connection = ServerConnection::new(config).unwrap();
+                let mut stream = StreamOwned::new(connection, socket.unwrap());
+
+                while stream.conn.is_handshaking() {
+                    if stream.conn.complete_io(&mut stream.sock).is_err() {
+                        return;
+                    }
+                }
+
+                if let Some(sender) = client_certs {
+                    let cert = stream
+                        .conn
+                        .peer_certificates()
+                        .and_then(|certs| certs.first())
+                        .map(|cert| cert.0.clone())
+                        .unwrap_or_default();
+
+                    if sender.send(cert).is_err() {
+                        return;
+                    }
+                }
+
+                let mut request = vec![];
+                let mut buffer = [0u8; 1024];
+
+                // Requests to /connector/status have no body, so answer
+                // each time we see the end of the headers
+                while let Ok(nbytes) = stream.read(&mut buffer) {
+                    if nbytes == 0 {
+                        return;
+                    }
+
+                    request.extend_from_slice(&buffer[..nbytes]);
+
+                    if request.ends_with(b"\r\n\r\n") {
+                        request.clear();
+
+                        if stream.write_all(STATUS_RESPONSE.as_bytes()).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    port
+}
+
+/// Create an `HttpConfig` for the test server with the given TLS options
+fn https_config(port: u16, tls: TlsConfig) -> HttpConfig {
+    HttpConfig {
+        addr: "localhost".to_owned(),
+        port,
+        tls: Some(tls),
+        ..Default::default()
+    }
+}
+
+/// `TlsConfig` which trusts only the test CA
+fn test_ca_config() -> TlsConfig {
+    TlsConfig {
+        ca_certs: vec![support_file("ca.pem")],
+        use_system_roots: false,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn https_status_test() {
+    let config = https_config(spawn_tls_server(), test_ca_config());
+    assert!(config.to_string().starts_with("https://"));
+
+    let connector = HttpConnector::open(config).unwrap_or_else(|err| panic!("{}", err));
+
+    // Send several requests to exercise connection reuse
+    for _ in 0..3 {
+        let status = connector.status().unwrap();
+        assert_eq!(status.message, "OK");
+        assert_eq!(status.pid, 12345);
+    }
+}
+
+#[test]
+fn https_untrusted_cert_test() {
+    let tls = TlsConfig {
+        use_system_roots: false,
+        ..Default::default()
+    };
+
+    assert!(HttpConnector::open(https_config(spawn_tls_server(), tls)).is_err());
+}
+
+#[test]
+fn https_domain_mismatch_test() {
+    let tls = TlsConfig {
+        domain: Some("connector.example.com".to_owned()),
+        ..test_ca_config()
+    };
+
+    assert!(HttpConnector::open(https_config(spawn_tls_server(), tls)).is_err());
+}
+
+#[test]
+fn https_pinned_cert_test() {
+    let tls = TlsConfig {
+        pinned_certs: vec![SERVER_CERT_FINGERPRINT.to_owned()],
+        ..test_ca_config()
+    };
+
+    let connector = HttpConnector::open(https_config(spawn_tls_server(), tls))
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(connector.status().unwrap().message, "OK");
+}
+
+#[test]
+fn https_pinned_cert_mismatch_test() {
+    let tls = TlsConfig {
+        pinned_certs: vec![SERVER_CERT_FINGERPRINT.replace("18:C2", "00:00")],
+        ..test_ca_config()
+    };
+
+    assert!(HttpConnector::open(https_config(spawn_tls_server(), tls)).is_err());
+}
+
+#[test]
+fn https_client_identity_test() {
+    let tls = TlsConfig {
+        client_identity: Some(support_file("client.p12")),
+        client_identity_password: PKCS12_PASSWORD.to_owned(),
+        ..test_ca_config()
+    };
+
+    let (port, client_certs) = spawn_client_auth_tls_server();
+
+    let connector =
+        HttpConnector::open(https_config(port, tls)).unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(connector.status().unwrap().message, "OK");
+    assert_eq!(
+        client_certs.recv().unwrap(),
+        read_support_file("client.der")
+    );
+}
+
+#[test]
+fn https_missing_client_identity_test() {
+    let (port, _client_certs) = spawn_client_auth_tls_server();
+
+    // With TLS 1.3 the server may only reject the (empty) client certificate
+    // after the client has finished its side of the handshake
+    let result = HttpConnector::open(https_config(port, test_ca_config())).and_then(|c| c.status());
+
+    assert!(result.is_err());
+}